@@ -0,0 +1,405 @@
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::device::{self, DeviceState, ThresholdLines};
+
+/// Name of the thresholds file in the app's config directory.
+const CONFIG_FILE_NAME: &str = "thresholds.toml";
+
+/// Ranges the thresholds can be set to, shared by the settings sliders.
+pub const TEMPERATURE_RANGE: RangeInclusive<u32> = 40..=110;
+pub const MEMORY_PERCENT_RANGE: RangeInclusive<u32> = 10..=100;
+pub const POWER_RANGE: RangeInclusive<u32> = 10..=1000;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{err}"),
+            ConfigError::Parse(err) => write!(f, "{err}"),
+            ConfigError::Serialize(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+/// Where the thresholds are saved to and loaded from, e.g. `~/.config/nvsmi-gui/thresholds.toml`
+/// on Linux. `None` if the platform has no config directory.
+pub fn config_path() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "nvsmi-gui")
+        .map(|dirs| dirs.config_dir().join(CONFIG_FILE_NAME))
+}
+
+/// The sensor the temperature threshold applies to, which is also the temperature shown in
+/// the window title. NVML doesn't expose the hotspot sensor, so it can't be picked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureSensor {
+    #[default]
+    Core,
+    /// The memory junction, which runs hotter than the core on GDDR6X and HBM cards.
+    Memory,
+}
+
+impl TemperatureSensor {
+    pub const ALL: [TemperatureSensor; 2] = [TemperatureSensor::Core, TemperatureSensor::Memory];
+
+    /// `None` if the device doesn't have the sensor.
+    pub fn read(self, device_state: &DeviceState) -> Option<u32> {
+        match self {
+            TemperatureSensor::Core => device_state.temperature,
+            TemperatureSensor::Memory => device_state.memory_temperature,
+        }
+    }
+}
+
+impl Display for TemperatureSensor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemperatureSensor::Core => write!(f, "GPU core"),
+            TemperatureSensor::Memory => write!(f, "Memory junction"),
+        }
+    }
+}
+
+/// User configurable limits above which a reading is highlighted as a warning.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thresholds {
+    pub temperature_enabled: bool,
+    /// Temperature of `temperature_sensor` in °C.
+    pub temperature: u32,
+    pub temperature_sensor: TemperatureSensor,
+    pub memory_enabled: bool,
+    /// Used memory as a percentage of the total.
+    pub memory_percent: u32,
+    pub power_enabled: bool,
+    /// Power usage in W.
+    pub power: u32,
+    /// Show a notification whenever a threshold is crossed.
+    pub notify: bool,
+    /// Play a sound whenever a threshold is crossed. Only used with the `audio` feature.
+    pub sound: bool,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            temperature_enabled: true,
+            temperature: 85,
+            temperature_sensor: TemperatureSensor::default(),
+            memory_enabled: true,
+            memory_percent: 90,
+            power_enabled: false,
+            power: 300,
+            notify: false,
+            sound: false,
+        }
+    }
+}
+
+impl Thresholds {
+    pub fn temperature_exceeded(&self, device_state: &DeviceState) -> bool {
+        self.temperature_enabled
+            && self
+                .temperature_sensor
+                .read(device_state)
+                .is_some_and(|temperature| temperature >= self.temperature)
+    }
+
+    /// The memory threshold in bytes for a device with `total` bytes of memory.
+    pub fn memory_threshold(&self, total: u64) -> Option<u64> {
+        self.memory_enabled
+            .then(|| total / 100 * self.memory_percent as u64)
+    }
+
+    pub fn memory_exceeded(&self, device_state: &DeviceState) -> bool {
+        device_state.mem_info.as_ref().is_some_and(|mem_info| {
+            self.memory_threshold(mem_info.total)
+                .is_some_and(|threshold| mem_info.used >= threshold)
+        })
+    }
+
+    pub fn power_exceeded(&self, device_state: &DeviceState) -> bool {
+        self.power_enabled
+            && device::power_watts(device_state).is_some_and(|power| power >= self.power as f64)
+    }
+
+    /// The enabled thresholds in the units of the plots, for a device with `total_mib` of
+    /// memory.
+    pub fn threshold_lines(&self, total_mib: u64) -> ThresholdLines {
+        ThresholdLines {
+            temperature: self.temperature_enabled.then_some(self.temperature as f64),
+            memory: self
+                .memory_enabled
+                .then(|| total_mib as f64 * self.memory_percent as f64 / 100.0),
+            power: self.power_enabled.then_some(self.power as f64),
+        }
+    }
+
+    /// Takes over thresholds dragged on the plots, rounded to whole units and clamped to the
+    /// ranges of the sliders.
+    pub fn apply_threshold_lines(&mut self, lines: ThresholdLines, total_mib: u64) {
+        if let Some(temperature) = lines.temperature {
+            self.temperature = clamp_to(temperature, &TEMPERATURE_RANGE);
+        }
+        if let (Some(memory), true) = (lines.memory, total_mib > 0) {
+            self.memory_percent =
+                clamp_to(memory / total_mib as f64 * 100.0, &MEMORY_PERCENT_RANGE);
+        }
+        if let Some(power) = lines.power {
+            self.power = clamp_to(power, &POWER_RANGE);
+        }
+    }
+
+    /// Reads thresholds from a TOML file. Missing keys keep their defaults and values outside
+    /// the ranges of the sliders are clamped to them.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let mut thresholds: Self =
+            toml::from_str(&std::fs::read_to_string(path)?).map_err(ConfigError::Parse)?;
+        thresholds.temperature = thresholds
+            .temperature
+            .clamp(*TEMPERATURE_RANGE.start(), *TEMPERATURE_RANGE.end());
+        thresholds.memory_percent = thresholds
+            .memory_percent
+            .clamp(*MEMORY_PERCENT_RANGE.start(), *MEMORY_PERCENT_RANGE.end());
+        thresholds.power = thresholds
+            .power
+            .clamp(*POWER_RANGE.start(), *POWER_RANGE.end());
+        Ok(thresholds)
+    }
+
+    /// Writes the thresholds to a TOML file, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(ConfigError::Serialize)?;
+        Ok(std::fs::write(path, contents)?)
+    }
+}
+
+/// Remembers which thresholds are currently exceeded, so that we only notify when a reading
+/// crosses a threshold rather than on every poll.
+#[derive(Debug, Clone, Default)]
+pub struct AlertMonitor {
+    temperature_exceeded: bool,
+    memory_exceeded: bool,
+    power_exceeded: bool,
+}
+
+impl AlertMonitor {
+    /// Returns a message for each threshold that was crossed since the last update.
+    pub fn update(&mut self, thresholds: &Thresholds, device_state: &DeviceState) -> Vec<String> {
+        let mut messages = Vec::new();
+
+        let temperature_exceeded = thresholds.temperature_exceeded(device_state);
+        if let (true, false, Some(temperature)) = (
+            temperature_exceeded,
+            self.temperature_exceeded,
+            thresholds.temperature_sensor.read(device_state),
+        ) {
+            let sensor = match thresholds.temperature_sensor {
+                TemperatureSensor::Core => "",
+                TemperatureSensor::Memory => "memory ",
+            };
+            messages.push(format!(
+                "{}: {sensor}temperature reached {temperature}°C",
+                device_state.name
+            ));
+        }
+        self.temperature_exceeded = temperature_exceeded;
+
+        let memory_exceeded = thresholds.memory_exceeded(device_state);
+        if memory_exceeded && !self.memory_exceeded {
+            messages.push(format!(
+                "{}: memory usage above {}%",
+                device_state.name, thresholds.memory_percent
+            ));
+        }
+        self.memory_exceeded = memory_exceeded;
+
+        let power_exceeded = thresholds.power_exceeded(device_state);
+        if power_exceeded && !self.power_exceeded {
+            messages.push(format!(
+                "{}: power usage reached {} W",
+                device_state.name, thresholds.power
+            ));
+        }
+        self.power_exceeded = power_exceeded;
+
+        messages
+    }
+}
+
+/// Rounds `value` to a whole number within `range`.
+fn clamp_to(value: f64, range: &RangeInclusive<u32>) -> u32 {
+    value
+        .round()
+        .clamp(*range.start() as f64, *range.end() as f64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device_state(temperature: u32, used: u64, total: u64) -> DeviceState {
+        DeviceState::mock()
+            .with_temperature(temperature)
+            .with_memory(used, total)
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let thresholds = Thresholds::default();
+        assert!(!thresholds.memory_exceeded(&device_state(50, 899, 1000)));
+        assert!(thresholds.memory_exceeded(&device_state(50, 900, 1000)));
+        assert!(thresholds.temperature_exceeded(&device_state(85, 0, 1000)));
+
+        let disabled = Thresholds {
+            temperature_enabled: false,
+            memory_enabled: false,
+            ..Thresholds::default()
+        };
+        assert!(!disabled.memory_exceeded(&device_state(100, 1000, 1000)));
+        assert!(!disabled.temperature_exceeded(&device_state(100, 1000, 1000)));
+
+        let power = Thresholds {
+            power_enabled: true,
+            power: 200,
+            ..Thresholds::default()
+        };
+        let mut drawing = device_state(50, 0, 1000);
+        drawing.power_usage = Some(199_000);
+        assert!(!power.power_exceeded(&drawing));
+        drawing.power_usage = Some(200_000);
+        assert!(power.power_exceeded(&drawing));
+    }
+
+    #[test]
+    fn test_memory_temperature_sensor() {
+        let thresholds = Thresholds {
+            temperature_sensor: TemperatureSensor::Memory,
+            ..Thresholds::default()
+        };
+        let mut hot_memory = device_state(60, 0, 1000);
+        assert!(!thresholds.temperature_exceeded(&hot_memory));
+        hot_memory.memory_temperature = Some(96);
+        assert!(thresholds.temperature_exceeded(&hot_memory));
+        assert!(!Thresholds::default().temperature_exceeded(&hot_memory));
+
+        assert_eq!(
+            AlertMonitor::default().update(&thresholds, &hot_memory),
+            ["Test GPU: memory temperature reached 96°C"]
+        );
+        let partial: Thresholds = toml::from_str(r#"temperature_sensor = "memory""#).unwrap();
+        assert_eq!(partial.temperature_sensor, TemperatureSensor::Memory);
+    }
+
+    #[test]
+    fn test_threshold_lines_round_trip() {
+        let mut thresholds = Thresholds::default();
+        let lines = thresholds.threshold_lines(8000);
+        assert_eq!(lines.temperature, Some(85.0));
+        assert_eq!(lines.memory, Some(7200.0));
+        assert_eq!(lines.power, None);
+
+        thresholds.apply_threshold_lines(
+            ThresholdLines {
+                temperature: Some(77.6),
+                memory: Some(4100.0),
+                power: None,
+            },
+            8000,
+        );
+        assert_eq!(thresholds.temperature, 78);
+        assert_eq!(thresholds.memory_percent, 51);
+        assert_eq!(thresholds.power, 300);
+
+        // Lines dragged past the ends of the sliders stop there
+        thresholds.apply_threshold_lines(
+            ThresholdLines {
+                temperature: Some(250.0),
+                memory: Some(-100.0),
+                power: Some(1e12),
+            },
+            8000,
+        );
+        assert_eq!(thresholds.temperature, *TEMPERATURE_RANGE.end());
+        assert_eq!(thresholds.memory_percent, *MEMORY_PERCENT_RANGE.start());
+        assert_eq!(thresholds.power, *POWER_RANGE.end());
+    }
+
+    #[test]
+    fn test_thresholds_file_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("nvsmi-gui-thresholds-{}.toml", std::process::id()));
+        let thresholds = Thresholds {
+            temperature: 75,
+            power_enabled: true,
+            power: 250,
+            ..Thresholds::default()
+        };
+        thresholds.save(&path).unwrap();
+        let loaded = Thresholds::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.temperature, 75);
+        assert!(loaded.power_enabled);
+        assert_eq!(loaded.power, 250);
+
+        // Keys that aren't in the file keep their defaults
+        let partial: Thresholds = toml::from_str("temperature = 70").unwrap();
+        assert_eq!(partial.temperature, 70);
+        assert_eq!(partial.memory_percent, Thresholds::default().memory_percent);
+
+        // Values outside the ranges of the sliders are clamped on load
+        std::fs::write(&path, "temperature = 5000\nmemory_percent = 0\npower = 0").unwrap();
+        let loaded = Thresholds::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.temperature, *TEMPERATURE_RANGE.end());
+        assert_eq!(loaded.memory_percent, *MEMORY_PERCENT_RANGE.start());
+        assert_eq!(loaded.power, *POWER_RANGE.start());
+    }
+
+    #[test]
+    fn test_alert_monitor_only_reports_crossings() {
+        let thresholds = Thresholds::default();
+        let mut monitor = AlertMonitor::default();
+
+        assert!(monitor
+            .update(&thresholds, &device_state(50, 100, 1000))
+            .is_empty());
+        assert_eq!(
+            monitor
+                .update(&thresholds, &device_state(50, 950, 1000))
+                .len(),
+            1
+        );
+        assert!(monitor
+            .update(&thresholds, &device_state(50, 960, 1000))
+            .is_empty());
+        assert!(monitor
+            .update(&thresholds, &device_state(50, 100, 1000))
+            .is_empty());
+        assert_eq!(
+            monitor
+                .update(&thresholds, &device_state(90, 950, 1000))
+                .len(),
+            2
+        );
+    }
+}
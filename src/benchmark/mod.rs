@@ -0,0 +1,238 @@
+use std::io;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::device::{self, DeviceState};
+use crate::source::GpuSource;
+use crate::units::{self, UnitSystem};
+
+/// How often the GPU is polled while the command runs.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Peak and average readings of a GPU over the lifetime of a command.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    pub samples: u32,
+    pub failed_polls: u32,
+    pub peak_temperature: Option<u32>,
+    /// In W, here and below.
+    pub peak_power: Option<f64>,
+    power_sum: f64,
+    power_samples: u32,
+    /// Used memory in bytes.
+    pub peak_memory: Option<u64>,
+    /// The energy counter at the first and latest poll that had it, in millijoules.
+    energy_counter: Option<(u64, u64)>,
+}
+
+impl Summary {
+    pub fn push(&mut self, device_state: &DeviceState) {
+        self.samples += 1;
+        if let Some(temperature) = device_state.temperature {
+            self.peak_temperature = self.peak_temperature.max(Some(temperature));
+        }
+        if let Some(power) = device::power_watts(device_state) {
+            self.peak_power = Some(self.peak_power.map_or(power, |peak| peak.max(power)));
+            self.power_sum += power;
+            self.power_samples += 1;
+        }
+        self.peak_memory = self.peak_memory.max(device::memory_used(device_state));
+        if let Some(counter) = device_state.total_energy {
+            let first = self.energy_counter.map_or(counter, |(first, _)| first);
+            self.energy_counter = Some((first, counter));
+        }
+    }
+
+    /// The mean of the power readings, `None` if the device doesn't report its power.
+    pub fn average_power(&self) -> Option<f64> {
+        (self.power_samples > 0).then(|| self.power_sum / self.power_samples as f64)
+    }
+
+    /// Energy used between the first and latest poll in Wh, `None` if the device has no
+    /// energy counter.
+    pub fn energy_wh(&self) -> Option<f64> {
+        self.energy_counter
+            .map(|(first, latest)| device::energy_wh(first, latest))
+    }
+
+    /// A plain text report, one reading per line.
+    pub fn report(&self, command: &str, device_name: &str, duration: Duration) -> String {
+        let na = || String::from("N/A");
+        let number_format = units::number_format();
+        [
+            format!("Command: {command}"),
+            format!("GPU: {device_name}"),
+            format!(
+                "Duration: {} s",
+                number_format.format(duration.as_secs_f64(), 1)
+            ),
+            format!("Samples: {} ({} failed)", self.samples, self.failed_polls),
+            format!(
+                "Peak temperature: {}",
+                device::format_metric(self.peak_temperature, "°C")
+            ),
+            format!(
+                "Peak power: {}",
+                self.peak_power.map_or_else(na, device::format_watts)
+            ),
+            format!(
+                "Average power: {}",
+                self.average_power().map_or_else(na, device::format_watts)
+            ),
+            format!(
+                "Peak memory: {}",
+                self.peak_memory
+                    .map_or_else(na, |bytes| units::format_bytes(
+                        bytes,
+                        UnitSystem::default()
+                    ))
+            ),
+            format!(
+                "Energy: {}",
+                self.energy_wh().map_or_else(na, |energy| format!(
+                    "{} Wh",
+                    number_format.format(energy, 3)
+                ))
+            ),
+        ]
+        .join("\n")
+            + "\n"
+    }
+}
+
+/// Runs `command` through the shell, polls `device_index` until it exits and writes the
+/// summary to `output`, or to stdout if `None`. Returns the command's exit status.
+pub fn run(
+    source: &dyn GpuSource,
+    device_index: usize,
+    command: &str,
+    output: Option<&Path>,
+) -> io::Result<ExitStatus> {
+    let device_name = source
+        .device_names()
+        .get(device_index)
+        .cloned()
+        .unwrap_or_default();
+    let mut summary = Summary::default();
+    let started_at = Instant::now();
+    let mut child = KillOnDrop(shell_command(command).spawn()?);
+    let status = loop {
+        match source.poll_device(device_index) {
+            Ok(system_state) => summary.push(&system_state.device_state),
+            Err(err) => {
+                log::warn!("Failed to poll device {device_index}: {err}");
+                summary.failed_polls += 1;
+            }
+        }
+        if let Some(status) = child.0.try_wait()? {
+            break status;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    let report = summary.report(command, &device_name, started_at.elapsed());
+    match output {
+        Some(path) => std::fs::write(path, report)?,
+        None => print!("{report}"),
+    }
+    Ok(status)
+}
+
+/// Kills and reaps the workload unless it already exited, so returning early with an error
+/// doesn't leave it running.
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        if !matches!(self.0.try_wait(), Ok(Some(_))) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::source::MockSource;
+
+    fn device_state(temperature: u32, power_mw: u32, used: u64, energy: u64) -> DeviceState {
+        DeviceState::mock()
+            .with_temperature(temperature)
+            .with_power(power_mw)
+            .with_memory(used, 8000)
+            .with_energy(energy)
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut summary = Summary::default();
+        assert_eq!(summary.average_power(), None);
+        assert_eq!(summary.energy_wh(), None);
+
+        summary.push(&device_state(60, 100_000, 2000, 1_000_000));
+        summary.push(&device_state(75, 300_000, 6000, 4_600_000));
+        summary.push(&device_state(70, 200_000, 4000, 8_200_000));
+        assert_eq!(summary.samples, 3);
+        assert_eq!(summary.peak_temperature, Some(75));
+        assert_eq!(summary.peak_power, Some(300.0));
+        assert_eq!(summary.average_power(), Some(200.0));
+        assert_eq!(summary.peak_memory, Some(6000));
+        assert_eq!(summary.energy_wh(), Some(2.0));
+    }
+
+    #[test]
+    fn test_report() {
+        let report = Summary::default().report("true", "Test GPU", Duration::from_millis(1300));
+        assert_eq!(
+            report,
+            "Command: true\nGPU: Test GPU\nDuration: 1.3 s\nSamples: 0 (0 failed)\n\
+             Peak temperature: N/A\nPeak power: N/A\nAverage power: N/A\nPeak memory: N/A\n\
+             Energy: N/A\n"
+        );
+    }
+
+    #[test]
+    fn test_run_returns_the_exit_status() {
+        let source = MockSource::new(vec![None]);
+        let path =
+            std::env::temp_dir().join(format!("nvsmi-gui-benchmark-{}.txt", std::process::id()));
+        let status = run(&source, 0, "exit 3", Some(&path)).unwrap();
+        let report = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert!(report.starts_with("Command: exit 3\nGPU: Mock GPU\n"));
+        assert!(report.contains("failed)\nPeak temperature: N/A\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_workload_is_killed_on_early_return() {
+        let child = KillOnDrop(shell_command("sleep 60").spawn().unwrap());
+        let pid = child.0.id().to_string();
+        drop(child);
+        // Signal 0 only checks whether the process is still there
+        let alive = Command::new("kill")
+            .args(["-0", &pid])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(!alive.success());
+    }
+}
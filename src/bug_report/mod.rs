@@ -0,0 +1,70 @@
+use crate::device::{self, DeviceState};
+
+/// The environment details asked for when filing an issue, copied by the "Copy diagnostics"
+/// button.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    /// Name of the backend the readings come from.
+    pub backend: String,
+    pub nvml_version: Option<String>,
+    pub device_uuid: Option<String>,
+    pub device_state: Option<DeviceState>,
+}
+
+impl Diagnostics {
+    /// Formats the details as a fenced block, ready to paste into a GitHub issue.
+    pub fn report(&self) -> String {
+        let unknown = || String::from("unknown");
+        let device_state = self.device_state.as_ref();
+        let lines = [
+            format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            format!("OS: {} ({})", std::env::consts::OS, std::env::consts::ARCH),
+            format!("Backend: {}", self.backend),
+            format!(
+                "NVML version: {}",
+                self.nvml_version.clone().unwrap_or_else(unknown)
+            ),
+            format!(
+                "Driver version: {}",
+                device_state.map_or_else(unknown, |state| state.driver_version.clone())
+            ),
+            format!(
+                "CUDA version: {}",
+                device_state
+                    .and_then(|state| state.cuda_driver_version.as_ref())
+                    .map_or_else(unknown, ToString::to_string)
+            ),
+            format!(
+                "Device: {}",
+                device_state.map_or_else(unknown, |state| state.name.clone())
+            ),
+            format!("UUID: {}", self.device_uuid.clone().unwrap_or_else(unknown)),
+            format!(
+                "Compute capability: {}",
+                device_state
+                    .and_then(device::format_compute_capability)
+                    .unwrap_or_else(unknown)
+            ),
+        ];
+        format!("```\n{}\n```\n", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_marks_missing_details() {
+        let report = Diagnostics {
+            backend: String::from("nvidia-smi"),
+            ..Diagnostics::default()
+        }
+        .report();
+        assert!(report.starts_with("```\nnvsmi-gui "));
+        assert!(report.ends_with("```\n"));
+        assert!(report.contains("\nBackend: nvidia-smi\n"));
+        assert!(report.contains("\nNVML version: unknown\n"));
+        assert!(report.contains("\nUUID: unknown\n"));
+    }
+}
@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use eframe::egui::{vec2, ColorImage, Pos2, Rect};
+
+use crate::device::PlotKind;
+
+/// Which part of the plots to save when the screenshot arrives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureTarget {
+    AllPlots,
+    Plot(PlotKind),
+}
+
+impl CaptureTarget {
+    /// The screen area to capture, given where each plot was drawn.
+    pub fn rect(&self, plot_rects: &[(PlotKind, Rect)]) -> Option<Rect> {
+        plot_rects
+            .iter()
+            .filter(|(kind, _)| match self {
+                CaptureTarget::AllPlots => true,
+                CaptureTarget::Plot(target) => kind == target,
+            })
+            .map(|&(_, rect)| rect)
+            .reduce(|a, b| a.union(b))
+    }
+
+    pub fn file_name_part(&self) -> String {
+        match self {
+            CaptureTarget::AllPlots => String::from("plots"),
+            CaptureTarget::Plot(kind) => kind.to_string().to_lowercase().replace(' ', "-"),
+        }
+    }
+}
+
+/// Crops a screenshot to `rect`, which is given in points. Parts of `rect` that lie outside
+/// of the screenshot are cut off.
+pub fn crop(screenshot: &ColorImage, rect: Rect, pixels_per_point: f32) -> ColorImage {
+    let [width, height] = screenshot.size;
+    let bounds = Rect::from_min_size(
+        Pos2::ZERO,
+        vec2(width as f32, height as f32) / pixels_per_point,
+    );
+    screenshot.region(&rect.intersect(bounds), Some(pixels_per_point))
+}
+
+pub fn save_png(path: &Path, image: &ColorImage) -> Result<(), image::ImageError> {
+    let [width, height] = image.size;
+    let pixels: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|color| color.to_array())
+        .collect();
+    image::save_buffer_with_format(
+        path,
+        &pixels,
+        width as u32,
+        height as u32,
+        image::ExtendedColorType::Rgba8,
+        image::ImageFormat::Png,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use eframe::egui::{pos2, Color32};
+
+    #[test]
+    fn test_crop_clamps_to_screenshot() {
+        let screenshot = ColorImage::new([100, 50], Color32::WHITE);
+        let cropped = crop(
+            &screenshot,
+            Rect::from_min_max(pos2(10.0, 10.0), pos2(80.0, 40.0)),
+            2.0,
+        );
+        assert_eq!(cropped.size, [80, 30]);
+    }
+
+    #[test]
+    fn test_capture_target_rect() {
+        let temperature = Rect::from_min_max(pos2(0.0, 0.0), pos2(10.0, 10.0));
+        let power = Rect::from_min_max(pos2(0.0, 20.0), pos2(10.0, 30.0));
+        let plot_rects = [
+            (PlotKind::Temperature, temperature),
+            (PlotKind::Power, power),
+        ];
+
+        assert_eq!(
+            CaptureTarget::Plot(PlotKind::Power).rect(&plot_rects),
+            Some(power)
+        );
+        assert_eq!(
+            CaptureTarget::AllPlots.rect(&plot_rects),
+            Some(Rect::from_min_max(pos2(0.0, 0.0), pos2(10.0, 30.0)))
+        );
+        assert_eq!(
+            CaptureTarget::Plot(PlotKind::Memory).rect(&plot_rects),
+            None
+        );
+    }
+}
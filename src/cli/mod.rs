@@ -0,0 +1,201 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use crate::settings::Profile;
+
+pub const USAGE: &str = "\
+Usage: nvsmi-gui [OPTIONS]
+
+Options:
+  --device <DEVICE>  GPU to show on startup, given as an index, a UUID (GPU-...) or a
+                     PCI bus id (0000:01:00.0)
+  --watch-pid <PID>  Open the Processes tab showing just this process and plot its memory,
+                     waiting for it to use the GPU if it doesn't yet
+  --profile <PROFILE>
+                     Layout of the Devices tab, desktop or server (compute only)
+  --run <COMMAND>    Instead of opening the window, run COMMAND through the shell, watch the
+                     GPU until it exits and print the peak temperature, peak and average
+                     power, peak memory and energy used
+  --output <FILE>    With --run, write the summary to FILE instead of stdout
+  --read-only        Disable killing processes, changing the power limit and resetting the
+                     GPU, regardless of the settings
+  --allow-multiple   Start even if nvsmi-gui is already running, instead of showing the
+                     running instance's window
+  -h, --help         Print this help
+";
+
+/// A GPU given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSelector {
+    Index(u32),
+    Uuid(String),
+    PciBusId(String),
+}
+
+impl DeviceSelector {
+    pub fn parse(value: &str) -> Self {
+        if let Ok(index) = value.parse() {
+            DeviceSelector::Index(index)
+        } else if value.contains(':') {
+            DeviceSelector::PciBusId(value.to_string())
+        } else {
+            DeviceSelector::Uuid(value.to_string())
+        }
+    }
+}
+
+impl Display for DeviceSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceSelector::Index(index) => write!(f, "index {index}"),
+            DeviceSelector::Uuid(uuid) => write!(f, "UUID {uuid}"),
+            DeviceSelector::PciBusId(pci_bus_id) => write!(f, "PCI bus id {pci_bus_id}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Args {
+    pub device: Option<DeviceSelector>,
+    pub watch_pid: Option<u32>,
+    pub profile: Option<Profile>,
+    /// The command to benchmark, in which case no window is opened.
+    pub run: Option<String>,
+    pub output: Option<PathBuf>,
+    pub read_only: bool,
+    pub allow_multiple: bool,
+    pub help: bool,
+}
+
+impl Args {
+    /// Parses the arguments, not including the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            match flag.as_str() {
+                "--device" => {
+                    let value = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| String::from("--device needs a value"))?;
+                    parsed.device = Some(DeviceSelector::parse(&value));
+                }
+                "--watch-pid" => {
+                    let value = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| String::from("--watch-pid needs a value"))?;
+                    parsed.watch_pid = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid pid for --watch-pid: {value}"))?,
+                    );
+                }
+                "--profile" => {
+                    let value = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| String::from("--profile needs a value"))?;
+                    parsed.profile = Some(
+                        Profile::parse(&value)
+                            .ok_or_else(|| format!("unknown profile: {value}"))?,
+                    );
+                }
+                "--run" => {
+                    let value = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| String::from("--run needs a command"))?;
+                    parsed.run = Some(value);
+                }
+                "--output" => {
+                    let value = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| String::from("--output needs a value"))?;
+                    parsed.output = Some(PathBuf::from(value));
+                }
+                "--read-only" => parsed.read_only = true,
+                "--allow-multiple" => parsed.allow_multiple = true,
+                "-h" | "--help" => parsed.help = true,
+                _ => return Err(format!("unknown argument: {flag}")),
+            }
+        }
+        if parsed.output.is_some() && parsed.run.is_none() {
+            return Err(String::from("--output only applies to --run"));
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_device() {
+        assert_eq!(
+            parse(&["--device", "1"]).unwrap().device,
+            Some(DeviceSelector::Index(1))
+        );
+        assert_eq!(
+            parse(&["--device=GPU-8d5d2a1c"]).unwrap().device,
+            Some(DeviceSelector::Uuid(String::from("GPU-8d5d2a1c")))
+        );
+        assert_eq!(
+            parse(&["--device", "0000:01:00.0"]).unwrap().device,
+            Some(DeviceSelector::PciBusId(String::from("0000:01:00.0")))
+        );
+        assert_eq!(parse(&[]).unwrap(), Args::default());
+    }
+
+    #[test]
+    fn test_parse_watch_pid() {
+        assert_eq!(
+            parse(&["--watch-pid", "4242"]).unwrap().watch_pid,
+            Some(4242)
+        );
+        assert_eq!(parse(&["--watch-pid=17"]).unwrap().watch_pid, Some(17));
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        assert_eq!(
+            parse(&["--profile", "server"]).unwrap().profile,
+            Some(Profile::Server)
+        );
+        assert_eq!(
+            parse(&["--profile=desktop"]).unwrap().profile,
+            Some(Profile::Desktop)
+        );
+    }
+
+    #[test]
+    fn test_parse_allow_multiple() {
+        assert!(parse(&["--allow-multiple"]).unwrap().allow_multiple);
+        assert!(!parse(&["--device", "0"]).unwrap().allow_multiple);
+        assert!(parse(&["--read-only"]).unwrap().read_only);
+    }
+
+    #[test]
+    fn test_parse_run() {
+        let args = parse(&["--run", "python train.py --epochs=3", "--output=bench.txt"]).unwrap();
+        assert_eq!(args.run.as_deref(), Some("python train.py --epochs=3"));
+        assert_eq!(args.output, Some(PathBuf::from("bench.txt")));
+        assert!(parse(&["--output", "bench.txt"]).is_err());
+        assert!(parse(&["--run"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&["--device"]).is_err());
+        assert!(parse(&["--watch-pid"]).is_err());
+        assert!(parse(&["--watch-pid", "python"]).is_err());
+        assert!(parse(&["--profile", "laptop"]).is_err());
+        assert!(parse(&["--frobnicate"]).is_err());
+    }
+}
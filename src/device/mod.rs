@@ -1,12 +1,75 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Display;
+use std::time::{Duration, SystemTime};
 
 use circular_buffer::CircularBuffer;
-use eframe::egui::{self, Color32};
-use egui_plot::{Legend, Line, Plot, PlotPoints};
+use eframe::egui::{self, Color32, Rect, RichText, Stroke};
+use egui_plot::{
+    Bar, BarChart, HLine, Legend, Line, LineStyle, Plot, PlotPoint, PlotPoints, PlotUi, Polygon,
+};
 
-use nvml_wrapper::struct_wrappers::device::MemoryInfo;
+use nvml_wrapper::bitmasks::device::ThrottleReasons;
+use nvml_wrapper::enums::device::DeviceArchitecture;
+use nvml_wrapper::struct_wrappers::device::{MemoryInfo, Utilization};
+use nvml_wrapper::structs::device::PowerManagementConstraints;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy)]
+use crate::alerts::{TemperatureSensor, Thresholds};
+use crate::metric::{self, MetricValues, MetricVisibility, METRICS, METRIC_COUNT};
+use crate::mig::MigInstance;
+use crate::process::{ProcessData, ProcessKind};
+use crate::theme::Series;
+use crate::units::{format_bytes, format_number, number_format, UnitSystem, MIB};
+
+/// Upper bound on the number of points handed to `egui_plot` per line.
+const MAX_PLOT_POINTS: usize = 1000;
+
+/// Below this width (in points) the device view switches to a single column layout.
+const COMPACT_WIDTH: f32 = 600.0;
+
+/// Height of each plot when they are stacked in compact mode.
+const COMPACT_PLOT_HEIGHT: f32 = 200.0;
+
+pub const DEFAULT_PLOT_HEIGHT: f32 = 400.0;
+
+const DASHBOARD_FONT_SIZE: f32 = 48.0;
+const DASHBOARD_TILE_WIDTH: f32 = 220.0;
+
+/// The multi-GPU grid scrolls rather than shrinking its plots below this height.
+const GRID_TILE_MIN_HEIGHT: f32 = 120.0;
+
+/// How close in points the pointer has to be to a threshold line to drag it.
+const THRESHOLD_GRAB_DISTANCE: f32 = 8.0;
+
+const THRESHOLD_COLOR: Color32 = Color32::from_rgb(230, 150, 30);
+
+/// How far back the trend arrows look.
+const TREND_WINDOW: Duration = Duration::from_secs(5);
+
+/// How far back the throttling forecast fits the temperature's slope.
+const THROTTLE_FORECAST_WINDOW: Duration = Duration::from_secs(30);
+
+/// The slowest rise in °C/s that gets a throttling forecast, anything slower counts as stable.
+const MIN_THROTTLE_FORECAST_SLOPE: f64 = 0.02;
+
+/// Forecasts further out than this aren't shown, the trend won't hold that long.
+const MAX_THROTTLE_FORECAST: Duration = Duration::from_secs(15 * 60);
+
+/// How far back the fan anomaly detection looks. A fan has to look wrong for this long to be
+/// flagged, which keeps a single odd reading from raising a warning.
+const FAN_ANOMALY_WINDOW: Duration = Duration::from_secs(10);
+
+/// GPU temperature in °C above which a stopped fan is flagged.
+const FAN_STOPPED_TEMPERATURE: u32 = 70;
+
+/// How many percentage points a fan can run below the average of its siblings.
+const FAN_DEVIATION_LIMIT: f64 = 30.0;
+
+/// Bin widths of the histogram view, in °C and W.
+const TEMPERATURE_BIN_WIDTH: f64 = 1.0;
+const POWER_BIN_WIDTH: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct CudaDriverVersion {
     pub major: i32,
     pub minor: i32,
@@ -18,30 +81,703 @@ impl Display for CudaDriverVersion {
     }
 }
 
-#[derive(Debug, Clone)]
+pub fn format_clock(clock: Option<u32>) -> String {
+    match clock {
+        Some(mhz) => format!("{} MHz", format_number(mhz)),
+        None => String::from("N/A"),
+    }
+}
+
+/// The graphics clocks the device supports for each of its memory clocks, in MHz.
+pub type SupportedClocks = Vec<(u32, Vec<u32>)>;
+
+/// The lowest and highest of `clocks`, e.g. "210 - 2100 MHz".
+fn format_clock_range(clocks: &[u32]) -> String {
+    match (clocks.iter().min(), clocks.iter().max()) {
+        (Some(&min), Some(&max)) if min == max => format!("{} MHz", format_number(min)),
+        (Some(&min), Some(&max)) => {
+            format!("{} - {} MHz", format_number(min), format_number(max))
+        }
+        _ => String::from("N/A"),
+    }
+}
+
+/// A table of the valid memory and graphics clock combinations, with the full list of graphics
+/// clocks shown when hovering a row.
+pub fn supported_clocks_ui(ui: &mut egui::Ui, supported_clocks: &SupportedClocks) {
+    egui::Grid::new("supported clocks")
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("Memory clock");
+            ui.strong("Graphics clocks");
+            ui.strong("Range");
+            ui.end_row();
+
+            for (memory_clock, graphics_clocks) in supported_clocks {
+                let list = graphics_clocks
+                    .iter()
+                    .map(|&clock| format_number(clock))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                ui.label(format!("{} MHz", format_number(*memory_clock)));
+                ui.label(graphics_clocks.len().to_string())
+                    .on_hover_text(format!("{list} MHz"));
+                ui.label(format_clock_range(graphics_clocks))
+                    .on_hover_text(format!("{list} MHz"));
+                ui.end_row();
+            }
+        });
+}
+
+/// A color that identifies the GPU at `device_index` wherever several GPUs are shown.
+/// Successive indices are spread around the color wheel by the golden angle, so neighbouring
+/// GPUs get clearly different colors.
+pub fn device_color(device_index: usize) -> Color32 {
+    const GOLDEN_RATIO_CONJUGATE: f32 = 0.618_034;
+    let hue = (0.6 + device_index as f32 * GOLDEN_RATIO_CONJUGATE).fract();
+    egui::ecolor::Hsva::new(hue, 0.65, 0.9, 1.0).into()
+}
+
+/// Whether the GPU is driving a display. Falls back to looking for graphics processes when
+/// the driver doesn't report it.
+pub fn drives_display(device_state: &DeviceState, processes: &[ProcessData]) -> bool {
+    device_state.display_active.unwrap_or_else(|| {
+        processes.iter().any(|process| {
+            matches!(
+                process.process_kind,
+                ProcessKind::Graphics | ProcessKind::Both
+            )
+        })
+    })
+}
+
+/// A small badge telling whether the GPU drives a display or only runs compute work.
+pub fn display_badge_ui(ui: &mut egui::Ui, device_state: &DeviceState, processes: &[ProcessData]) {
+    let (text, color, hover_text) = if drives_display(device_state, processes) {
+        (
+            "🖵 Display",
+            Color32::from_rgb(70, 130, 220),
+            "This GPU is driving a display",
+        )
+    } else {
+        (
+            "Compute only",
+            Color32::from_rgb(118, 185, 0),
+            "No display is using this GPU",
+        )
+    };
+    let hover_text = if device_state.display_active.is_none() {
+        format!("{hover_text} (guessed from the graphics processes)")
+    } else {
+        hover_text.to_string()
+    };
+    egui::Frame::none()
+        .fill(color)
+        .rounding(4.0)
+        .inner_margin(egui::Margin::symmetric(5.0, 1.0))
+        .show(ui, |ui| {
+            ui.label(RichText::new(text).strong().color(Color32::WHITE));
+        })
+        .response
+        .on_hover_text(hover_text);
+}
+
+/// Formats a reading followed by `unit`, or "N/A" if the device doesn't support it.
+pub fn format_metric(value: Option<impl Display>, unit: &str) -> String {
+    match value {
+        Some(value) => format!("{value}{unit}"),
+        None => String::from("N/A"),
+    }
+}
+
+/// Converts a power reading to watts. NVML and nvidia-smi both report milliwatts, which are
+/// kept as integers everywhere but in the readouts.
+pub fn milliwatts_to_watts(milliwatts: u32) -> f64 {
+    milliwatts as f64 / 1000.0
+}
+
+/// Power usage in watts.
+pub fn power_watts(device_state: &DeviceState) -> Option<f64> {
+    device_state.power_usage.map(milliwatts_to_watts)
+}
+
+/// Formats a power in watts with one decimal, e.g. "218.4 W". All power readouts go through
+/// this, so that they agree on the unit and precision.
+pub fn format_watts(watts: f64) -> String {
+    format!("{} W", number_format().format(watts, 1))
+}
+
+/// The power readout, e.g. "154.2 W". Shows the `smoothed` value instead of the latest
+/// reading when power smoothing is on.
+pub fn format_power(device_state: &DeviceState, smoothed: Option<f64>) -> String {
+    let watts = match smoothed {
+        Some(watts) if device_state.power_usage.is_some() => Some(watts),
+        _ => power_watts(device_state),
+    };
+    watts.map_or_else(|| String::from("N/A"), format_watts)
+}
+
+/// An exponential moving average over irregularly spaced samples.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ema {
+    /// Time in seconds and value of the average after the last sample.
+    state: Option<(f64, f64)>,
+}
+
+impl Ema {
+    /// Adds a sample taken at `time` and returns the new average. A sample `time_constant`
+    /// seconds after the previous one moves the average about 63% of the way to it, and a
+    /// time constant of zero turns the averaging off.
+    pub fn update(&mut self, time: f64, value: f64, time_constant: f64) -> f64 {
+        let average = match self.state {
+            Some((last_time, last_average)) if time_constant > 0.0 => {
+                let alpha = 1.0 - (-(time - last_time).max(0.0) / time_constant).exp();
+                last_average + alpha * (value - last_average)
+            }
+            _ => value,
+        };
+        self.state = Some((time, average));
+        average
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.state.map(|(_, average)| average)
+    }
+
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+pub fn gpu_utilization(device_state: &DeviceState) -> Option<u32> {
+    device_state
+        .utilization
+        .as_ref()
+        .map(|utilization| utilization.gpu)
+}
+
+/// The used memory in bytes.
+pub fn memory_used(device_state: &DeviceState) -> Option<u64> {
+    device_state.mem_info.as_ref().map(|mem_info| mem_info.used)
+}
+
+/// The total memory in bytes, 0 if the device doesn't report it.
+pub fn memory_total(device_state: &DeviceState) -> u64 {
+    device_state
+        .mem_info
+        .as_ref()
+        .map_or(0, |mem_info| mem_info.total)
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct DeviceState {
     pub name: String,
     pub driver_version: String,
-    pub cuda_driver_version: CudaDriverVersion,
-    pub temperature: u32,
-    pub mem_info: MemoryInfo,
-    pub fan_speeds: Vec<u32>,
-    pub power_usage: u32,
+    /// `None` for metrics the device or driver doesn't report, here and below.
+    pub cuda_driver_version: Option<CudaDriverVersion>,
+    /// CUDA compute capability as (major, minor).
+    pub compute_capability: Option<(i32, i32)>,
+    pub architecture: Option<DeviceArchitecture>,
+    pub temperature: Option<u32>,
+    /// Memory junction temperature, only reported by some (mostly HBM/GDDR6X) cards.
+    /// NVML doesn't expose the hotspot sensor, so this is the only extra sensor we can read.
+    pub memory_temperature: Option<u32>,
+    /// Temperature at which the GPU starts hardware throttling.
+    pub slowdown_temperature: Option<u32>,
+    /// Memory temperature at which the memory starts slowing down.
+    pub memory_slowdown_temperature: Option<u32>,
+    /// Temperature at which the GPU shuts down to protect itself.
+    pub shutdown_temperature: Option<u32>,
+    pub mem_info: Option<MemoryInfo>,
+    /// Speed of each fan in percent, `None` for fans that can't report their speed.
+    /// Empty for passively cooled GPUs and most laptops.
+    pub fan_speeds: Vec<Option<u32>>,
+    /// Power usage in milliwatts.
+    pub power_usage: Option<u32>,
+    /// Energy used since the driver was loaded, in millijoules.
+    pub total_energy: Option<u64>,
+    /// The readings described by the metric registry, polled by every backend.
+    #[serde(serialize_with = "metric::serialize_by_id")]
+    pub metrics: MetricValues,
+    pub utilization: Option<Utilization>,
+    /// Current performance state, from P0 (maximum performance) to P15 (minimum).
+    pub pstate: Option<u8>,
+    /// Why the clocks are currently held back, if the driver reports it.
+    pub throttle_reasons: Option<ThrottleReasons>,
+    /// Power management limit in milliwatts.
+    pub power_limit: Option<u32>,
+    pub power_limit_constraints: Option<PowerManagementConstraints>,
+    /// Empty unless the GPU is in MIG mode.
+    pub mig_instances: Vec<MigInstance>,
+    /// Whether a display is initialized on the GPU, which only some drivers report.
+    pub display_active: Option<bool>,
+    /// `None` if ECC is disabled or not supported.
+    pub ecc_errors: Option<EccErrors>,
+}
+
+/// Memory errors counted since the driver was loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EccErrors {
+    pub corrected: u64,
+    pub uncorrected: u64,
+}
+
+/// A single set of plotted measurements, as pushed to the plots and recorded to session files.
+/// Readings the device doesn't report are `None`, so they leave gaps instead of zeros.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    pub temperature: Option<u32>,
+    pub memory_temperature: Option<u32>,
+    /// Used memory in MiB.
+    pub memory_usage: Option<u64>,
+    /// Power usage in mW.
+    pub power_usage_mw: Option<u32>,
+    /// GPU utilization in percent.
+    pub gpu_utilization: Option<u32>,
+    /// Share of the time the memory was being read or written, in percent.
+    pub memory_utilization: Option<u32>,
+    /// Whether the clocks were throttled because of power or temperature.
+    pub throttled: bool,
+    /// The readings described by the metric registry.
+    pub metrics: MetricValues,
+}
+
+impl DeviceState {
+    /// Whether the clocks are held back by a power or thermal limit. Idling and clocks set by
+    /// the user or applications don't count.
+    pub fn is_throttled(&self) -> bool {
+        let limits = ThrottleReasons::SW_POWER_CAP
+            | ThrottleReasons::HW_SLOWDOWN
+            | ThrottleReasons::SW_THERMAL_SLOWDOWN
+            | ThrottleReasons::HW_THERMAL_SLOWDOWN
+            | ThrottleReasons::HW_POWER_BRAKE_SLOWDOWN;
+        self.throttle_reasons
+            .is_some_and(|reasons| reasons.intersects(limits))
+    }
+}
+
+/// Readings for tests, built up from [`DeviceState::mock`].
+#[cfg(test)]
+impl DeviceState {
+    /// An idle "Test GPU" at 50°C with 8000 bytes of memory that reports nothing optional
+    /// beyond its power and utilization.
+    pub fn mock() -> Self {
+        Self {
+            name: String::from("Test GPU"),
+            driver_version: String::new(),
+            cuda_driver_version: Some(CudaDriverVersion {
+                major: 12,
+                minor: 0,
+            }),
+            compute_capability: None,
+            architecture: None,
+            temperature: Some(50),
+            memory_temperature: None,
+            slowdown_temperature: None,
+            memory_slowdown_temperature: None,
+            shutdown_temperature: None,
+            mem_info: Some(MemoryInfo {
+                free: 8000,
+                total: 8000,
+                used: 0,
+            }),
+            fan_speeds: Vec::new(),
+            power_usage: Some(0),
+            total_energy: None,
+            metrics: [None; METRIC_COUNT],
+            utilization: Some(Utilization { gpu: 0, memory: 0 }),
+            pstate: None,
+            throttle_reasons: None,
+            power_limit: None,
+            power_limit_constraints: None,
+            mig_instances: Vec::new(),
+            display_active: None,
+            ecc_errors: None,
+        }
+    }
+
+    pub fn with_temperature(mut self, temperature: u32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Used and total memory in bytes.
+    pub fn with_memory(mut self, used: u64, total: u64) -> Self {
+        self.mem_info = Some(MemoryInfo {
+            free: total - used,
+            total,
+            used,
+        });
+        self
+    }
+
+    pub fn with_power(mut self, power_mw: u32) -> Self {
+        self.power_usage = Some(power_mw);
+        self
+    }
+
+    /// The energy counter in millijoules.
+    pub fn with_energy(mut self, energy: u64) -> Self {
+        self.total_energy = Some(energy);
+        self
+    }
+
+    pub fn with_gpu_utilization(mut self, gpu: u32) -> Self {
+        self.utilization = Some(Utilization { gpu, memory: 0 });
+        self
+    }
+}
+
+impl From<&DeviceState> for Sample {
+    fn from(device_state: &DeviceState) -> Self {
+        Self {
+            temperature: device_state.temperature,
+            memory_temperature: device_state.memory_temperature,
+            memory_usage: device_state
+                .mem_info
+                .as_ref()
+                .map(|mem_info| mem_info.used / MIB),
+            power_usage_mw: device_state.power_usage,
+            gpu_utilization: gpu_utilization(device_state),
+            memory_utilization: device_state
+                .utilization
+                .as_ref()
+                .map(|utilization| utilization.memory),
+            throttled: device_state.is_throttled(),
+            metrics: device_state.metrics,
+        }
+    }
+}
+
+/// The most samples per device that are kept across restarts, which bounds the size of the
+/// saved state.
+const MAX_SAVED_SAMPLES: usize = 2000;
+
+/// Saved history older than this isn't restored, the gap would make up most of the plots.
+const MAX_RESTORE_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// The plotted history of the last session, see [`DeviceView::saved_history`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedHistory {
+    saved_at: SystemTime,
+    devices: Vec<SavedDevice>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedDevice {
+    device_index: usize,
+    /// The history is only restored onto a device of the same name, in case the GPUs changed.
+    device_name: String,
+    /// Seconds before the history was saved, which are negative, and the samples taken then.
+    samples: Vec<(f64, Sample)>,
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct DeviceView {
-    pub device_stats_plot: DeviceStatsPlot,
+    /// Plotted history per GPU index, so that switching devices doesn't throw it away.
+    device_stats_plots: HashMap<usize, DeviceStatsPlot>,
+    /// Plots shown in windows of their own instead of in the Devices tab.
+    detached_plots: HashSet<PlotKind>,
+    #[cfg(feature = "power-control")]
+    pub power_limit_control: PowerLimitControl,
+    #[cfg(feature = "admin")]
+    pub gpu_reset_control: GpuResetControl,
+}
+
+impl DeviceView {
+    pub fn device_stats_plot(&mut self, device_index: usize) -> &mut DeviceStatsPlot {
+        self.device_stats_plots.entry(device_index).or_default()
+    }
+
+    /// The latest samples of every device, `session_time` seconds into the session, to be
+    /// restored by [`DeviceView::restore_history`] after a restart.
+    pub fn saved_history(&self, session_time: f64, device_names: &[String]) -> SavedHistory {
+        let devices = self
+            .device_stats_plots
+            .iter()
+            .filter_map(|(&device_index, plot)| {
+                let samples = plot.samples();
+                let recent = &samples[samples.len().saturating_sub(MAX_SAVED_SAMPLES)..];
+                Some(SavedDevice {
+                    device_index,
+                    device_name: device_names.get(device_index)?.clone(),
+                    samples: recent
+                        .iter()
+                        .map(|&(time, sample)| (time - session_time, sample))
+                        .collect(),
+                })
+            })
+            .filter(|device| !device.samples.is_empty())
+            .collect();
+        SavedHistory {
+            saved_at: SystemTime::now(),
+            devices,
+        }
+    }
+
+    /// Puts the history saved by the last session in front of the plots, as if it had been
+    /// recorded just before this session started. History older than `MAX_RESTORE_AGE` or of
+    /// a device that has changed since is left out.
+    pub fn restore_history(&mut self, history: SavedHistory, device_names: &[String]) {
+        let Some(age) = SystemTime::now()
+            .duration_since(history.saved_at)
+            .ok()
+            .filter(|&age| age <= MAX_RESTORE_AGE)
+        else {
+            return;
+        };
+        for device in history.devices {
+            if device_names.get(device.device_index) != Some(&device.device_name) {
+                continue;
+            }
+            let plot = self.device_stats_plot(device.device_index);
+            for (time, sample) in device.samples {
+                plot.push_to_buffers(time - age.as_secs_f64(), &sample);
+            }
+        }
+    }
+
+    /// The smoothed power usage of `device_index` in W, `None` while smoothing is off.
+    pub fn smoothed_power(&self, device_index: usize) -> Option<f64> {
+        self.device_stats_plots
+            .get(&device_index)
+            .and_then(DeviceStatsPlot::smoothed_power)
+    }
+
+    /// Draws the plots of `device_index` that aren't detached.
+    pub fn plot_ui(&mut self, ui: &mut egui::Ui, device_index: usize) {
+        self.device_stats_plots
+            .entry(device_index)
+            .or_default()
+            .plot_ui(ui, Some(&mut self.detached_plots));
+    }
+
+    /// Tiles the GPU utilization plot of every device, so that all of them can be watched at
+    /// once. The plots of devices other than the selected one are only fed while this is shown.
+    pub fn grid_ui(&mut self, ui: &mut egui::Ui, device_names: &[String]) {
+        let (columns, rows) = grid_dimensions(device_names.len());
+        if columns == 0 {
+            ui.label("No GPUs");
+            return;
+        }
+        let spacing = ui.spacing().item_spacing;
+        let width = (ui.available_width() - spacing.x * (columns - 1) as f32) / columns as f32;
+        let title_height = ui.text_style_height(&egui::TextStyle::Body) + spacing.y;
+        let height = ((ui.available_height() - spacing.y * (rows - 1) as f32) / rows as f32
+            - title_height)
+            .max(GRID_TILE_MIN_HEIGHT);
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (row, names) in device_names.chunks(columns).enumerate() {
+                ui.horizontal(|ui| {
+                    for (column, name) in names.iter().enumerate() {
+                        let device_index = row * columns + column;
+                        let plot = self.device_stats_plots.entry(device_index).or_default();
+                        ui.vertical(|ui| {
+                            ui.set_width(width);
+                            let utilization = plot.utilization_vals.back().copied().flatten();
+                            ui.label(
+                                RichText::new(format!(
+                                    "● {device_index}: {name} {}",
+                                    format_metric(utilization, "%")
+                                ))
+                                .color(device_color(device_index)),
+                            );
+                            plot.utilization_tile_ui(ui, device_index, width, height);
+                        });
+                    }
+                });
+            }
+        });
+    }
+
+    /// Draws a resizable window for every detached plot. Closing a window puts the plot back
+    /// into the Devices tab.
+    pub fn detached_plots_ui(&mut self, ctx: &egui::Context, device_index: usize) {
+        let plot = self.device_stats_plots.entry(device_index).or_default();
+        for kind in plot.plot_kinds() {
+            if !self.detached_plots.contains(&kind) {
+                continue;
+            }
+            let mut open = true;
+            egui::Window::new(kind.to_string())
+                .id(egui::Id::new(("detached plot", kind)))
+                .open(&mut open)
+                .default_size([500.0, 300.0])
+                .show(ctx, |ui| {
+                    let size = ui.available_size();
+                    plot.kind_plot_ui(kind, ui, size.x, size.y, is_compact(ui));
+                });
+            if !open {
+                self.detached_plots.remove(&kind);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "power-control")]
+#[derive(Debug, Default, Clone)]
+pub struct PowerLimitControl {
+    /// The limit shown on the slider in watts, initialized from the device on first draw.
+    slider_watts: Option<u32>,
+    pub error: Option<String>,
+}
+
+#[cfg(feature = "power-control")]
+impl PowerLimitControl {
+    /// Returns the requested power limit in milliwatts once the user lets go of the slider.
+    pub fn ui(&mut self, ui: &mut egui::Ui, device_state: &DeviceState) -> Option<u32> {
+        let (Some(power_limit), Some(constraints)) = (
+            device_state.power_limit,
+            device_state.power_limit_constraints.as_ref(),
+        ) else {
+            ui.label("Power limit control is not supported on this device");
+            return None;
+        };
+
+        let slider_watts = self.slider_watts.get_or_insert(power_limit / 1000);
+        let mut requested_limit = None;
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::Slider::new(
+                    slider_watts,
+                    constraints.min_limit / 1000..=constraints.max_limit / 1000,
+                )
+                .text("Power limit")
+                .suffix(" W"),
+            );
+            if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                requested_limit = Some(
+                    (*slider_watts * 1000).clamp(constraints.min_limit, constraints.max_limit),
+                );
+            }
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::RED, error);
+        }
+
+        requested_limit
+    }
+}
+
+/// The word that has to be typed to confirm a GPU reset.
+#[cfg(feature = "admin")]
+const RESET_CONFIRMATION: &str = "reset";
+
+#[cfg(feature = "admin")]
+#[derive(Debug, Default, Clone)]
+pub struct GpuResetControl {
+    /// Whether the confirmation window is open.
+    confirming: bool,
+    confirmation: String,
+    pub error: Option<String>,
+}
+
+#[cfg(feature = "admin")]
+impl GpuResetControl {
+    /// Returns `true` once the user confirmed the reset. Resetting is only offered while no
+    /// process is using the GPU.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        device_state: &DeviceState,
+        process_count: usize,
+    ) -> bool {
+        ui.horizontal(|ui| {
+            let idle = process_count == 0;
+            let response = ui
+                .add_enabled(idle, egui::Button::new("⚠ Reset GPU..."))
+                .on_hover_text("Reset the GPU, e.g. after a hung kernel")
+                .on_disabled_hover_text(format!(
+                    "{process_count} processes are using the GPU, stop them first"
+                ));
+            if response.clicked() {
+                self.confirming = true;
+                self.confirmation.clear();
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::RED, error);
+        }
+
+        let mut confirmed = false;
+        let mut open = self.confirming;
+        egui::Window::new("Reset GPU")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ui.ctx(), |ui| {
+                ui.label(format!(
+                    "This resets {}. Anything still using it will crash, and the display \
+                     may go blank if it drives one.",
+                    device_state.name
+                ));
+                ui.label(format!("Type \"{RESET_CONFIRMATION}\" to confirm:"));
+                ui.text_edit_singleline(&mut self.confirmation);
+                let button = egui::Button::new(
+                    RichText::new("Reset GPU").color(ui.visuals().error_fg_color),
+                );
+                confirmed = ui
+                    .add_enabled(self.confirmation.trim() == RESET_CONFIRMATION, button)
+                    .clicked();
+            });
+        self.confirming = open && !confirmed;
+        confirmed
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct DeviceStatsPlot {
-    pub temperature_vals: CircularBuffer<5000, u32>,
+    /// `None` where the device didn't report the reading, here and in the buffers below.
+    pub temperature_vals: CircularBuffer<5000, Option<u32>>,
+    pub memory_temperature_vals: CircularBuffer<5000, Option<u32>>,
     max_temperature: u32,
-    pub memory_usage_vals: CircularBuffer<5000, u64>,
+    slowdown_temperature: Option<u32>,
+    memory_slowdown_temperature: Option<u32>,
+    shutdown_temperature: Option<u32>,
+    pub memory_usage_vals: CircularBuffer<5000, Option<u64>>,
     max_memory_usage: u64,
-    pub power_usage_vals: CircularBuffer<5000, u32>,
+    /// Keep the memory plot's y axis at `max_memory_usage` instead of fitting it to the data.
+    pin_memory_to_total: bool,
+    /// Memory usage in MiB above which the memory plot is drawn as a warning.
+    memory_warning_threshold: Option<u64>,
+    memory_unit: UnitSystem,
+    /// In mW, like the readings.
+    pub power_usage_vals: CircularBuffer<5000, Option<u32>>,
+    /// In W.
     max_power_usage: u32,
+    /// Time constant in seconds of the average behind the power readout, zero for none.
+    power_smoothing: f64,
+    smoothed_power: Ema,
+    pub utilization_vals: CircularBuffer<5000, Option<u32>>,
+    pub memory_utilization_vals: CircularBuffer<5000, Option<u32>>,
+    pub throttled_vals: CircularBuffer<5000, bool>,
+    /// The registry metrics of each sample, boxed since they don't fit the stack as well.
+    pub metric_vals: Box<CircularBuffer<5000, MetricValues>>,
+    /// Which registry metrics get a plot.
+    shown_metrics: MetricVisibility,
+    /// When each sample was taken, in seconds since the start of the session.
+    pub timestamps: CircularBuffer<5000, f64>,
+    pub fan_history: FanHistory,
+    aggregation: Aggregation,
+    series_style: SeriesStyle,
+    retention_policy: RetentionPolicy,
+    /// Draw the alert thresholds as lines that can be dragged to change them.
+    editing_thresholds: bool,
+    threshold_lines: ThresholdLines,
+    /// Whether `threshold_lines` were moved since the last `take_edited_thresholds`.
+    thresholds_edited: bool,
+    /// Show how the temperature and power samples are distributed instead of the time series.
+    histogram_view: bool,
+    /// Whether the temperature plot keeps the newest samples in view, until it is dragged or
+    /// zoomed.
+    follow_live: bool,
+    /// Set by the "Follow live" button to snap the temperature plot back to the newest samples.
+    snap_to_live: bool,
+    /// Height of each row of plots, `None` to share the available height between the rows.
+    plot_height: Option<f32>,
+    plot_rects: Vec<(PlotKind, Rect)>,
 }
 
 impl Default for DeviceStatsPlot {
@@ -49,11 +785,38 @@ impl Default for DeviceStatsPlot {
         Self {
             // TODO(Thomas): These max value asusmptions should come from a better place than this
             temperature_vals: CircularBuffer::new(),
+            memory_temperature_vals: CircularBuffer::new(),
             max_temperature: 100,
+            slowdown_temperature: None,
+            memory_slowdown_temperature: None,
+            shutdown_temperature: None,
             memory_usage_vals: CircularBuffer::new(),
             max_memory_usage: 0,
+            pin_memory_to_total: true,
+            memory_warning_threshold: None,
+            memory_unit: UnitSystem::default(),
             power_usage_vals: CircularBuffer::new(),
             max_power_usage: 1000,
+            power_smoothing: 0.0,
+            smoothed_power: Ema::default(),
+            utilization_vals: CircularBuffer::new(),
+            memory_utilization_vals: CircularBuffer::new(),
+            throttled_vals: CircularBuffer::new(),
+            metric_vals: CircularBuffer::boxed(),
+            shown_metrics: [true; METRIC_COUNT],
+            timestamps: CircularBuffer::new(),
+            fan_history: FanHistory::default(),
+            aggregation: Aggregation::None,
+            series_style: SeriesStyle::default(),
+            retention_policy: RetentionPolicy::default(),
+            editing_thresholds: false,
+            threshold_lines: ThresholdLines::default(),
+            thresholds_edited: false,
+            histogram_view: false,
+            follow_live: true,
+            snap_to_live: false,
+            plot_height: Some(DEFAULT_PLOT_HEIGHT),
+            plot_rects: Vec::new(),
         }
     }
 }
@@ -62,91 +825,1894 @@ impl DeviceStatsPlot {
     pub fn set_max_memory_usage(&mut self, max_memory_usage: u64) {
         self.max_memory_usage = max_memory_usage;
     }
+
+    /// Sets the memory plot's maximum unless a valid one is already known, so that it's
+    /// picked up as soon as the device first reports its total memory.
+    pub fn init_max_memory_usage(&mut self, max_memory_usage: u64) {
+        if self.max_memory_usage == 0 {
+            self.max_memory_usage = max_memory_usage;
+        }
+    }
+
+    pub fn set_plot_height(&mut self, plot_height: Option<f32>) {
+        self.plot_height = plot_height;
+    }
+
+    /// The height of a row of plots when there are `rows` of them.
+    fn row_height(&self, ui: &egui::Ui, rows: usize) -> f32 {
+        self.plot_height.unwrap_or_else(|| {
+            let spacing = ui.spacing().item_spacing.y * (rows - 1) as f32;
+            ((ui.available_height() - spacing) / rows as f32).max(COMPACT_PLOT_HEIGHT / 2.0)
+        })
+    }
+
+    pub fn set_series_style(&mut self, series_style: SeriesStyle) {
+        self.series_style = series_style;
+    }
+
+    pub fn set_shown_metrics(&mut self, shown_metrics: MetricVisibility) {
+        self.shown_metrics = shown_metrics;
+    }
+
+    pub fn set_retention_policy(&mut self, retention_policy: RetentionPolicy) {
+        self.retention_policy = retention_policy;
+    }
+
+    pub fn set_threshold_lines(&mut self, threshold_lines: ThresholdLines) {
+        self.threshold_lines = threshold_lines;
+    }
+
+    /// The threshold lines if they were dragged since the last call.
+    pub fn take_edited_thresholds(&mut self) -> Option<ThresholdLines> {
+        std::mem::take(&mut self.thresholds_edited).then_some(self.threshold_lines)
+    }
+
+    pub fn set_pin_memory_to_total(&mut self, pin_memory_to_total: bool) {
+        self.pin_memory_to_total = pin_memory_to_total;
+    }
+
+    pub fn set_memory_unit(&mut self, memory_unit: UnitSystem) {
+        self.memory_unit = memory_unit;
+    }
+
+    pub fn set_power_smoothing(&mut self, time_constant: Duration) {
+        self.power_smoothing = time_constant.as_secs_f64();
+    }
+
+    /// The averaged power usage in W for the readouts. The plots keep the raw samples.
+    pub fn smoothed_power(&self) -> Option<f64> {
+        (self.power_smoothing > 0.0)
+            .then(|| self.smoothed_power.value())
+            .flatten()
+    }
+
+    pub fn set_memory_warning_threshold(&mut self, memory_warning_threshold: Option<u64>) {
+        self.memory_warning_threshold = memory_warning_threshold;
+    }
+
+    pub fn set_temperature_thresholds(
+        &mut self,
+        slowdown_temperature: Option<u32>,
+        memory_slowdown_temperature: Option<u32>,
+        shutdown_temperature: Option<u32>,
+    ) {
+        self.slowdown_temperature = slowdown_temperature;
+        self.memory_slowdown_temperature = memory_slowdown_temperature;
+        self.shutdown_temperature = shutdown_temperature;
+    }
+
+    /// Pushes a sample taken `time` seconds after the start of the session.
+    pub fn push_sample(&mut self, time: f64, sample: &Sample) {
+        if self.retention_policy == RetentionPolicy::Downsample && self.timestamps.is_full() {
+            self.downsample_history();
+        }
+        self.push_to_buffers(time, sample);
+        if let Some(power_usage_mw) = sample.power_usage_mw {
+            self.smoothed_power.update(
+                time,
+                milliwatts_to_watts(power_usage_mw),
+                self.power_smoothing,
+            );
+        }
+    }
+
+    fn push_to_buffers(&mut self, time: f64, sample: &Sample) {
+        self.timestamps.push_back(time);
+        self.temperature_vals.push_back(sample.temperature);
+        self.memory_temperature_vals
+            .push_back(sample.memory_temperature);
+        self.memory_usage_vals.push_back(sample.memory_usage);
+        self.power_usage_vals.push_back(sample.power_usage_mw);
+        self.utilization_vals.push_back(sample.gpu_utilization);
+        self.memory_utilization_vals
+            .push_back(sample.memory_utilization);
+        self.throttled_vals.push_back(sample.throttled);
+        self.metric_vals.push_back(sample.metrics);
+    }
+
+    /// Makes room in the full buffers by merging the older half of the samples in pairs, so
+    /// the oldest samples get coarser every time the buffers fill up.
+    fn downsample_history(&mut self) {
+        let samples = downsample_older_half(&self.samples());
+        self.clear_buffers();
+        for (time, sample) in samples {
+            self.push_to_buffers(time, &sample);
+        }
+    }
+
+    /// Empties the plotted history, keeping the plot's limits and thresholds.
+    pub fn clear_history(&mut self) {
+        self.clear_buffers();
+        self.fan_history.clear();
+        self.smoothed_power.reset();
+    }
+
+    fn clear_buffers(&mut self) {
+        self.timestamps.clear();
+        self.temperature_vals.clear();
+        self.memory_temperature_vals.clear();
+        self.memory_usage_vals.clear();
+        self.power_usage_vals.clear();
+        self.utilization_vals.clear();
+        self.memory_utilization_vals.clear();
+        self.throttled_vals.clear();
+        self.metric_vals.clear();
+    }
+
+    /// All buffered samples with their timestamps, oldest first.
+    pub fn samples(&self) -> Vec<(f64, Sample)> {
+        (0..self.timestamps.len())
+            .map(|i| {
+                let sample = Sample {
+                    temperature: self.temperature_vals[i],
+                    memory_temperature: self.memory_temperature_vals[i],
+                    memory_usage: self.memory_usage_vals[i],
+                    power_usage_mw: self.power_usage_vals[i],
+                    gpu_utilization: self.utilization_vals[i],
+                    memory_utilization: self.memory_utilization_vals[i],
+                    throttled: self.throttled_vals[i],
+                    metrics: self.metric_vals[i],
+                };
+                (self.timestamps[i], sample)
+            })
+            .collect()
+    }
+
+    /// How the latest of `values` compares to the ones buffered during the `TREND_WINDOW`
+    /// before it. `values` has to be one of the sample buffers. `None` while the latest value
+    /// is missing.
+    fn buffer_trend(
+        &self,
+        values: &CircularBuffer<5000, Option<u32>>,
+        tolerance: f64,
+    ) -> Option<Trend> {
+        let (&latest, &latest_time) = values.back().zip(self.timestamps.back())?;
+        let previous = values
+            .iter()
+            .zip(self.timestamps.iter())
+            .rev()
+            .skip(1)
+            .take_while(|(_, &time)| latest_time - time <= TREND_WINDOW.as_secs_f64())
+            .filter_map(|(&value, _)| value.map(f64::from));
+        trend(latest? as f64, previous, tolerance)
+    }
+
+    pub fn temperature_trend(&self) -> Option<Trend> {
+        self.buffer_trend(&self.temperature_vals, 1.0)
+    }
+
+    pub fn power_trend(&self) -> Option<Trend> {
+        self.buffer_trend(&self.power_usage_vals, 2000.0)
+    }
+
+    pub fn utilization_trend(&self) -> Option<Trend> {
+        self.buffer_trend(&self.utilization_vals, 2.0)
+    }
+
+    /// How long until the GPU reaches its slowdown temperature if it keeps heating up like
+    /// it did over the `THROTTLE_FORECAST_WINDOW`. `None` while it isn't heating up.
+    pub fn time_to_throttle(&self) -> Option<Duration> {
+        let slowdown_temperature = self.slowdown_temperature?;
+        let &latest_time = self.timestamps.back()?;
+        let points: Vec<(f64, f64)> = self
+            .timestamps
+            .iter()
+            .zip(self.temperature_vals.iter())
+            .rev()
+            .take_while(|(&time, _)| latest_time - time <= THROTTLE_FORECAST_WINDOW.as_secs_f64())
+            .filter_map(|(&time, &temperature)| Some((time, temperature? as f64)))
+            .collect();
+        time_to_limit(&points, slowdown_temperature as f64)
+    }
 }
 
 impl DeviceStatsPlot {
-    pub fn plot_ui(&mut self, ui: &mut egui::Ui) {
+    /// Pairs each buffered value with its x coordinate, which is the sample's timestamp when
+    /// aggregating and the sample index otherwise. `None` values are skipped.
+    fn series_points(&self, values: impl Iterator<Item = Option<f64>>) -> Vec<[f64; 2]> {
+        values
+            .zip(self.timestamps.iter())
+            .enumerate()
+            .filter_map(|(i, (value, &time))| {
+                let x = match self.aggregation {
+                    Aggregation::None => i as f64,
+                    _ => time,
+                };
+                Some([x, value?])
+            })
+            .collect()
+    }
+
+    /// The plots to draw, leaving out the registry metrics that are hidden.
+    fn plot_kinds(&self) -> Vec<PlotKind> {
+        PlotKind::all()
+            .into_iter()
+            .filter(|kind| match kind {
+                PlotKind::Metric(index) => self.shown_metrics[*index],
+                _ => true,
+            })
+            .collect()
+    }
+
+    fn x_axis_label(&self) -> &'static str {
+        match self.aggregation {
+            Aggregation::None => "measurements",
+            _ => "seconds",
+        }
+    }
+
+    /// Draws the plots that aren't in `detached`, with a menu to detach them. Pass `None` to
+    /// always draw every plot, without the menu.
+    pub fn plot_ui(&mut self, ui: &mut egui::Ui, mut detached: Option<&mut HashSet<PlotKind>>) {
         ui.horizontal(|ui| {
-            ui.set_height(400.0);
-            egui_plot::Plot::new("temperature")
-                .width(ui.available_width() / 2.0)
-                .include_x(0)
-                .include_y(0)
-                .include_y(self.max_temperature)
-                .allow_zoom(true)
-                .allow_drag(true)
-                .allow_scroll(false)
-                .legend(Legend::default())
-                .x_axis_label("measurements")
-                .y_axis_label("deg")
-                .show_grid(false)
-                .show(ui, |plot_ui| {
-                    let temperature_points: PlotPoints = self
-                        .temperature_vals
-                        .iter()
-                        .enumerate()
-                        .map(|(i, &temp)| [i as f64, temp as f64])
-                        .collect();
-
-                    plot_ui.line(
-                        Line::new(temperature_points)
-                            .name("GPU Temperature")
-                            .color(Color32::from_rgb(168, 68, 13)),
+            if let Some(detached) = detached.as_deref_mut() {
+                ui.menu_button("⬈ Detach", |ui| {
+                    for kind in self.plot_kinds() {
+                        let mut is_detached = detached.contains(&kind);
+                        if ui.checkbox(&mut is_detached, kind.to_string()).changed() {
+                            if is_detached {
+                                detached.insert(kind);
+                            } else {
+                                detached.remove(&kind);
+                            }
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Show plots in windows of their own");
+            }
+            if self.threshold_lines != ThresholdLines::default() {
+                ui.toggle_value(&mut self.editing_thresholds, "📏 Thresholds")
+                    .on_hover_text(
+                        "Drag the threshold lines on the plots, or hover a plot and use the \
+                         arrow keys, to change the alert thresholds",
                     );
-                });
+            }
+            if !self.follow_live && !self.histogram_view {
+                self.snap_to_live = ui
+                    .button("⏭ Follow live")
+                    .on_hover_text("Jump back to the newest temperature samples and stay there")
+                    .clicked();
+            }
+            ui.toggle_value(&mut self.histogram_view, "Histogram")
+                .on_hover_text("Show how much of the time was spent at each temperature and power");
+            if !self.histogram_view {
+                ui.label("Aggregation:");
+                egui::ComboBox::from_id_source("aggregation")
+                    .selected_text(self.aggregation.to_string())
+                    .show_ui(ui, |ui| {
+                        for aggregation in Aggregation::ALL {
+                            ui.selectable_value(
+                                &mut self.aggregation,
+                                aggregation,
+                                aggregation.to_string(),
+                            );
+                        }
+                    });
+            }
+        });
 
-            Plot::new("memory usage")
-                .width(ui.available_width())
-                .include_x(0)
-                .include_y(0)
-                .include_y(self.max_memory_usage as f64)
-                .allow_zoom(false)
-                .allow_drag(false)
-                .allow_scroll(false)
-                .legend(Legend::default())
-                .x_axis_label("measurements")
-                .y_axis_label("MiB")
-                .show_grid(false)
-                .show(ui, |plot_ui| {
-                    let memory_usage_points: PlotPoints = self
-                        .memory_usage_vals
-                        .iter()
-                        .enumerate()
-                        .map(|(i, &mem_usage)| [i as f64, mem_usage as f64])
-                        .collect();
-
-                    plot_ui.line(
-                        Line::new(memory_usage_points)
-                            .name("Memory Usage")
-                            .color(Color32::from_rgb(95, 118, 156)),
-                    );
+        if self.histogram_view {
+            self.histograms_ui(ui);
+            return;
+        }
+
+        let kinds: Vec<PlotKind> = self
+            .plot_kinds()
+            .into_iter()
+            .filter(|kind| {
+                detached
+                    .as_ref()
+                    .is_none_or(|detached| !detached.contains(kind))
+            })
+            .collect();
+        let mut plot_rects = Vec::new();
+        // Narrow windows stack the plots in a single column instead of side by side
+        if is_compact(ui) {
+            let height = COMPACT_PLOT_HEIGHT;
+            for kind in kinds {
+                let rect = self.kind_plot_ui(kind, ui, ui.available_width(), height, true);
+                plot_rects.push((kind, rect));
+            }
+        } else if !kinds.is_empty() {
+            let rows: Vec<&[PlotKind]> = kinds.chunks(2).collect();
+            let height = self.row_height(ui, rows.len());
+            for row in rows {
+                ui.horizontal(|ui| {
+                    ui.set_height(height);
+                    for (position, &kind) in row.iter().enumerate() {
+                        // Split what is left between this plot and the ones after it
+                        let width = ui.available_width() / (row.len() - position) as f32;
+                        let rect = self.kind_plot_ui(kind, ui, width, height, false);
+                        plot_rects.push((kind, rect));
+                    }
                 });
-        });
-        Plot::new("power usage")
-            .width(ui.available_width() / 2.0)
+            }
+        }
+        self.plot_rects = plot_rects;
+    }
+
+    /// A bare utilization plot for the multi-GPU grid.
+    fn utilization_tile_ui(&self, ui: &mut egui::Ui, device_index: usize, width: f32, height: f32) {
+        let points = self.series_points(
+            self.utilization_vals
+                .iter()
+                .map(|utilization| utilization.map(f64::from)),
+        );
+        Plot::new(("utilization tile", device_index))
+            .width(width)
+            .height(height)
             .include_x(0)
             .include_y(0)
-            .include_y(self.max_power_usage as f64)
+            .include_y(100)
+            .allow_scroll(false)
             .allow_zoom(false)
             .allow_drag(false)
+            .show_grid(false)
+            .show(ui, |plot_ui| {
+                draw_series(
+                    plot_ui,
+                    &points,
+                    self.aggregation,
+                    self.series_style,
+                    "GPU utilization",
+                    device_color(device_index),
+                );
+            });
+    }
+
+    fn kind_plot_ui(
+        &mut self,
+        kind: PlotKind,
+        ui: &mut egui::Ui,
+        width: f32,
+        height: f32,
+        compact: bool,
+    ) -> Rect {
+        match kind {
+            PlotKind::Temperature => self.temperature_plot_ui(ui, width, height, compact),
+            PlotKind::Memory => self.memory_plot_ui(ui, width, height, compact),
+            PlotKind::Power => self.power_plot_ui(ui, width, height, compact),
+            PlotKind::Utilization => self.utilization_plot_ui(ui, width, height, compact),
+            PlotKind::Efficiency => self.efficiency_plot_ui(ui, width, height, compact),
+            PlotKind::Metric(index) => self.metric_plot_ui(index, ui, width, height, compact),
+        }
+    }
+
+    fn histograms_ui(&mut self, ui: &mut egui::Ui) {
+        let temperature_bins = histogram(
+            self.temperature_vals
+                .iter()
+                .filter_map(|temp| temp.map(f64::from)),
+            TEMPERATURE_BIN_WIDTH,
+        );
+        let power_bins = histogram(
+            self.power_usage_vals
+                .iter()
+                .filter_map(|milliwatts| milliwatts.map(milliwatts_to_watts)),
+            POWER_BIN_WIDTH,
+        );
+        let dark_mode = ui.visuals().dark_mode;
+        let temperature_chart = || {
+            histogram_chart(&temperature_bins, TEMPERATURE_BIN_WIDTH)
+                .color(Series::Temperature.color(dark_mode))
+                .name("Temperature")
+        };
+        let power_chart = || {
+            histogram_chart(&power_bins, POWER_BIN_WIDTH)
+                .color(Series::Power.color(dark_mode))
+                .name("Power Usage")
+        };
+
+        let compact = is_compact(ui);
+        let [temperature, power] = if compact {
+            let (width, height) = (ui.available_width(), COMPACT_PLOT_HEIGHT);
+            [
+                histogram_plot("temperature histogram", "°C", width, height, compact)
+                    .show(ui, |plot_ui| plot_ui.bar_chart(temperature_chart())),
+                histogram_plot("power histogram", "W", width, height, compact)
+                    .show(ui, |plot_ui| plot_ui.bar_chart(power_chart())),
+            ]
+            .map(|plot| plot.response.rect)
+        } else {
+            let height = self.row_height(ui, 1);
+            ui.horizontal(|ui| {
+                let width = ui.available_width() / 2.0;
+                let temperature =
+                    histogram_plot("temperature histogram", "°C", width, height, compact)
+                        .show(ui, |plot_ui| plot_ui.bar_chart(temperature_chart()));
+                let width = ui.available_width();
+                let power = histogram_plot("power histogram", "W", width, height, compact)
+                    .show(ui, |plot_ui| plot_ui.bar_chart(power_chart()));
+                [temperature.response.rect, power.response.rect]
+            })
+            .inner
+        };
+        self.plot_rects = vec![
+            (PlotKind::Temperature, temperature),
+            (PlotKind::Power, power),
+        ];
+    }
+
+    /// Where each plot was drawn on screen during the last frame.
+    pub fn plot_rects(&self) -> &[(PlotKind, Rect)] {
+        &self.plot_rects
+    }
+
+    /// A plot with the settings shared by all of the device plots. Axis labels are left out
+    /// in compact mode to leave more room for the data.
+    fn base_plot(
+        &self,
+        id: &str,
+        width: f32,
+        height: f32,
+        compact: bool,
+        y_label: &str,
+    ) -> Plot<'_> {
+        let plot = Plot::new(id)
+            .width(width)
+            .height(height)
+            .include_x(0)
+            .include_y(0)
             .allow_scroll(false)
             .legend(Legend::default())
-            .x_axis_label("measurements")
-            .y_axis_label("W")
-            .show_grid(false)
+            .show_grid(false);
+        if compact {
+            plot
+        } else {
+            plot.x_axis_label(self.x_axis_label()).y_axis_label(y_label)
+        }
+    }
+
+    fn temperature_plot_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        width: f32,
+        height: f32,
+        compact: bool,
+    ) -> Rect {
+        let aggregation = self.aggregation;
+        let dark_mode = ui.visuals().dark_mode;
+        // Shared by the memory temperature series and its slowdown line
+        let memory_temperature_color = Series::MemoryTemperature.color(dark_mode);
+        let snap_to_live = std::mem::take(&mut self.snap_to_live);
+        let mut follow_live = self.follow_live;
+        let response = self
+            .base_plot("temperature", width, height, compact, "deg")
+            .include_y(self.max_temperature)
+            .include_y(self.shutdown_temperature.unwrap_or(0))
+            .include_y(self.memory_slowdown_temperature.unwrap_or(0))
+            .allow_zoom(true)
+            .allow_drag(!self.editing_thresholds)
             .show(ui, |plot_ui| {
-                let power_usage_points: PlotPoints = self
-                    .power_usage_vals
-                    .iter()
-                    .enumerate()
-                    .map(|(i, &power_usage)| [i as f64, power_usage as f64])
-                    .collect();
+                // The plot fits all samples until it is dragged or zoomed, which shows up here
+                // a frame later
+                if snap_to_live {
+                    plot_ui.set_auto_bounds(egui::Vec2b::TRUE);
+                }
+                follow_live = plot_ui.auto_bounds().any();
+                let temperature_points = self
+                    .series_points(self.temperature_vals.iter().map(|temp| temp.map(f64::from)));
+                draw_series(
+                    plot_ui,
+                    &temperature_points,
+                    aggregation,
+                    self.series_style,
+                    "GPU Temperature",
+                    Series::Temperature.color(dark_mode),
+                );
 
-                plot_ui.line(
-                    Line::new(power_usage_points)
-                        .name("Power Usage")
-                        .color(Color32::from_rgb(207, 184, 54)),
+                let memory_temperature_points = self.series_points(
+                    self.memory_temperature_vals
+                        .iter()
+                        .map(|&temp| temp.map(|temp| temp as f64)),
                 );
+                if !memory_temperature_points.is_empty() {
+                    draw_series(
+                        plot_ui,
+                        &memory_temperature_points,
+                        aggregation,
+                        self.series_style,
+                        "Memory Temperature",
+                        memory_temperature_color,
+                    );
+                    // Memory usually runs hotter than the core and has a limit of its own
+                    if let Some(memory_slowdown_temperature) = self.memory_slowdown_temperature {
+                        plot_ui.hline(
+                            HLine::new(memory_slowdown_temperature)
+                                .name("Memory slowdown")
+                                .color(memory_temperature_color)
+                                .style(LineStyle::dashed_loose()),
+                        );
+                    }
+                }
+
+                if let Some(slowdown_temperature) = self.slowdown_temperature {
+                    plot_ui.hline(
+                        HLine::new(slowdown_temperature)
+                            .name("GPU slowdown")
+                            .color(Color32::from_rgb(230, 150, 30)),
+                    );
+                }
+                if let Some(shutdown_temperature) = self.shutdown_temperature {
+                    plot_ui.hline(
+                        HLine::new(shutdown_temperature)
+                            .name("Shutdown")
+                            .color(Color32::from_rgb(200, 30, 30)),
+                    );
+                }
+                self.threshold_lines
+                    .temperature
+                    .filter(|_| self.editing_thresholds)
+                    .map(|threshold| threshold_line_ui(plot_ui, threshold, 1.0, "Alert threshold"))
             });
+        self.follow_live = follow_live;
+        self.apply_threshold_edit(response.inner, |lines| &mut lines.temperature);
+        response.response.rect
+    }
+
+    /// Stores a threshold dragged to `edited`, if it moved.
+    fn apply_threshold_edit(
+        &mut self,
+        edited: Option<f64>,
+        threshold: impl FnOnce(&mut ThresholdLines) -> &mut Option<f64>,
+    ) {
+        let threshold = threshold(&mut self.threshold_lines);
+        if edited.is_some() && edited != *threshold {
+            *threshold = edited;
+            self.thresholds_edited = true;
+        }
+    }
+
+    fn memory_plot_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        width: f32,
+        height: f32,
+        compact: bool,
+    ) -> Rect {
+        let aggregation = self.aggregation;
+        let dark_mode = ui.visuals().dark_mode;
+        // The buffers hold MiB, which are converted to the selected unit for display
+        let unit = self.memory_unit.unit_for(self.max_memory_usage * MIB);
+        let to_unit = |mib: u64| unit.convert((mib * MIB) as f64);
+        let y_max = if self.pin_memory_to_total {
+            to_unit(self.max_memory_usage)
+        } else {
+            0.0
+        };
+        let response = self
+            .base_plot("memory usage", width, height, compact, unit.label)
+            .include_y(y_max)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .show(ui, |plot_ui| {
+                let memory_usage_points = self.series_points(
+                    self.memory_usage_vals
+                        .iter()
+                        .map(|mem_usage| mem_usage.map(to_unit)),
+                );
+                let warning = self.memory_warning_threshold.is_some_and(|threshold| {
+                    self.memory_usage_vals
+                        .back()
+                        .is_some_and(|&mem_usage| mem_usage.is_some_and(|used| used >= threshold))
+                });
+                draw_series(
+                    plot_ui,
+                    &memory_usage_points,
+                    aggregation,
+                    self.series_style,
+                    "Memory Usage",
+                    if warning {
+                        Color32::RED
+                    } else {
+                        Series::Memory.color(dark_mode)
+                    },
+                );
+
+                match self.threshold_lines.memory {
+                    Some(threshold) if self.editing_thresholds => {
+                        let mib = unit.convert(MIB as f64);
+                        let step = to_unit(self.max_memory_usage / 100);
+                        let edited =
+                            threshold_line_ui(plot_ui, threshold * mib, step, "Alert threshold");
+                        Some(edited / mib)
+                    }
+                    _ => {
+                        if let Some(threshold) = self.memory_warning_threshold {
+                            plot_ui.hline(
+                                HLine::new(to_unit(threshold))
+                                    .name("Warning")
+                                    .color(THRESHOLD_COLOR),
+                            );
+                        }
+                        None
+                    }
+                }
+            });
+        self.apply_threshold_edit(response.inner, |lines| &mut lines.memory);
+        response.response.rect
+    }
+
+    fn power_plot_ui(&mut self, ui: &mut egui::Ui, width: f32, height: f32, compact: bool) -> Rect {
+        let aggregation = self.aggregation;
+        let dark_mode = ui.visuals().dark_mode;
+        let response = self
+            .base_plot("power usage", width, height, compact, "W")
+            .include_y(self.max_power_usage as f64)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .show(ui, |plot_ui| {
+                let throttled_points = self.series_points(
+                    self.throttled_vals
+                        .iter()
+                        .map(|&throttled| Some(if throttled { 1.0 } else { 0.0 })),
+                );
+                for (start, end) in throttled_ranges(&throttled_points) {
+                    let top = self.max_power_usage as f64;
+                    plot_ui.polygon(
+                        Polygon::new(vec![[start, 0.0], [end, 0.0], [end, top], [start, top]])
+                            .fill_color(Color32::from_rgba_unmultiplied(220, 50, 50, 40))
+                            .stroke(Stroke::new(0.0, Color32::RED))
+                            .name("Throttled"),
+                    );
+                }
+                let power_usage_points = self.series_points(
+                    self.power_usage_vals
+                        .iter()
+                        .map(|milliwatts| milliwatts.map(milliwatts_to_watts)),
+                );
+                draw_series(
+                    plot_ui,
+                    &power_usage_points,
+                    aggregation,
+                    self.series_style,
+                    "Power Usage",
+                    Series::Power.color(dark_mode),
+                );
+                self.threshold_lines
+                    .power
+                    .filter(|_| self.editing_thresholds)
+                    .map(|threshold| threshold_line_ui(plot_ui, threshold, 1.0, "Alert threshold"))
+            });
+        self.apply_threshold_edit(response.inner, |lines| &mut lines.power);
+        response.response.rect
+    }
+
+    /// The GPU and memory utilization, to tell compute bound phases from memory bound ones.
+    fn utilization_plot_ui(
+        &self,
+        ui: &mut egui::Ui,
+        width: f32,
+        height: f32,
+        compact: bool,
+    ) -> Rect {
+        let aggregation = self.aggregation;
+        let dark_mode = ui.visuals().dark_mode;
+        self.base_plot("utilization", width, height, compact, "%")
+            .include_y(100)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .show(ui, |plot_ui| {
+                let gpu_points = self.series_points(
+                    self.utilization_vals
+                        .iter()
+                        .map(|utilization| utilization.map(f64::from)),
+                );
+                draw_series(
+                    plot_ui,
+                    &gpu_points,
+                    aggregation,
+                    self.series_style,
+                    "GPU",
+                    Series::GpuUtilization.color(dark_mode),
+                );
+                let memory_points = self.series_points(
+                    self.memory_utilization_vals
+                        .iter()
+                        .map(|utilization| utilization.map(f64::from)),
+                );
+                draw_series(
+                    plot_ui,
+                    &memory_points,
+                    aggregation,
+                    self.series_style,
+                    "Memory",
+                    Series::MemoryUtilization.color(dark_mode),
+                );
+            })
+            .response
+            .rect
+    }
+
+    /// A plot of the registry metric at `index` into [`METRICS`].
+    fn metric_plot_ui(
+        &self,
+        index: usize,
+        ui: &mut egui::Ui,
+        width: f32,
+        height: f32,
+        compact: bool,
+    ) -> Rect {
+        let metric = &METRICS[index];
+        let color = metric
+            .plot
+            .unwrap_or(Series::Clock)
+            .color(ui.visuals().dark_mode);
+        let aggregation = self.aggregation;
+        self.base_plot(metric.id, width, height, compact, metric.unit)
+            .allow_zoom(false)
+            .allow_drag(false)
+            .show(ui, |plot_ui| {
+                let points =
+                    self.series_points(self.metric_vals.iter().map(|values| values[index]));
+                draw_series(
+                    plot_ui,
+                    &points,
+                    aggregation,
+                    self.series_style,
+                    metric.name,
+                    color,
+                );
+            })
+            .response
+            .rect
+    }
+
+    /// Utilization per watt, a rough proxy for performance per watt.
+    fn efficiency_plot_ui(
+        &self,
+        ui: &mut egui::Ui,
+        width: f32,
+        height: f32,
+        compact: bool,
+    ) -> Rect {
+        let aggregation = self.aggregation;
+        let dark_mode = ui.visuals().dark_mode;
+        self.base_plot("efficiency", width, height, compact, "%/W")
+            .allow_zoom(false)
+            .allow_drag(false)
+            .show(ui, |plot_ui| {
+                let efficiency_points = self.series_points(
+                    self.utilization_vals
+                        .iter()
+                        .zip(self.power_usage_vals.iter())
+                        .map(|(&utilization, &power_usage)| efficiency(utilization?, power_usage?)),
+                );
+                draw_series(
+                    plot_ui,
+                    &efficiency_points,
+                    aggregation,
+                    self.series_style,
+                    "Utilization per Watt",
+                    Series::Efficiency.color(dark_mode),
+                );
+            })
+            .response
+            .rect
+    }
+}
+
+/// Counts the values falling into each `bin_width` wide bin. Returns the lower edge of each
+/// non-empty bin with its share of all values in percent, in ascending order.
+pub fn histogram(values: impl Iterator<Item = f64>, bin_width: f64) -> Vec<[f64; 2]> {
+    let mut bins: BTreeMap<i64, usize> = BTreeMap::new();
+    let mut total = 0;
+    for value in values {
+        *bins.entry((value / bin_width).floor() as i64).or_default() += 1;
+        total += 1;
+    }
+    bins.into_iter()
+        .map(|(bin, count)| [bin as f64 * bin_width, count as f64 / total as f64 * 100.0])
+        .collect()
+}
+
+fn histogram_chart(bins: &[[f64; 2]], bin_width: f64) -> BarChart {
+    let bars = bins
+        .iter()
+        .map(|&[start, percent]| Bar::new(start + bin_width / 2.0, percent).width(bin_width))
+        .collect();
+    BarChart::new(bars)
+}
+
+/// A plot for a histogram, with `x_label` as the unit of the binned values.
+fn histogram_plot<'a>(
+    id: &str,
+    x_label: &'a str,
+    width: f32,
+    height: f32,
+    compact: bool,
+) -> Plot<'a> {
+    let plot = Plot::new(id)
+        .width(width)
+        .height(height)
+        .include_y(0)
+        .allow_zoom(false)
+        .allow_drag(false)
+        .allow_scroll(false)
+        .legend(Legend::default())
+        .show_grid(false);
+    if compact {
+        plot
+    } else {
+        plot.x_axis_label(x_label).y_axis_label("% of samples")
+    }
+}
+
+/// The x ranges of consecutive points with a non-zero value. Each range reaches up to the
+/// point after it, so that a single throttled sample still covers some width.
+fn throttled_ranges(points: &[[f64; 2]]) -> Vec<(f64, f64)> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for &[x, value] in points {
+        if value != 0.0 {
+            start.get_or_insert(x);
+        } else if let Some(start) = start.take() {
+            ranges.push((start, x));
+        }
+    }
+    if let (Some(start), Some(&[end, _])) = (start, points.last()) {
+        ranges.push((start, end));
+    }
+    ranges
+}
+
+/// Which way a metric has been going lately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Rising,
+    Falling,
+    Stable,
+}
+
+impl Display for Trend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trend::Rising => write!(f, "▲"),
+            Trend::Falling => write!(f, "▼"),
+            Trend::Stable => write!(f, "▬"),
+        }
+    }
+}
+
+/// Compares `latest` to the average of the `previous` values, which are stable while within
+/// `tolerance` of it. `None` without previous values.
+pub fn trend(latest: f64, previous: impl Iterator<Item = f64>, tolerance: f64) -> Option<Trend> {
+    let (sum, count) = previous.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 {
+        return None;
+    }
+    let average = sum / count as f64;
+    Some(if latest > average + tolerance {
+        Trend::Rising
+    } else if latest < average - tolerance {
+        Trend::Falling
+    } else {
+        Trend::Stable
+    })
+}
+
+/// When the line fitted through the `(time, value)` points reaches `limit`, counted from
+/// the latest point. `None` if it is already reached, the values aren't rising, or it is
+/// further out than `MAX_THROTTLE_FORECAST`.
+fn time_to_limit(points: &[(f64, f64)], limit: f64) -> Option<Duration> {
+    // A handful of samples over a few seconds is too noisy to extrapolate from
+    let first_time = points.iter().map(|(time, _)| *time).reduce(f64::min)?;
+    let (latest_time, latest) = *points.iter().max_by(|a, b| a.0.total_cmp(&b.0))?;
+    if points.len() < 3 || latest_time - first_time < TREND_WINDOW.as_secs_f64() {
+        return None;
+    }
+    let count = points.len() as f64;
+    let mean_time = points.iter().map(|(time, _)| time).sum::<f64>() / count;
+    let mean_value = points.iter().map(|(_, value)| value).sum::<f64>() / count;
+    let (covariance, variance) =
+        points
+            .iter()
+            .fold((0.0, 0.0), |(covariance, variance), (time, value)| {
+                let dt = time - mean_time;
+                (covariance + dt * (value - mean_value), variance + dt * dt)
+            });
+    let slope = covariance / variance;
+    if slope < MIN_THROTTLE_FORECAST_SLOPE || latest >= limit {
+        return None;
+    }
+    // From the fitted line at the latest time, which smooths out the odd jump in the readings
+    let fitted = mean_value + slope * (latest_time - mean_time);
+    let seconds = ((limit - fitted) / slope).max(0.0);
+    Some(Duration::from_secs_f64(seconds)).filter(|&time| time <= MAX_THROTTLE_FORECAST)
+}
+
+/// A rough countdown like "~90 s" or "~4 min".
+pub fn format_time_to_throttle(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 120 {
+        format!("~{seconds} s")
+    } else {
+        format!("~{} min", (seconds + 30) / 60)
+    }
+}
+
+/// Appends the trend arrow to a readout, e.g. "Temperature: 54°C ▲".
+pub fn with_trend(text: String, trend: Option<Trend>) -> String {
+    match trend {
+        Some(trend) => format!("{text} {trend}"),
+        None => text,
+    }
+}
+
+/// Why a fan is flagged as possibly failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanAnomaly {
+    /// The fan stood still while the GPU was hot.
+    StoppedWhileHot,
+    /// The fan ran much slower than the other fans of the same GPU.
+    SlowerThanSiblings,
+}
+
+impl Display for FanAnomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FanAnomaly::StoppedWhileHot => write!(f, "Stopped while the GPU is hot"),
+            FanAnomaly::SlowerThanSiblings => write!(f, "Much slower than the other fans"),
+        }
+    }
+}
+
+/// Recent fan speed readings of a device, used to spot fans that may be failing.
+#[derive(Debug, Clone, Default)]
+pub struct FanHistory {
+    /// Seconds since the start of the session and the speed of each fan at that time.
+    readings: CircularBuffer<5000, (f64, Vec<Option<u32>>)>,
+}
+
+impl FanHistory {
+    pub fn push(&mut self, time: f64, fan_speeds: &[Option<u32>]) {
+        self.readings.push_back((time, fan_speeds.to_vec()));
+    }
+
+    pub fn clear(&mut self) {
+        self.readings.clear();
+    }
+
+    /// Checks each fan's readings over the `FAN_ANOMALY_WINDOW`, given the current GPU
+    /// temperature. Nothing is flagged until the history covers the whole window.
+    pub fn anomalies(&self, temperature: Option<u32>) -> Vec<Option<FanAnomaly>> {
+        let (Some((first_time, _)), Some((latest_time, latest))) =
+            (self.readings.front(), self.readings.back())
+        else {
+            return Vec::new();
+        };
+        let window = FAN_ANOMALY_WINDOW.as_secs_f64();
+        if latest_time - first_time < window {
+            return vec![None; latest.len()];
+        }
+        let recent: Vec<&[Option<u32>]> = self
+            .readings
+            .iter()
+            .rev()
+            .take_while(|(time, _)| latest_time - time <= window)
+            .map(|(_, fan_speeds)| fan_speeds.as_slice())
+            .collect();
+        // Average speed of each fan over the window, `None` for fans that can't be read
+        let averages: Vec<Option<f64>> = (0..latest.len())
+            .map(|fan| {
+                let (sum, count) = recent
+                    .iter()
+                    .filter_map(|fan_speeds| fan_speeds.get(fan).copied().flatten())
+                    .fold((0.0, 0), |(sum, count), speed| {
+                        (sum + speed as f64, count + 1)
+                    });
+                (count > 0).then(|| sum / count as f64)
+            })
+            .collect();
+        let hot = temperature.is_some_and(|temperature| temperature >= FAN_STOPPED_TEMPERATURE);
+
+        averages
+            .iter()
+            .enumerate()
+            .map(|(fan, average)| {
+                let average = (*average)?;
+                if hot && average == 0.0 {
+                    return Some(FanAnomaly::StoppedWhileHot);
+                }
+                let (sum, count) = averages
+                    .iter()
+                    .enumerate()
+                    .filter(|&(sibling, _)| sibling != fan)
+                    .filter_map(|(_, average)| *average)
+                    .fold((0.0, 0), |(sum, count), average| (sum + average, count + 1));
+                (count > 0 && sum / count as f64 - average > FAN_DEVIATION_LIMIT)
+                    .then_some(FanAnomaly::SlowerThanSiblings)
+            })
+            .collect()
+    }
+}
+
+/// GPU utilization in percent divided by the power usage in W, or `None` while the
+/// power usage reads zero.
+fn efficiency(utilization: u32, power_usage_mw: u32) -> Option<f64> {
+    (power_usage_mw > 0).then(|| utilization as f64 / milliwatts_to_watts(power_usage_mw))
+}
+
+/// Whether `ui` is too narrow for the side by side layouts.
+pub fn is_compact(ui: &egui::Ui) -> bool {
+    ui.available_width() < COMPACT_WIDTH
+}
+
+/// Energy in Wh used between two readings of the millijoule energy counter.
+pub fn energy_wh(baseline_mj: u64, current_mj: u64) -> f64 {
+    current_mj.saturating_sub(baseline_mj) as f64 / 3_600_000.0
+}
+
+/// Formats the compute capability and architecture like "Compute 8.9 (Ada)".
+pub fn format_compute_capability(device_state: &DeviceState) -> Option<String> {
+    let (major, minor) = device_state.compute_capability?;
+    Some(match &device_state.architecture {
+        Some(architecture) => format!("Compute {major}.{minor} ({architecture})"),
+        None => format!("Compute {major}.{minor}"),
+    })
+}
+
+/// The number of columns and rows for tiling `count` plots in a grid that is about as wide as
+/// it is high.
+pub fn grid_dimensions(count: usize) -> (usize, usize) {
+    if count == 0 {
+        return (0, 0);
+    }
+    let columns = (count as f64).sqrt().ceil() as usize;
+    (columns, count.div_ceil(columns))
+}
+
+fn memory_percentage(mem_info: &MemoryInfo) -> f64 {
+    if mem_info.total > 0 {
+        mem_info.used as f64 / mem_info.total as f64 * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// The readings that matter for compute work laid out in a dense grid, for the server profile.
+pub fn server_view_ui(
+    ui: &mut egui::Ui,
+    device_state: &DeviceState,
+    thresholds: &Thresholds,
+    memory_unit: UnitSystem,
+    smoothed_power: Option<f64>,
+) {
+    let warning_color = ui.visuals().warn_fg_color;
+    let value = |text: String, warning: bool| {
+        let text = RichText::new(text).strong();
+        if warning {
+            text.color(warning_color)
+        } else {
+            text
+        }
+    };
+    let power = match device_state.power_limit {
+        Some(power_limit) => format!(
+            "{} / {}",
+            format_power(device_state, smoothed_power),
+            format_watts(milliwatts_to_watts(power_limit))
+        ),
+        None => format_power(device_state, smoothed_power),
+    };
+    let na = || String::from("N/A");
+    let (ecc_corrected, ecc_uncorrected) = match device_state.ecc_errors {
+        Some(errors) => (
+            format_number(errors.corrected),
+            format_number(errors.uncorrected),
+        ),
+        None => (na(), na()),
+    };
+
+    egui::Grid::new("server view")
+        .num_columns(4)
+        .spacing([20.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("GPU utilization");
+            ui.label(value(
+                format_metric(gpu_utilization(device_state), "%"),
+                false,
+            ));
+            ui.label("Memory utilization");
+            ui.label(value(
+                format_metric(
+                    device_state
+                        .utilization
+                        .as_ref()
+                        .map(|utilization| utilization.memory),
+                    "%",
+                ),
+                false,
+            ));
+            ui.end_row();
+
+            ui.label("Memory");
+            ui.label(value(
+                device_state.mem_info.as_ref().map_or_else(na, |mem_info| {
+                    format!(
+                        "{} / {} ({:.0}%)",
+                        format_bytes(mem_info.used, memory_unit),
+                        format_bytes(mem_info.total, memory_unit),
+                        memory_percentage(mem_info)
+                    )
+                }),
+                thresholds.memory_exceeded(device_state),
+            ));
+            ui.label("Power");
+            ui.label(value(power, thresholds.power_exceeded(device_state)));
+            ui.end_row();
+
+            ui.label(temperature_label(thresholds));
+            ui.label(value(
+                format_metric(thresholds.temperature_sensor.read(device_state), "°C"),
+                thresholds.temperature_exceeded(device_state),
+            ));
+            ui.label("Performance state");
+            ui.label(value(
+                device_state
+                    .pstate
+                    .map_or_else(|| String::from("N/A"), |pstate| format!("P{pstate}")),
+                false,
+            ));
+            ui.end_row();
+
+            ui.label("ECC errors, corrected");
+            ui.label(value(ecc_corrected, false));
+            ui.label("ECC errors, uncorrected");
+            ui.label(value(
+                ecc_uncorrected,
+                device_state
+                    .ecc_errors
+                    .is_some_and(|errors| errors.uncorrected > 0),
+            ))
+            .on_hover_text("Since the driver was loaded");
+            ui.end_row();
+        });
+}
+
+/// The name of the temperature the thresholds watch.
+fn temperature_label(thresholds: &Thresholds) -> &'static str {
+    match thresholds.temperature_sensor {
+        TemperatureSensor::Core => "Temperature",
+        TemperatureSensor::Memory => "Memory temperature",
+    }
+}
+
+/// Large readouts of the most important numbers, for glancing at from across the room.
+pub fn dashboard_ui(
+    ui: &mut egui::Ui,
+    device_state: &DeviceState,
+    device_color: Color32,
+    thresholds: &Thresholds,
+    memory_unit: UnitSystem,
+    smoothed_power: Option<f64>,
+) {
+    ui.horizontal(|ui| {
+        ui.heading(RichText::new("●").color(device_color));
+        ui.heading(&device_state.name);
+    });
+    ui.add_space(10.0);
+
+    let memory = device_state.mem_info.as_ref().map_or_else(
+        || String::from("N/A"),
+        |mem_info| {
+            format!(
+                "{} ({:.0}%)",
+                format_bytes(mem_info.used, memory_unit),
+                memory_percentage(mem_info)
+            )
+        },
+    );
+    let readouts = [
+        (
+            temperature_label(thresholds),
+            format_metric(thresholds.temperature_sensor.read(device_state), "°C"),
+            thresholds.temperature_exceeded(device_state),
+        ),
+        (
+            "Power",
+            format_power(device_state, smoothed_power),
+            thresholds.power_exceeded(device_state),
+        ),
+        (
+            "GPU utilization",
+            format_metric(gpu_utilization(device_state), "%"),
+            false,
+        ),
+        ("Memory", memory, thresholds.memory_exceeded(device_state)),
+    ];
+
+    ui.horizontal_wrapped(|ui| {
+        for (caption, value, warning) in readouts {
+            ui.group(|ui| {
+                ui.set_min_width(DASHBOARD_TILE_WIDTH);
+                ui.vertical(|ui| {
+                    ui.label(caption);
+                    let mut text = RichText::new(value).size(DASHBOARD_FONT_SIZE).strong();
+                    if warning {
+                        text = text.color(Color32::RED);
+                    }
+                    ui.label(text);
+                });
+            });
+        }
+    });
+}
+
+/// Draws a series either as a downsampled line, or as its bucket averages on top of a
+/// band spanning each bucket's min and max.
+fn draw_series(
+    plot_ui: &mut egui_plot::PlotUi,
+    points: &[[f64; 2]],
+    aggregation: Aggregation,
+    style: SeriesStyle,
+    name: &str,
+    color: Color32,
+) {
+    let line = |points: Vec<[f64; 2]>| {
+        let line = Line::new(PlotPoints::from(points))
+            .name(name)
+            .color(color)
+            .width(style.width);
+        if style.fill {
+            line.fill(0.0)
+        } else {
+            line
+        }
+    };
+    let Some(bucket_width) = aggregation.bucket_seconds() else {
+        plot_ui.line(line(downsample_lttb(points, MAX_PLOT_POINTS)));
+        return;
+    };
+
+    let buckets = aggregate(points, bucket_width);
+    let band_color = color.gamma_multiply(0.3);
+    // One quad per pair of neighbouring buckets, since egui_plot can only fill convex polygons
+    for pair in buckets.windows(2) {
+        let [a, b] = [pair[0], pair[1]];
+        plot_ui.polygon(
+            Polygon::new(PlotPoints::from(vec![
+                [a.time, a.min],
+                [b.time, b.min],
+                [b.time, b.max],
+                [a.time, a.max],
+            ]))
+            .fill_color(band_color)
+            // A zero width stroke in the series color, plus the shared name, makes the band
+            // and the average line share a single legend entry that hides both
+            .stroke(Stroke::new(0.0, color))
+            .name(name),
+        );
+    }
+
+    let averages: Vec<[f64; 2]> = buckets
+        .iter()
+        .map(|bucket| [bucket.time, bucket.avg])
+        .collect();
+    plot_ui.line(line(averages));
+}
+
+/// The alert thresholds drawn on the plots while editing them, in the plots' units: °C, MiB
+/// and W. `None` for disabled thresholds.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ThresholdLines {
+    pub temperature: Option<f64>,
+    pub memory: Option<f64>,
+    pub power: Option<f64>,
+}
+
+/// Draws a threshold at `value` that can be dragged up and down, or moved by `step` with the
+/// arrow keys while the plot is hovered. Returns where it was moved to.
+fn threshold_line_ui(plot_ui: &mut PlotUi, value: f64, step: f64, name: &str) -> f64 {
+    let response = plot_ui.response().clone();
+    let ctx = plot_ui.ctx().clone();
+    let line_y = plot_ui.screen_from_plot(PlotPoint::new(0.0, value)).y;
+    let near_line = |pos: Option<egui::Pos2>| {
+        pos.is_some_and(|pos| (pos.y - line_y).abs() <= THRESHOLD_GRAB_DISTANCE)
+    };
+
+    // Whether the drag started on the line has to be remembered while it goes on
+    let id = response.id.with("dragged threshold");
+    let dragging = if response.drag_started() {
+        near_line(response.interact_pointer_pos())
+    } else {
+        response.dragged() && ctx.data(|data| data.get_temp(id)).unwrap_or(false)
+    };
+    ctx.data_mut(|data| data.insert_temp(id, dragging));
+
+    let mut value = value;
+    if dragging {
+        if let Some(pointer) = plot_ui.pointer_coordinate() {
+            value = pointer.y;
+        }
+    }
+    if response.hovered() {
+        let (up, down) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+            )
+        });
+        if up {
+            value += step;
+        }
+        if down {
+            value -= step;
+        }
+    }
+    if dragging || near_line(response.hover_pos()) {
+        ctx.set_cursor_icon(egui::CursorIcon::ResizeVertical);
+    }
+
+    let value = value.max(0.0);
+    plot_ui.hline(
+        HLine::new(value)
+            .name(name)
+            .color(THRESHOLD_COLOR)
+            .width(if dragging { 3.0 } else { 2.0 })
+            .style(LineStyle::dashed_dense()),
+    );
+    value
+}
+
+/// How the plotted series are drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeriesStyle {
+    /// Line width in points.
+    pub width: f32,
+    /// Fill the area between the line and zero.
+    pub fill: bool,
+}
+
+impl Default for SeriesStyle {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            fill: false,
+        }
+    }
+}
+
+/// What happens to the plotted history once the buffers are full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RetentionPolicy {
+    /// Drop the oldest samples.
+    #[default]
+    Ring,
+    /// Keep the whole session, at a coarser resolution the older it gets.
+    Downsample,
+}
+
+/// The average of two readings, or the one that is there.
+fn mean<T: Into<u64> + TryFrom<u64>>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => T::try_from((a.into() + b.into()) / 2).ok(),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Merges the older half of `samples` in pairs, keeping the newer half as it is. A merged
+/// sample averages the pair, and counts as throttled if either one was.
+fn downsample_older_half(samples: &[(f64, Sample)]) -> Vec<(f64, Sample)> {
+    let (older, newer) = samples.split_at(samples.len() / 2);
+    older
+        .chunks(2)
+        .map(|pair| match pair {
+            [(time, a), (_, b)] => {
+                let sample = Sample {
+                    temperature: mean(a.temperature, b.temperature),
+                    memory_temperature: mean(a.memory_temperature, b.memory_temperature),
+                    memory_usage: mean(a.memory_usage, b.memory_usage),
+                    power_usage_mw: mean(a.power_usage_mw, b.power_usage_mw),
+                    gpu_utilization: mean(a.gpu_utilization, b.gpu_utilization),
+                    memory_utilization: mean(a.memory_utilization, b.memory_utilization),
+                    throttled: a.throttled || b.throttled,
+                    metrics: std::array::from_fn(|index| {
+                        match (a.metrics[index], b.metrics[index]) {
+                            (Some(a), Some(b)) => Some((a + b) / 2.0),
+                            (a, b) => a.or(b),
+                        }
+                    }),
+                };
+                // Like a bucket, the merged sample starts where the pair starts
+                (*time, sample)
+            }
+            _ => pair[0],
+        })
+        .chain(newer.iter().copied())
+        .collect()
+}
+
+/// The plots drawn by `DeviceStatsPlot::plot_ui`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlotKind {
+    Temperature,
+    Memory,
+    Power,
+    Utilization,
+    Efficiency,
+    /// The plot of the metric at this index into [`METRICS`].
+    Metric(usize),
+}
+
+impl PlotKind {
+    /// The built-in plots followed by those of the plotted registry metrics.
+    pub fn all() -> Vec<PlotKind> {
+        [
+            PlotKind::Temperature,
+            PlotKind::Memory,
+            PlotKind::Power,
+            PlotKind::Utilization,
+            PlotKind::Efficiency,
+        ]
+        .into_iter()
+        .chain(
+            (0..METRIC_COUNT)
+                .filter(|&index| METRICS[index].plot.is_some())
+                .map(PlotKind::Metric),
+        )
+        .collect()
+    }
+}
+
+impl Display for PlotKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlotKind::Temperature => write!(f, "Temperature"),
+            PlotKind::Memory => write!(f, "Memory"),
+            PlotKind::Power => write!(f, "Power"),
+            PlotKind::Utilization => write!(f, "Utilization"),
+            PlotKind::Efficiency => write!(f, "Efficiency"),
+            PlotKind::Metric(index) => write!(f, "{}", METRICS[*index].name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    None,
+    OneSecond,
+    TenSeconds,
+    OneMinute,
+}
+
+impl Aggregation {
+    pub const ALL: [Aggregation; 4] = [
+        Aggregation::None,
+        Aggregation::OneSecond,
+        Aggregation::TenSeconds,
+        Aggregation::OneMinute,
+    ];
+
+    pub fn bucket_seconds(&self) -> Option<f64> {
+        match self {
+            Aggregation::None => None,
+            Aggregation::OneSecond => Some(1.0),
+            Aggregation::TenSeconds => Some(10.0),
+            Aggregation::OneMinute => Some(60.0),
+        }
+    }
+}
+
+impl Display for Aggregation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Aggregation::None => write!(f, "None"),
+            Aggregation::OneSecond => write!(f, "1 s"),
+            Aggregation::TenSeconds => write!(f, "10 s"),
+            Aggregation::OneMinute => write!(f, "1 min"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bucket {
+    /// Start of the bucket, in the same unit as the input x coordinates.
+    pub time: f64,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+}
+
+/// Groups `points` (sorted by x) into buckets of `bucket_width` and computes the min, average
+/// and max of each bucket. Empty buckets are left out.
+pub fn aggregate(points: &[[f64; 2]], bucket_width: f64) -> Vec<Bucket> {
+    let mut buckets: Vec<Bucket> = Vec::new();
+    let mut count = 0;
+
+    for &[x, y] in points {
+        let time = (x / bucket_width).floor() * bucket_width;
+        match buckets.last_mut() {
+            Some(bucket) if bucket.time == time => {
+                bucket.min = bucket.min.min(y);
+                bucket.max = bucket.max.max(y);
+                // Keep a running sum in `avg` until the bucket is complete
+                bucket.avg += y;
+                count += 1;
+            }
+            _ => {
+                if let Some(bucket) = buckets.last_mut() {
+                    bucket.avg /= count as f64;
+                }
+                buckets.push(Bucket {
+                    time,
+                    min: y,
+                    avg: y,
+                    max: y,
+                });
+                count = 1;
+            }
+        }
+    }
+    if let Some(bucket) = buckets.last_mut() {
+        bucket.avg /= count as f64;
+    }
+
+    buckets
+}
+
+/// Downsamples `points` to at most `threshold` points using the Largest-Triangle-Three-Buckets
+/// algorithm, which keeps the visual shape of the line (including peaks) intact.
+///
+/// The first and last points are always kept. If there are fewer points than `threshold`
+/// they are returned as is.
+pub fn downsample_lttb(points: &[[f64; 2]], threshold: usize) -> Vec<[f64; 2]> {
+    if threshold < 3 || points.len() <= threshold {
+        return points.to_vec();
+    }
+
+    let mut sampled = Vec::with_capacity(threshold);
+    sampled.push(points[0]);
+
+    // Everything except the first and the last point is split into equally sized buckets
+    let bucket_size = (points.len() - 2) as f64 / (threshold - 2) as f64;
+    let mut selected = 0;
+
+    for bucket in 0..threshold - 2 {
+        let start = (bucket as f64 * bucket_size) as usize + 1;
+        let end = ((bucket + 1) as f64 * bucket_size) as usize + 1;
+
+        // Average of the next bucket, or the last point for the final bucket
+        let next_start = end;
+        let next_end = (((bucket + 2) as f64 * bucket_size) as usize + 1).min(points.len());
+        let next_len = (next_end - next_start) as f64;
+        let (avg_x, avg_y) = points[next_start..next_end]
+            .iter()
+            .fold((0.0, 0.0), |(x, y), point| (x + point[0], y + point[1]));
+        let (avg_x, avg_y) = (avg_x / next_len, avg_y / next_len);
+
+        let [ax, ay] = points[selected];
+        let mut max_area = -1.0;
+        for (i, &[bx, by]) in points.iter().enumerate().take(end).skip(start) {
+            let area = ((ax - avg_x) * (by - ay) - (ax - bx) * (avg_y - ay)).abs();
+            if area > max_area {
+                max_area = area;
+                selected = i;
+            }
+        }
+        sampled.push(points[selected]);
+    }
+
+    sampled.push(points[points.len() - 1]);
+    sampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trend() {
+        let previous = [50.0, 52.0, 54.0];
+        assert_eq!(trend(60.0, previous.into_iter(), 1.0), Some(Trend::Rising));
+        assert_eq!(trend(45.0, previous.into_iter(), 1.0), Some(Trend::Falling));
+        assert_eq!(trend(52.5, previous.into_iter(), 1.0), Some(Trend::Stable));
+        assert_eq!(trend(52.0, std::iter::empty(), 1.0), None);
+    }
+
+    #[test]
+    fn test_ema() {
+        let mut ema = Ema::default();
+        assert_eq!(ema.value(), None);
+        assert_eq!(ema.update(0.0, 100.0, 2.0), 100.0);
+        // One time constant later the average has moved ~63% of the way
+        let average = ema.update(2.0, 200.0, 2.0);
+        assert!((average - 163.2).abs() < 0.1, "{average}");
+        // Without a time constant the average follows the samples
+        assert_eq!(ema.update(3.0, 50.0, 0.0), 50.0);
+        ema.reset();
+        assert_eq!(ema.value(), None);
+    }
+
+    #[test]
+    fn test_fan_anomalies() {
+        let history_of = |temperature: u32, readings: &[[Option<u32>; 2]]| {
+            let mut history = FanHistory::default();
+            for (i, fan_speeds) in readings.iter().enumerate() {
+                history.push(i as f64, fan_speeds);
+            }
+            history.anomalies(Some(temperature))
+        };
+        let healthy = [[Some(60), Some(62)]; 12];
+        assert_eq!(history_of(80, &healthy), vec![None, None]);
+        // Too short a history to judge
+        assert_eq!(history_of(80, &[[Some(0), Some(60)]; 5]), vec![None, None]);
+
+        let stopped = [[Some(0), Some(60)]; 12];
+        assert_eq!(
+            history_of(80, &stopped),
+            vec![Some(FanAnomaly::StoppedWhileHot), None]
+        );
+        // A stopped fan on a cool GPU is still much slower than its sibling
+        assert_eq!(
+            history_of(40, &stopped),
+            vec![Some(FanAnomaly::SlowerThanSiblings), None]
+        );
+        assert_eq!(history_of(40, &[[Some(0), None]; 12]), vec![None, None]);
+    }
+
+    #[test]
+    fn test_grid_dimensions() {
+        assert_eq!(grid_dimensions(0), (0, 0));
+        assert_eq!(grid_dimensions(1), (1, 1));
+        assert_eq!(grid_dimensions(2), (2, 1));
+        assert_eq!(grid_dimensions(3), (2, 2));
+        assert_eq!(grid_dimensions(8), (3, 3));
+        assert_eq!(grid_dimensions(9), (3, 3));
+        assert_eq!(grid_dimensions(10), (4, 3));
+    }
+
+    #[test]
+    fn test_format_clock_range() {
+        assert_eq!(format_clock_range(&[]), "N/A");
+        assert_eq!(format_clock_range(&[1500]), "1,500 MHz");
+        assert_eq!(format_clock_range(&[1500, 210, 2100]), "210 - 2,100 MHz");
+    }
+
+    #[test]
+    fn test_downsample_lttb() {
+        {
+            // Short input is returned untouched
+            let points: Vec<[f64; 2]> = (0..10).map(|i| [i as f64, i as f64]).collect();
+            assert_eq!(downsample_lttb(&points, 100), points);
+        }
+        {
+            let points: Vec<[f64; 2]> = (0..5000).map(|i| [i as f64, (i % 7) as f64]).collect();
+            let sampled = downsample_lttb(&points, 1000);
+            assert_eq!(sampled.len(), 1000);
+            assert_eq!(sampled.first(), points.first());
+            assert_eq!(sampled.last(), points.last());
+            assert!(sampled.windows(2).all(|pair| pair[0][0] < pair[1][0]));
+        }
+        {
+            // A single spike survives downsampling
+            let mut points: Vec<[f64; 2]> = (0..5000).map(|i| [i as f64, 0.0]).collect();
+            points[2500][1] = 100.0;
+            let sampled = downsample_lttb(&points, 1000);
+            assert!(sampled.iter().any(|point| point[1] == 100.0));
+        }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        assert!(aggregate(&[], 1.0).is_empty());
+
+        let points = [[0.0, 1.0], [0.5, 3.0], [1.2, 4.0], [3.9, 10.0], [3.1, 6.0]];
+        let buckets = aggregate(&points, 1.0);
+        assert_eq!(
+            buckets,
+            vec![
+                Bucket {
+                    time: 0.0,
+                    min: 1.0,
+                    avg: 2.0,
+                    max: 3.0
+                },
+                Bucket {
+                    time: 1.0,
+                    min: 4.0,
+                    avg: 4.0,
+                    max: 4.0
+                },
+                Bucket {
+                    time: 3.0,
+                    min: 6.0,
+                    avg: 8.0,
+                    max: 10.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_energy_wh() {
+        assert_eq!(energy_wh(1_000, 3_601_000), 1.0);
+        // The counter starts over when the driver is reloaded
+        assert_eq!(energy_wh(5_000, 1_000), 0.0);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let bins = histogram([40.0, 41.5, 41.0, 45.0].into_iter(), 1.0);
+        assert_eq!(bins, vec![[40.0, 25.0], [41.0, 50.0], [45.0, 25.0]]);
+        assert!(histogram(std::iter::empty(), 5.0).is_empty());
+    }
+
+    #[test]
+    fn test_device_color() {
+        assert_eq!(device_color(2), device_color(2));
+        assert_ne!(device_color(0), device_color(1));
+    }
+
+    #[test]
+    fn test_format_watts() {
+        assert_eq!(format_watts(milliwatts_to_watts(218_437)), "218.4 W");
+        assert_eq!(format_watts(milliwatts_to_watts(999)), "1.0 W");
+    }
+
+    #[test]
+    fn test_efficiency() {
+        assert_eq!(efficiency(50, 200_000), Some(0.25));
+        assert_eq!(efficiency(50, 0), None);
+    }
+
+    #[test]
+    fn test_throttled_ranges() {
+        let points = [
+            [0.0, 0.0],
+            [1.0, 1.0],
+            [2.0, 1.0],
+            [3.0, 0.0],
+            [4.0, 0.0],
+            [5.0, 1.0],
+        ];
+        assert_eq!(throttled_ranges(&points), vec![(1.0, 3.0), (5.0, 5.0)]);
+        assert_eq!(throttled_ranges(&[]), vec![]);
+    }
+
+    fn sample_with_temperature(temperature: u32) -> Sample {
+        Sample {
+            temperature: Some(temperature),
+            memory_temperature: None,
+            memory_usage: Some(0),
+            power_usage_mw: None,
+            gpu_utilization: Some(0),
+            memory_utilization: None,
+            throttled: temperature > 80,
+            metrics: [None; METRIC_COUNT],
+        }
+    }
+
+    #[test]
+    fn test_downsample_older_half() {
+        let samples: Vec<(f64, Sample)> = [40, 50, 60, 90, 70, 75]
+            .into_iter()
+            .enumerate()
+            .map(|(i, temperature)| (i as f64, sample_with_temperature(temperature)))
+            .collect();
+        let downsampled = downsample_older_half(&samples);
+        let temperatures: Vec<(f64, Option<u32>, bool)> = downsampled
+            .iter()
+            .map(|(time, sample)| (*time, sample.temperature, sample.throttled))
+            .collect();
+        assert_eq!(
+            temperatures,
+            vec![
+                (0.0, Some(45), false),
+                (2.0, Some(60), false),
+                (3.0, Some(90), true),
+                (4.0, Some(70), false),
+                (5.0, Some(75), false)
+            ]
+        );
+        assert!(downsampled
+            .iter()
+            .all(|(_, sample)| sample.power_usage_mw.is_none()));
+    }
+
+    #[test]
+    fn test_sample_leaves_unreported_readings_empty() {
+        let mut device_state = DeviceState::mock().with_temperature(60);
+        device_state.mem_info = None;
+        device_state.power_usage = None;
+        device_state.utilization = None;
+        let sample = Sample::from(&device_state);
+        assert_eq!(sample.temperature, Some(60));
+        assert_eq!(sample.memory_usage, None);
+        assert_eq!(sample.power_usage_mw, None);
+        assert_eq!(sample.gpu_utilization, None);
+        assert_eq!(sample.memory_utilization, None);
+
+        let mut plot = DeviceStatsPlot::default();
+        plot.push_sample(0.0, &sample);
+        assert_eq!(plot.samples()[0].1, sample);
+    }
+
+    #[test]
+    fn test_time_to_limit() {
+        let rising: Vec<(f64, f64)> = (0..=10).map(|i| (i as f64, 60.0 + i as f64)).collect();
+        assert_eq!(time_to_limit(&rising, 85.0), Some(Duration::from_secs(15)));
+        assert_eq!(time_to_limit(&rising, 70.0), None);
+        // Too far out, too short to tell and not rising
+        assert_eq!(time_to_limit(&rising, 2000.0), None);
+        assert_eq!(time_to_limit(&rising[..3], 85.0), None);
+        let stable: Vec<(f64, f64)> = (0..=10).map(|i| (i as f64, 60.0)).collect();
+        assert_eq!(time_to_limit(&stable, 85.0), None);
+        let falling: Vec<(f64, f64)> = rising.iter().map(|&(t, v)| (t, 140.0 - v)).collect();
+        assert_eq!(time_to_limit(&falling, 85.0), None);
+
+        assert_eq!(format_time_to_throttle(Duration::from_secs(90)), "~90 s");
+        assert_eq!(format_time_to_throttle(Duration::from_secs(250)), "~4 min");
+    }
+
+    #[test]
+    fn test_restore_history() {
+        let names = vec![String::from("GPU A"), String::from("GPU B")];
+        let mut view = DeviceView::default();
+        for i in 0..3 {
+            view.device_stats_plot(0)
+                .push_sample(i as f64, &sample_with_temperature(50 + i));
+            view.device_stats_plot(1)
+                .push_sample(i as f64, &sample_with_temperature(70));
+        }
+        let history = view.saved_history(4.0, &names);
+
+        let mut restored = DeviceView::default();
+        let changed = vec![String::from("GPU A"), String::from("GPU C")];
+        restored.restore_history(history.clone(), &changed);
+        let samples = restored.device_stats_plot(0).samples();
+        let times: Vec<f64> = samples.iter().map(|(time, _)| *time).collect();
+        assert_eq!(samples.len(), 3);
+        assert!(times.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(times[2] <= -2.0 && times[2] > -3.0);
+        assert_eq!(samples[2].1.temperature, Some(52));
+        assert!(restored.device_stats_plot(1).samples().is_empty());
+
+        let stale = SavedHistory {
+            saved_at: history.saved_at - MAX_RESTORE_AGE * 2,
+            ..history
+        };
+        let mut restored = DeviceView::default();
+        restored.restore_history(stale, &names);
+        assert!(restored.device_stats_plot(0).samples().is_empty());
+    }
+
+    #[test]
+    fn test_downsample_retention_keeps_first_sample() {
+        let mut plot = DeviceStatsPlot::default();
+        plot.set_retention_policy(RetentionPolicy::Downsample);
+        for i in 0..12_000 {
+            plot.push_sample(i as f64, &sample_with_temperature(50));
+        }
+        assert_eq!(plot.timestamps.front(), Some(&0.0));
+        assert_eq!(plot.timestamps.back(), Some(&11_999.0));
+
+        let mut plot = DeviceStatsPlot::default();
+        for i in 0..12_000 {
+            plot.push_sample(i as f64, &sample_with_temperature(50));
+        }
+        assert_eq!(plot.timestamps.front(), Some(&7_000.0));
     }
 }
@@ -0,0 +1,330 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use circular_buffer::CircularBuffer;
+use serde::Serialize;
+
+use crate::device::Sample;
+use crate::json;
+use crate::metric::METRICS;
+use crate::source::SystemState;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:9400";
+
+/// How long a client gets to send its whole request before the connection is dropped, so a
+/// client trickling in bytes can't keep its thread around.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The most bytes of request line and headers read from a client. Real requests to the API
+/// are a few hundred bytes.
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+
+/// The plotted history of one GPU.
+struct DeviceHistory {
+    name: String,
+    /// Seconds since the start of the session and the sample taken then, oldest first.
+    samples: Box<CircularBuffer<5000, (f64, Sample)>>,
+}
+
+/// The readings the server answers with, updated by the UI thread after every poll.
+#[derive(Default)]
+struct Shared {
+    system_state: Option<SystemState>,
+    /// Per GPU index, so that switching devices doesn't mix up their samples.
+    history: BTreeMap<usize, DeviceHistory>,
+}
+
+/// A tiny HTTP server serving the latest poll as JSON at `/api/state` and the plotted
+/// history at `/api/history`.
+pub struct HttpApi {
+    shared: Arc<Mutex<Shared>>,
+    address: SocketAddr,
+}
+
+impl HttpApi {
+    /// Binds `address` and starts answering requests on a background thread.
+    pub fn spawn(address: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        thread::Builder::new()
+            .name(String::from("http-api"))
+            .spawn({
+                let shared = Arc::clone(&shared);
+                move || serve(listener, shared)
+            })?;
+        Ok(Self { shared, address })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Publishes a poll of `device_index` taken `time` seconds after the start of the session.
+    pub fn push(&self, time: f64, device_index: usize, system_state: &SystemState) {
+        let mut shared = self.shared.lock().unwrap();
+        let history = shared
+            .history
+            .entry(device_index)
+            .or_insert_with(|| DeviceHistory {
+                name: String::new(),
+                // Too big for the stack
+                samples: CircularBuffer::boxed(),
+            });
+        history.name.clone_from(&system_state.device_state.name);
+        history
+            .samples
+            .push_back((time, Sample::from(&system_state.device_state)));
+        shared.system_state = Some(system_state.clone());
+    }
+}
+
+/// Answers every connection on a thread of its own, so a slow client doesn't hold up the
+/// others.
+fn serve(listener: TcpListener, shared: Arc<Mutex<Shared>>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                log::debug!("HTTP API connection failed: {err}");
+                continue;
+            }
+        };
+        let shared = Arc::clone(&shared);
+        let spawned = thread::Builder::new()
+            .name(String::from("http-api-connection"))
+            .spawn(move || {
+                if let Err(err) = handle_connection(stream, &shared) {
+                    log::debug!("HTTP API connection failed: {err}");
+                }
+            });
+        if let Err(err) = spawned {
+            log::warn!("Failed to answer an HTTP API connection: {err}");
+        }
+    }
+}
+
+/// Reads from a stream until a deadline, however the reads are spread out.
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "request took too long",
+            ));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, shared: &Mutex<Shared>) -> io::Result<()> {
+    let mut reader = BufReader::new(
+        DeadlineReader {
+            stream: &stream,
+            deadline: Instant::now() + REQUEST_TIMEOUT,
+        }
+        .take(MAX_REQUEST_BYTES),
+    );
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // None of the headers matter, but they have to be read before answering
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if reader.read_line(&mut header)? == 0 {
+            // Cut off by the size limit or the client
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request too large or incomplete",
+            ));
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+    }
+    drop(reader);
+
+    let (status, body) = respond(&request_line, shared);
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// The status line and body answering `request_line`, e.g. `GET /api/state HTTP/1.1`. The
+/// readings are copied out under the lock and serialized after it is released, so a slow
+/// answer doesn't hold up the UI thread pushing the next poll.
+fn respond(request_line: &str, shared: &Mutex<Shared>) -> (&'static str, String) {
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return ("400 Bad Request", error_json("malformed request"));
+    };
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+    match (method, path) {
+        ("GET", "/api/state") => {
+            let system_state = shared.lock().unwrap().system_state.clone();
+            ("200 OK", json::state_json(system_state.as_ref()))
+        }
+        ("GET", "/api/history") => {
+            let devices: Vec<HistoryJson> = shared
+                .lock()
+                .unwrap()
+                .history
+                .iter()
+                .map(|(&index, history)| HistoryJson {
+                    index,
+                    name: history.name.clone(),
+                    samples: history
+                        .samples
+                        .iter()
+                        .map(|&(time, sample)| SampleJson { time, sample })
+                        .collect(),
+                })
+                .collect();
+            let metrics = METRICS.iter().map(|metric| metric.id).collect();
+            ("200 OK", json::to_json(&History { metrics, devices }))
+        }
+        (_, "/api/state" | "/api/history") => (
+            "405 Method Not Allowed",
+            error_json("only GET is supported"),
+        ),
+        _ => ("404 Not Found", error_json("unknown endpoint")),
+    }
+}
+
+fn error_json(error: &str) -> String {
+    #[derive(Serialize)]
+    struct Error<'a> {
+        error: &'a str,
+    }
+    json::to_json(&Error { error })
+}
+
+#[derive(Serialize)]
+struct History {
+    /// The ids of the values in each sample's `metrics`, in order.
+    metrics: Vec<&'static str>,
+    devices: Vec<HistoryJson>,
+}
+
+#[derive(Serialize)]
+struct HistoryJson {
+    index: usize,
+    name: String,
+    samples: Vec<SampleJson>,
+}
+
+#[derive(Serialize)]
+struct SampleJson {
+    time: f64,
+    #[serde(flatten)]
+    sample: Sample,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::device::DeviceState;
+    use crate::process::ProcessState;
+
+    #[test]
+    fn test_respond() {
+        let shared = Mutex::new(Shared::default());
+        assert_eq!(
+            respond("GET /api/state HTTP/1.1\r\n", &shared),
+            ("200 OK", String::from("null"))
+        );
+        assert_eq!(
+            respond("GET /api/history?since=0 HTTP/1.1\r\n", &shared),
+            (
+                "200 OK",
+                String::from(
+                    r#"{"metrics":["graphics_clock_mhz","sm_clock_mhz","memory_clock_mhz","video_clock_mhz"],"devices":[]}"#
+                )
+            )
+        );
+        assert_eq!(
+            respond("POST /api/state HTTP/1.1", &shared).0,
+            "405 Method Not Allowed"
+        );
+        assert_eq!(respond("GET /metrics HTTP/1.1", &shared).0, "404 Not Found");
+        assert_eq!(respond("", &shared).0, "400 Bad Request");
+    }
+
+    #[test]
+    fn test_history_per_device() {
+        let http_api = HttpApi::spawn("127.0.0.1:0").unwrap();
+        for (time, device_index, temperature) in [(0.0, 0, 40), (1.0, 1, 70), (2.0, 0, 41)] {
+            let system_state = SystemState {
+                device_state: DeviceState::mock().with_temperature(temperature),
+                process_state: ProcessState::from_lists(Vec::new(), Vec::new()),
+            };
+            http_api.push(time, device_index, &system_state);
+        }
+        let (_, body) = respond("GET /api/history HTTP/1.1", &http_api.shared);
+        let history: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let devices = history["devices"].as_array().unwrap();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0]["index"], 0);
+        assert_eq!(devices[0]["name"], "Test GPU");
+        let samples = devices[0]["samples"].as_array().unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[1]["time"], 2.0);
+        assert_eq!(samples[1]["temperature"], 41);
+        assert_eq!(devices[1]["samples"][0]["temperature"], 70);
+    }
+
+    #[test]
+    fn test_serves_requests() {
+        use std::io::Read;
+
+        let http_api = HttpApi::spawn("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(http_api.address()).unwrap();
+        stream
+            .write_all(b"GET /api/state HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nnull"));
+    }
+
+    #[test]
+    fn test_drops_oversized_requests() {
+        let http_api = HttpApi::spawn("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(http_api.address()).unwrap();
+        // A client that never ends its headers doesn't get to send more than the limit
+        let header = format!("X-Padding: {}\r\n", "a".repeat(1000));
+        let _ = stream.write_all(b"GET /api/state HTTP/1.1\r\n");
+        for _ in 0..MAX_REQUEST_BYTES / 1000 + 1 {
+            let _ = stream.write_all(header.as_bytes());
+        }
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        assert!(response.is_empty());
+
+        // Other clients are still answered
+        let mut stream = TcpStream::connect(http_api.address()).unwrap();
+        stream
+            .write_all(b"GET /api/state HTTP/1.1\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+}
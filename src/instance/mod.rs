@@ -0,0 +1,227 @@
+use std::collections::hash_map::RandomState;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use eframe::egui;
+
+const FOCUS_REQUEST: &str = "nvsmi-gui focus";
+const FOCUS_REPLY: &str = "nvsmi-gui ok";
+
+/// How long to wait for the running instance to answer before starting anyway.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The file the running instance announces its port in, e.g.
+/// `$XDG_RUNTIME_DIR/nvsmi-gui/instance` on Linux. It lives in a directory only the current
+/// user can read, so every user of the machine gets an instance of their own. `None` if the
+/// platform has no such directory.
+pub fn default_path() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "nvsmi-gui")?;
+    let dir = dirs.runtime_dir().unwrap_or_else(|| dirs.data_local_dir());
+    Some(dir.join("instance"))
+}
+
+/// Marks this process as the running instance until it exits.
+pub struct SingleInstance {
+    /// `None` if the instance file couldn't be written, in which case other instances can't
+    /// reach this one.
+    listener: Option<TcpListener>,
+    path: PathBuf,
+    /// Sent along with focus requests, so only someone who can read the instance file can
+    /// raise the window.
+    token: String,
+    focus_requested: Arc<AtomicBool>,
+}
+
+impl SingleInstance {
+    /// Becomes the running instance, or asks the one announced in the file at `path` to
+    /// focus its window and returns `None`.
+    pub fn acquire(path: &Path) -> Option<Self> {
+        // A file nobody answers for is left over from an instance that crashed
+        if let Ok(contents) = fs::read_to_string(path) {
+            match request_focus(&contents) {
+                Ok(()) => return None,
+                Err(err) => log::debug!("Replacing stale instance file {}: {err}", path.display()),
+            }
+        }
+
+        let token = new_token();
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .and_then(|listener| {
+                write_instance_file(path, listener.local_addr()?, &token)?;
+                Ok(listener)
+            })
+            .inspect_err(|err| {
+                log::warn!(
+                    "Failed to announce the instance in {}, not checking for other instances: {err}",
+                    path.display()
+                );
+            })
+            .ok();
+        Some(Self {
+            listener,
+            path: path.to_path_buf(),
+            token,
+            focus_requested: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Starts answering other instances on a background thread, waking up `ctx` when one
+    /// asks for the window.
+    pub fn listen(&mut self, ctx: &egui::Context) {
+        let Some(listener) = self.listener.take() else {
+            return;
+        };
+        let focus_requested = Arc::clone(&self.focus_requested);
+        let expected = format!("{FOCUS_REQUEST} {}", self.token);
+        let ctx = ctx.clone();
+        let spawned = thread::Builder::new()
+            .name(String::from("single-instance"))
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream.and_then(|stream| answer(stream, &expected, &focus_requested)) {
+                        // The window may be hidden or minimized, so don't wait for input
+                        Ok(true) => ctx.request_repaint(),
+                        Ok(false) => {}
+                        Err(err) => log::debug!("Instance connection failed: {err}"),
+                    }
+                }
+            });
+        if let Err(err) = spawned {
+            log::warn!("Failed to listen for other instances: {err}");
+        }
+    }
+
+    /// Whether another instance asked for the window since the last call.
+    pub fn take_focus_request(&self) -> bool {
+        self.focus_requested.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Drop for SingleInstance {
+    fn drop(&mut self) {
+        // Unless an instance started alongside this one took the file over
+        let ours = fs::read_to_string(&self.path)
+            .is_ok_and(|contents| contents.split_whitespace().nth(1) == Some(&self.token));
+        if ours {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Not cryptographically strong, but unguessable enough for other local users who can't
+/// read the instance file.
+fn new_token() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    if let Ok(since_epoch) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(since_epoch.as_nanos());
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+fn write_instance_file(path: &Path, address: SocketAddr, token: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file: File = options.open(path)?;
+    writeln!(file, "{} {token}", address.port())
+}
+
+/// Asks the instance announced by `contents`, a port and a token, to show its window.
+fn request_focus(contents: &str) -> io::Result<()> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_owned());
+    let (port, token) = contents
+        .trim()
+        .split_once(' ')
+        .ok_or_else(|| invalid("malformed instance file"))?;
+    let port: u16 = port
+        .parse()
+        .map_err(|_| invalid("malformed instance file"))?;
+
+    let mut stream = TcpStream::connect_timeout(
+        &SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+        HANDSHAKE_TIMEOUT,
+    )?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    writeln!(stream, "{FOCUS_REQUEST} {token}")?;
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    if reply.trim_end() == FOCUS_REPLY {
+        Ok(())
+    } else {
+        Err(invalid("not an nvsmi-gui instance"))
+    }
+}
+
+/// Answers one connection, `true` if it was another instance asking for the window with
+/// the `expected` request.
+fn answer(mut stream: TcpStream, expected: &str, focus_requested: &AtomicBool) -> io::Result<bool> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+    if request.trim_end() != expected {
+        return Ok(false);
+    }
+    // Before replying, so the window is shown by the time the other instance exits
+    focus_requested.store(true, Ordering::Relaxed);
+    writeln!(stream, "{FOCUS_REPLY}")?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("nvsmi-gui-test-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_second_instance_focuses_the_first() {
+        let path = test_path("focus");
+        let mut first = SingleInstance::acquire(&path).unwrap();
+        first.listen(&egui::Context::default());
+
+        assert!(SingleInstance::acquire(&path).is_none());
+        assert!(first.take_focus_request());
+        assert!(!first.take_focus_request());
+
+        drop(first);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_ignores_stale_files_and_wrong_tokens() {
+        let other = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = other.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in other.incoming() {
+                let _ = stream.and_then(|mut stream| writeln!(stream, "HTTP/1.1 400"));
+            }
+        });
+        let path = test_path("stale");
+        fs::write(&path, format!("{port} token\n")).unwrap();
+
+        let mut instance = SingleInstance::acquire(&path).unwrap();
+        assert!(instance.listener.is_some());
+        instance.listen(&egui::Context::default());
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let (port, _) = contents.split_once(' ').unwrap();
+        assert!(request_focus(&format!("{port} guessed")).is_err());
+        assert!(!instance.take_focus_request());
+        assert!(request_focus(&contents).is_ok());
+        assert!(instance.take_focus_request());
+    }
+}
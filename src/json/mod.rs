@@ -0,0 +1,63 @@
+use serde::Serialize;
+
+use crate::device::DeviceState;
+use crate::process::ProcessData;
+use crate::source::SystemState;
+
+/// A poll as served by the HTTP API and the local stream.
+#[derive(Serialize)]
+struct State<'a> {
+    device: &'a DeviceState,
+    /// Whether the clocks are held back by a power or thermal limit, which the raw throttle
+    /// reasons leave to the reader to work out.
+    throttled: bool,
+    processes: &'a [ProcessData],
+}
+
+/// `null` until the first successful poll.
+pub fn state_json(system_state: Option<&SystemState>) -> String {
+    let state = system_state.map(|system_state| State {
+        device: &system_state.device_state,
+        throttled: system_state.device_state.is_throttled(),
+        processes: &system_state.process_state.processes,
+    });
+    to_json(&state)
+}
+
+/// Serializes `value`, which can't fail for the types of this crate.
+pub fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("readings serialize to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nvml_wrapper::enums::device::UsedGpuMemory;
+
+    use crate::process::ProcessState;
+
+    #[test]
+    fn test_state_json() {
+        assert_eq!(state_json(None), "null");
+
+        let mut process = ProcessData::mock(7, UsedGpuMemory::Used(1024));
+        process.command_line = Some(String::from("train.py --token=secret"));
+        let mut device_state = DeviceState::mock().with_temperature(54);
+        device_state.metrics[1] = Some(1410.0);
+        let system_state = SystemState {
+            device_state,
+            process_state: ProcessState::from_lists(Vec::new(), vec![process]),
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&state_json(Some(&system_state))).unwrap();
+        assert_eq!(json["device"]["name"], "Test GPU");
+        assert_eq!(json["device"]["temperature"], 54);
+        assert_eq!(json["device"]["metrics"]["sm_clock_mhz"], 1410.0);
+        assert!(json["device"]["metrics"]["video_clock_mhz"].is_null());
+        assert_eq!(json["throttled"], false);
+        assert_eq!(json["processes"][0]["process_info"]["pid"], 7);
+        assert_eq!(json["processes"][0]["process_kind"], "compute");
+        assert!(json["processes"][0].get("command_line").is_none());
+    }
+}
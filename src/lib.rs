@@ -1,2 +1,30 @@
+pub mod alerts;
+pub mod benchmark;
+pub mod bug_report;
+pub mod capture;
+pub mod cli;
 pub mod device;
+#[cfg(feature = "http-api")]
+pub mod http_api;
+pub mod instance;
+#[cfg(any(feature = "http-api", feature = "local-stream"))]
+pub mod json;
+pub mod metric;
+pub mod mig;
+pub mod palette;
+pub mod poller;
 pub mod process;
+pub mod read_only;
+pub mod recording;
+pub mod settings;
+pub mod snapshot;
+#[cfg(feature = "audio")]
+pub mod sound;
+pub mod source;
+#[cfg(feature = "local-stream")]
+pub mod stream;
+pub mod theme;
+pub mod toast;
+#[cfg(feature = "tray")]
+pub mod tray;
+pub mod units;
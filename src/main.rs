@@ -1,176 +1,1607 @@
-use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use eframe::egui;
 
-use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
+use nvml_wrapper::error::NvmlError;
 use nvml_wrapper::Nvml;
 
-use once_cell::sync::Lazy;
+mod alerts;
+use alerts::{AlertMonitor, TemperatureSensor};
+
+mod benchmark;
+
+mod bug_report;
+use bug_report::Diagnostics;
+
+mod capture;
+use capture::CaptureTarget;
+
+mod cli;
+use cli::Args;
 
 mod device;
-use device::{CudaDriverVersion, DeviceState, DeviceView};
+use device::{DeviceView, PlotKind, Sample, SavedHistory, SupportedClocks};
+
+#[cfg(feature = "http-api")]
+mod http_api;
+#[cfg(feature = "http-api")]
+use http_api::HttpApi;
+
+mod instance;
+use instance::SingleInstance;
+
+#[cfg(any(feature = "http-api", feature = "local-stream"))]
+mod json;
+
+mod metric;
+
+mod mig;
+use mig::MigInstance;
+
+mod palette;
+use palette::CommandPalette;
+
+mod poller;
+use poller::Poller;
 
 mod process;
-use process::{ProcessData, ProcessKind, ProcessState, ProcessTable};
+use process::{BulkKill, ProcessTable};
 
-static NVML: Lazy<Nvml> = Lazy::new(|| Nvml::init().unwrap());
+mod read_only;
 
-fn poll_device() -> SystemState {
-    let device = NVML.device_by_index(0).unwrap();
-    let cuda_driver_version = NVML.sys_cuda_driver_version().unwrap();
-    let running_graphics_processes = device.running_graphics_processes().unwrap();
+mod recording;
+use recording::Replay;
 
-    let graphics_process_names: Vec<String> = running_graphics_processes
-        .iter()
-        .map(|process| {
-            NVML.sys_process_name(process.pid, 64)
-                .unwrap_or_else(|_| String::from("Unknown"))
-        })
-        .collect();
-
-    let graphics_process_data_vec: Vec<ProcessData> = running_graphics_processes
-        .iter()
-        .zip(graphics_process_names)
-        .map(|(process_info, process_name)| ProcessData {
-            process_info: process_info.clone(),
-            process_kind: ProcessKind::Graphics,
-            process_name: process::get_process_name(&process_name)
-                .to_string()
-                .to_lowercase(),
-        })
-        .collect();
-
-    let running_compute_processes = device.running_compute_processes().unwrap();
-    let compute_process_names: Vec<String> = running_compute_processes
-        .iter()
-        .map(|process| {
-            NVML.sys_process_name(process.pid, 64)
-                .unwrap_or_else(|_| String::from("Unknown"))
-        })
-        .collect();
-
-    let compute_process_data_vec: Vec<ProcessData> = running_compute_processes
-        .iter()
-        .zip(compute_process_names)
-        .map(|(process_info, process_name)| ProcessData {
-            process_info: process_info.clone(),
-            process_kind: ProcessKind::Compute,
-            process_name: process::get_process_name(&process_name).to_string(),
-        })
-        .collect();
+mod settings;
+use settings::{Profile, Settings};
 
-    let processes = [graphics_process_data_vec, compute_process_data_vec].concat();
-    let process_state = ProcessState { processes };
+mod snapshot;
+use snapshot::SnapshotDiff;
 
-    let num_fans = device.num_fans().unwrap();
-    let mut fan_speeds = Vec::new();
-    for fan_idx in 0..num_fans {
-        fan_speeds.push(device.fan_speed(fan_idx).unwrap());
-    }
+mod source;
+use source::{DeviceIds, GpuSource, SourceError, SystemState};
 
-    let device_state = DeviceState {
-        name: device.name().unwrap(),
-        driver_version: NVML.sys_driver_version().unwrap(),
-        cuda_driver_version: CudaDriverVersion {
-            major: nvml_wrapper::cuda_driver_version_major(cuda_driver_version),
-            minor: nvml_wrapper::cuda_driver_version_minor(cuda_driver_version),
-        },
-        temperature: device.temperature(TemperatureSensor::Gpu).unwrap(),
-        mem_info: device.memory_info().unwrap(),
-        fan_speeds,
-        power_usage: device.power_usage().unwrap(),
-    };
+#[cfg(feature = "local-stream")]
+mod stream;
+#[cfg(feature = "local-stream")]
+use stream::MetricStream;
 
-    SystemState {
-        device_state,
-        process_state,
-    }
+#[cfg(feature = "audio")]
+mod sound;
+#[cfg(feature = "audio")]
+use sound::AlertSound;
+
+mod theme;
+
+mod toast;
+use toast::Toasts;
+
+mod units;
+use units::{format_bytes, UnitSystem, MIB};
+
+#[cfg(feature = "tray")]
+mod tray;
+#[cfg(feature = "tray")]
+use tray::{Tray, TrayCommand};
+
+/// How often we poll while the window is minimized or hidden in the tray.
+const BACKGROUND_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often we poll and repaint while dimmed because the GPU is idle.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often we poll and repaint at most while another window has the focus.
+const UNFOCUSED_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Size of the window in overlay mode.
+const OVERLAY_SIZE: egui::Vec2 = egui::vec2(300.0, 44.0);
+
+/// How long the GPU and the user have to be inactive before the window is dimmed.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// GPU utilization (in percent) at or above which the GPU isn't considered idle.
+const IDLE_UTILIZATION_THRESHOLD: u32 = 5;
+
+/// How long the GPU selection has to settle before we ask the poller to switch devices.
+const DEVICE_SWITCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The multi-GPU grid polls every device, so it doesn't go faster than this.
+const GRID_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Polls that finish quicker than this don't flash the busy spinner.
+const BUSY_INDICATOR_DELAY: Duration = Duration::from_millis(150);
+
+/// How long the content takes to fade in after switching tabs.
+const TAB_TRANSITION_S: f32 = 0.15;
+
+/// How far the content slides up while fading in, in points.
+const TAB_TRANSITION_SLIDE: f32 = 12.0;
+
+/// How many update intervals may pass without a successful poll before the shown readings
+/// are marked as stale.
+const STALE_INTERVALS: u32 = 3;
+
+/// Readings younger than this are never considered stale, so that short update intervals don't
+/// flicker on a single slow poll.
+const MIN_STALE_AGE: Duration = Duration::from_secs(2);
+
+/// The storage key of the plotted history kept across restarts.
+const HISTORY_KEY: &str = "plot_history";
+
+/// Every memory clock of the device, with the graphics clocks that can be paired with it.
+fn poll_supported_clocks(nvml: &Nvml, device_index: usize) -> Result<SupportedClocks, NvmlError> {
+    let device = nvml.device_by_index(device_index as u32)?;
+    device
+        .supported_memory_clocks()?
+        .into_iter()
+        .map(|memory_clock| {
+            let graphics_clocks = device.supported_graphics_clocks(memory_clock)?;
+            Ok((memory_clock, graphics_clocks))
+        })
+        .collect()
+}
+
+#[cfg(feature = "power-control")]
+fn set_power_limit(nvml: &Nvml, device_index: usize, limit: u32) -> Result<(), SourceError> {
+    read_only::check()?;
+    let mut device = nvml.device_by_index(device_index as u32)?;
+    Ok(device.set_power_management_limit(limit)?)
 }
 
 fn main() -> eframe::Result {
     env_logger::init();
 
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+    // Checked before opening the GPUs, which is the slow part of starting up
+    let single_instance = if args.allow_multiple || args.run.is_some() {
+        None
+    } else {
+        match instance::default_path().map(|path| SingleInstance::acquire(&path)) {
+            Some(Some(single_instance)) => Some(single_instance),
+            Some(None) => {
+                log::info!("nvsmi-gui is already running, showing its window instead");
+                return Ok(());
+            }
+            None => None,
+        }
+    };
+    let source: Arc<dyn GpuSource> = match source::open() {
+        Ok(source) => Arc::from(source),
+        Err(err) => {
+            eprintln!("error: failed to read the GPUs: {err}");
+            std::process::exit(1);
+        }
+    };
+    let initial_device = match &args.device {
+        Some(selector) => match source.resolve_device(selector) {
+            Ok(index) => Some(index),
+            Err(err) => {
+                eprintln!("error: no GPU with {selector}: {err}");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    if let Some(command) = &args.run {
+        let device_index = initial_device.unwrap_or(0);
+        match benchmark::run(
+            source.as_ref(),
+            device_index,
+            command,
+            args.output.as_deref(),
+        ) {
+            // Passed on, so scripts can tell whether the workload succeeded
+            Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+            Err(err) => {
+                eprintln!("error: failed to run {command}: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([800.0, 600.0]),
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([800.0, 600.0])
+            // Lets the overlay be see-through, the normal window stays opaque
+            .with_transparent(true),
         ..Default::default()
     };
     eframe::run_native(
         "nvsmi-gui",
         options,
-        Box::new(|_cc| Ok(Box::new(MyApp::new()))),
+        Box::new(move |cc| {
+            Ok(Box::new(MyApp::new(
+                cc,
+                source,
+                initial_device,
+                args.watch_pid,
+                args.profile,
+                args.read_only,
+                single_instance,
+            )))
+        }),
     )
     .unwrap();
 
     Ok(())
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Tab {
+    Devices,
+    Processes,
+    Dashboard,
+    /// The utilization of every GPU side by side.
+    AllGpus,
+}
+
+/// Actions offered by the command palette.
 #[derive(Debug, Clone)]
-struct SystemState {
-    device_state: DeviceState,
-    process_state: ProcessState,
+enum PaletteAction {
+    SelectDevice(usize),
+    ShowTab(Tab),
+    RefreshNow,
+    ExportSession,
+    TakeSnapshot,
+    ClearHistory,
+    ToggleAlwaysOnTop,
+    ToggleTheme,
+    OpenSettings,
+    CopyDiagnostics,
+    ToggleOverlay,
 }
 
+/// Where the plotted history comes from.
 #[derive(Debug, Clone)]
-enum Tab {
-    Devices,
-    Processes,
+enum DataSource {
+    Live,
+    Replay(Box<Replay>),
 }
 
 struct MyApp {
+    source: Arc<dyn GpuSource>,
+    poller: Poller<Result<SystemState, SourceError>>,
+    /// Polls every device while the multi-GPU grid is shown.
+    grid_poller: Option<Poller<Vec<Option<Sample>>>>,
+    /// Polls every device while the process table lists the processes of all of them.
+    all_processes_poller: Option<Poller<Vec<Option<SystemState>>>>,
+    device_names: Vec<String>,
+    /// The PCI bus id and UUID of each device, shown when hovering the device selector.
+    device_ids: Vec<DeviceIds>,
+    selected_device: usize,
+    pending_device_switch: Option<(usize, Instant)>,
     current_state: Option<SystemState>,
+    last_poll_duration: Option<Duration>,
+    /// Number of polls in a row that failed, used to back off from a broken driver.
+    consecutive_failures: u32,
+    last_poll_error: Option<SourceError>,
+    /// The energy counter of each device when we first polled it, in millijoules.
+    energy_baselines: HashMap<usize, u64>,
+    show_diagnostics: bool,
+    command_palette: CommandPalette,
     device_view: DeviceView,
     process_table: ProcessTable,
+    bulk_kill: BulkKill,
     current_tab: Tab,
-    last_update: Instant,
-    update_interval: Duration,
+    /// The tab whose transition is playing or was played last.
+    transition_tab: Tab,
+    data_source: DataSource,
+    session_path: String,
+    session_status: Option<String>,
+    /// Plots to save once the requested screenshot arrives.
+    pending_capture: Option<CaptureTarget>,
+    snapshots: Vec<(String, SystemState)>,
+    snapshot_name: String,
+    /// Index into `snapshots` of the snapshot that is compared against the live state.
+    compared_snapshot: Option<usize>,
+    /// The clock table shown in the supported clocks window and the device it was read from,
+    /// `None` while the window is closed.
+    supported_clocks: Option<(usize, Result<SupportedClocks, NvmlError>)>,
+    started_at: Instant,
+    /// When `current_state` was last replaced by a successful poll.
+    state_received_at: Option<Instant>,
+    settings: Settings,
+    /// The always-on-top setting that was last sent to the window, if any.
+    applied_always_on_top: Option<bool>,
+    /// Whether the high-contrast visuals were applied last frame.
+    applied_high_contrast: bool,
+    /// The title that was last sent to the window, if any.
+    applied_title: Option<String>,
+    /// The window size to go back to when leaving the overlay, `Some` while in overlay mode.
+    overlay_restore_size: Option<egui::Vec2>,
+    alert_monitor: AlertMonitor,
+    toasts: Toasts,
+    #[cfg(feature = "audio")]
+    alert_sound: Option<AlertSound>,
+    #[cfg(feature = "http-api")]
+    http_api: Option<HttpApi>,
+    #[cfg(feature = "local-stream")]
+    metric_stream: Option<MetricStream>,
+    last_activity: Instant,
+    /// `None` when started with `--allow-multiple`.
+    single_instance: Option<SingleInstance>,
+    #[cfg(feature = "tray")]
+    tray: Option<Tray>,
+    #[cfg(feature = "tray")]
+    window_visible: bool,
 }
 
 impl MyApp {
-    fn new() -> Self {
-        let current_state = poll_device();
+    /// `initial_device` is the GPU picked on the command line, which takes precedence over the
+    /// one that was shown last time.
+    fn new(
+        cc: &eframe::CreationContext,
+        source: Arc<dyn GpuSource>,
+        initial_device: Option<usize>,
+        watch_pid: Option<u32>,
+        profile: Option<Profile>,
+        read_only: bool,
+        mut single_instance: Option<SingleInstance>,
+    ) -> Self {
+        let ctx = &cc.egui_ctx;
+        if let Some(single_instance) = &mut single_instance {
+            single_instance.listen(ctx);
+        }
+        let mut settings: Settings = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default();
+        settings.load_thresholds_file();
+        if let Some(profile) = profile {
+            settings.profile = profile;
+        }
+        settings.read_only_forced = read_only;
+        read_only::set_enabled(settings.is_read_only());
+        let update_interval = settings.update_interval();
+        source.set_metric_intervals(settings.metric_intervals);
+        source.set_process_name_length(settings.process_name_length);
+        let device_names = source.device_names();
+        // Go back to the GPU that was shown last time, unless it has disappeared since
+        let selected_device = match initial_device {
+            Some(index) => index,
+            None if settings.last_device < device_names.len() => settings.last_device,
+            None => 0,
+        };
+        let current_state = source
+            .poll_device(selected_device)
+            .inspect_err(|err| log::error!("Failed to poll device {selected_device}: {err}"))
+            .ok();
         let mut device_view = DeviceView::default();
-        device_view
-            .device_stats_plot
-            .set_max_memory_usage(current_state.device_state.mem_info.total / 1_000_000);
+        if settings.restore_history {
+            let history = cc
+                .storage
+                .and_then(|storage| eframe::get_value::<Option<SavedHistory>>(storage, HISTORY_KEY))
+                .flatten();
+            if let Some(history) = history {
+                device_view.restore_history(history, &device_names);
+            }
+        }
+        if let Some(current_state) = &current_state {
+            device_view
+                .device_stats_plot(selected_device)
+                .init_max_memory_usage(device::memory_total(&current_state.device_state) / MIB);
+        }
+        #[cfg(feature = "http-api")]
+        let http_api = HttpApi::spawn(&settings.http_api_address)
+            .inspect(|http_api| log::info!("Serving the HTTP API on {}", http_api.address()))
+            .inspect_err(|err| {
+                log::error!(
+                    "Failed to start the HTTP API on {}: {err}",
+                    settings.http_api_address
+                )
+            })
+            .ok();
+        #[cfg(feature = "local-stream")]
+        let metric_stream = MetricStream::spawn(Path::new(&settings.stream_path))
+            .inspect(|_| log::info!("Streaming metrics to {}", settings.stream_path))
+            .inspect_err(|err| {
+                log::error!(
+                    "Failed to start the metric stream at {}: {err}",
+                    settings.stream_path
+                )
+            })
+            .ok();
+        let initial_tab = if watch_pid.is_some() {
+            Tab::Processes
+        } else {
+            Tab::Devices
+        };
+        let mut process_table = ProcessTable::default();
+        process_table.device_names = device_names.clone();
+        if let Some(pid) = watch_pid {
+            process_table.watch(pid);
+        }
         Self {
-            current_state: Some(current_state),
+            poller: Poller::spawn(ctx.clone(), selected_device, update_interval, {
+                let source = Arc::clone(&source);
+                move |device_index| source.poll_device(device_index)
+            }),
+            grid_poller: None,
+            all_processes_poller: None,
+            device_names,
+            device_ids: source.device_ids(),
+            source,
+            selected_device,
+            pending_device_switch: None,
+            state_received_at: current_state.is_some().then(Instant::now),
+            current_state,
+            last_poll_duration: None,
+            consecutive_failures: 0,
+            last_poll_error: None,
+            energy_baselines: HashMap::new(),
+            show_diagnostics: false,
+            command_palette: CommandPalette::default(),
             device_view,
-            process_table: ProcessTable::default(),
-            current_tab: Tab::Devices,
-            last_update: Instant::now(),
-            update_interval: Duration::from_millis(20),
+            process_table,
+            bulk_kill: BulkKill::default(),
+            current_tab: initial_tab.clone(),
+            transition_tab: initial_tab,
+            data_source: DataSource::Live,
+            session_path: String::from("nvsmi-session.csv"),
+            session_status: None,
+            pending_capture: None,
+            snapshots: Vec::new(),
+            snapshot_name: String::new(),
+            compared_snapshot: None,
+            supported_clocks: None,
+            started_at: Instant::now(),
+            settings,
+            applied_always_on_top: None,
+            applied_high_contrast: false,
+            applied_title: None,
+            overlay_restore_size: None,
+            alert_monitor: AlertMonitor::default(),
+            toasts: Toasts::default(),
+            #[cfg(feature = "audio")]
+            alert_sound: AlertSound::new()
+                .inspect_err(|err| log::error!("Failed to open audio output: {err}"))
+                .ok(),
+            #[cfg(feature = "http-api")]
+            http_api,
+            #[cfg(feature = "local-stream")]
+            metric_stream,
+            last_activity: Instant::now(),
+            single_instance,
+            #[cfg(feature = "tray")]
+            tray: Tray::new(ctx)
+                .inspect_err(|err| log::error!("Failed to create tray icon: {err}"))
+                .ok(),
+            #[cfg(feature = "tray")]
+            window_visible: true,
+        }
+    }
+
+    fn receive_poll_results(&mut self) {
+        while let Some(result) = self.poller.try_recv() {
+            // Drop results that were already in flight before switching devices
+            if result.device_index != self.selected_device {
+                continue;
+            }
+            self.last_poll_duration = Some(result.duration);
+            let system_state = match result.value {
+                Ok(system_state) => system_state,
+                Err(err) => {
+                    self.consecutive_failures += 1;
+                    log::warn!(
+                        "Polling device {} failed ({} in a row): {err}",
+                        result.device_index,
+                        self.consecutive_failures
+                    );
+                    self.last_poll_error = Some(err);
+                    continue;
+                }
+            };
+            self.consecutive_failures = 0;
+            self.last_poll_error = None;
+
+            if let Some(total_energy) = system_state.device_state.total_energy {
+                let baseline = self
+                    .energy_baselines
+                    .entry(result.device_index)
+                    .or_insert(total_energy);
+                // The counter starts over when the driver is reloaded
+                if total_energy < *baseline {
+                    *baseline = total_energy;
+                }
+            }
+
+            self.device_view
+                .device_stats_plot(self.selected_device)
+                .init_max_memory_usage(device::memory_total(&system_state.device_state) / MIB);
+            let messages = self
+                .alert_monitor
+                .update(&self.settings.thresholds, &system_state.device_state);
+            #[cfg(feature = "audio")]
+            if self.settings.thresholds.sound && !messages.is_empty() {
+                if let Some(alert_sound) = &self.alert_sound {
+                    alert_sound.play();
+                }
+            }
+            if self.settings.thresholds.notify {
+                for message in messages {
+                    self.toasts.push(message);
+                }
+            }
+            self.push_poll(&system_state);
+            self.current_state = Some(system_state);
+            self.state_received_at = Some(Instant::now());
+        }
+    }
+
+    /// Feeds a successful poll of the selected device to the plots, the process table and
+    /// the API. Only new results are pushed, so nothing is plotted while polls fail.
+    fn push_poll(&mut self, system_state: &SystemState) {
+        // The table is refreshed with every poll rather than every frame. Header clicks
+        // re-sort it right away.
+        self.process_table.total_memory = device::memory_total(&system_state.device_state);
+        if self.all_processes_poller.is_none() {
+            self.process_table.processes = system_state.process_state.processes.clone();
+            self.process_table.sort_processes();
+            self.process_table
+                .record_memory(self.started_at.elapsed().as_secs_f64());
+            self.process_table.track_changes();
+        }
+
+        self.device_view
+            .device_stats_plot(self.selected_device)
+            .set_memory_warning_threshold(
+                self.settings
+                    .thresholds
+                    .memory_threshold(device::memory_total(&system_state.device_state))
+                    .map(|threshold| threshold / MIB),
+            );
+        self.device_view
+            .device_stats_plot(self.selected_device)
+            .set_temperature_thresholds(
+                system_state.device_state.slowdown_temperature,
+                system_state.device_state.memory_slowdown_temperature,
+                system_state.device_state.shutdown_temperature,
+            );
+        let plot = self.device_view.device_stats_plot(self.selected_device);
+        let time = self.started_at.elapsed().as_secs_f64();
+        plot.push_sample(time, &Sample::from(&system_state.device_state));
+        #[cfg(feature = "http-api")]
+        if let Some(http_api) = &self.http_api {
+            http_api.push(time, self.selected_device, system_state);
+        }
+        #[cfg(feature = "local-stream")]
+        if let Some(metric_stream) = &self.metric_stream {
+            metric_stream.push(system_state);
+        }
+        plot.fan_history
+            .push(time, &system_state.device_state.fan_speeds);
+    }
+
+    fn diagnostics_ui(&self, ctx: &egui::Context) {
+        let frame_time = ctx.input(|i| i.unstable_dt);
+        egui::Area::new(egui::Id::new("diagnostics"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(format!("Frame time: {:.1} ms", frame_time * 1000.0));
+                    match self.last_poll_duration {
+                        Some(duration) => ui.label(format!(
+                            "Last poll: {:.1} ms",
+                            duration.as_secs_f64() * 1000.0
+                        )),
+                        None => ui.label("Last poll: -"),
+                    };
+                });
+            });
+    }
+
+    /// Runs the poller of the multi-GPU grid while it is shown, and feeds the plots of the
+    /// devices the main poller doesn't cover.
+    fn update_grid_poller(&mut self, ctx: &egui::Context) {
+        if !matches!(self.current_tab, Tab::AllGpus) {
+            // Dropping the poller stops its thread
+            self.grid_poller = None;
+            return;
+        }
+        let interval = self.settings.update_interval().max(GRID_POLL_INTERVAL);
+        let grid_poller = self.grid_poller.get_or_insert_with(|| {
+            let source = Arc::clone(&self.source);
+            let device_count = self.device_names.len();
+            Poller::spawn(ctx.clone(), 0, interval, move |_| {
+                (0..device_count)
+                    .map(|device_index| {
+                        source
+                            .poll_device(device_index)
+                            .map(|system_state| Sample::from(&system_state.device_state))
+                            .ok()
+                    })
+                    .collect()
+            })
+        });
+        grid_poller.set_interval(interval);
+        let time = self.started_at.elapsed().as_secs_f64();
+        while let Some(result) = grid_poller.try_recv() {
+            for (device_index, sample) in result.value.into_iter().enumerate() {
+                if let (Some(sample), true) = (sample, device_index != self.selected_device) {
+                    self.device_view
+                        .device_stats_plot(device_index)
+                        .push_sample(time, &sample);
+                }
+            }
+        }
+    }
+
+    /// Runs a poller of every device while the process table lists the processes of all of
+    /// them, and refreshes the table from it instead of the main poller.
+    fn update_all_processes_poller(&mut self, ctx: &egui::Context) {
+        if !(matches!(self.current_tab, Tab::Processes) && self.process_table.shows_all_gpus()) {
+            if self.all_processes_poller.take().is_some() {
+                self.process_table.reset_changes();
+            }
+            return;
+        }
+        let interval = self.settings.update_interval();
+        let all_processes_poller = self.all_processes_poller.get_or_insert_with(|| {
+            self.process_table.reset_changes();
+            let source = Arc::clone(&self.source);
+            let device_count = self.device_names.len();
+            Poller::spawn(ctx.clone(), 0, interval, move |_| {
+                (0..device_count)
+                    .map(|device_index| source.poll_device(device_index).ok())
+                    .collect()
+            })
+        });
+        all_processes_poller.set_interval(interval);
+        while let Some(result) = all_processes_poller.try_recv() {
+            let states: Vec<(usize, SystemState)> = result
+                .value
+                .into_iter()
+                .enumerate()
+                .filter_map(|(device_index, state)| Some((device_index, state?)))
+                .collect();
+            self.process_table.gpu_total_memory = states
+                .iter()
+                .map(|(device_index, state)| {
+                    (*device_index, device::memory_total(&state.device_state))
+                })
+                .collect();
+            self.process_table.processes = process::merge_device_processes(
+                states
+                    .into_iter()
+                    .map(|(device_index, state)| (device_index, state.process_state.processes)),
+            );
+            self.process_table.sort_processes();
+            self.process_table
+                .record_memory(self.started_at.elapsed().as_secs_f64());
+            self.process_table.track_changes();
+        }
+    }
+
+    fn select_device(&mut self, device_index: usize) {
+        if device_index != self.selected_device {
+            self.pending_device_switch = Some((device_index, Instant::now()));
+        }
+    }
+
+    fn apply_pending_device_switch(&mut self) {
+        if let Some((device_index, requested_at)) = self.pending_device_switch {
+            if requested_at.elapsed() >= DEVICE_SWITCH_DEBOUNCE {
+                self.pending_device_switch = None;
+                self.selected_device = device_index;
+                self.settings.last_device = device_index;
+                self.current_state = None;
+                self.process_table.reset_changes();
+                #[cfg(feature = "power-control")]
+                {
+                    self.device_view.power_limit_control = Default::default();
+                }
+                #[cfg(feature = "admin")]
+                {
+                    self.device_view.gpu_reset_control = Default::default();
+                }
+                self.poller.set_device(device_index);
+            }
+        }
+    }
+
+    fn session_menu_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.text_edit_singleline(&mut self.session_path);
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Export")
+                .on_hover_text("As JSON lines if the file ends in .jsonl, as CSV otherwise")
+                .clicked()
+            {
+                self.export_session();
+            }
+            if ui.button("Import and replay").clicked() {
+                match recording::import(Path::new(&self.session_path)) {
+                    Ok(samples) => {
+                        self.session_status = Some(format!("Imported {} samples", samples.len()));
+                        self.data_source = DataSource::Replay(Box::new(Replay::new(
+                            self.session_path.clone(),
+                            samples,
+                            self.settings.update_interval(),
+                        )));
+                        self.current_tab = Tab::Devices;
+                        ui.close_menu();
+                    }
+                    Err(err) => self.session_status = Some(format!("Import failed: {err}")),
+                }
+            }
+        });
+
+        if let Some(status) = &self.session_status {
+            ui.label(status);
+        }
+    }
+
+    /// A one line summary of the selected GPU and of the polling, shown below every tab.
+    fn status_bar_ui(&self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if let Some(system_state) = &self.current_state {
+                let device_state = &system_state.device_state;
+                let memory_percent = device_state
+                    .mem_info
+                    .as_ref()
+                    .filter(|mem_info| mem_info.total > 0)
+                    .map(|mem_info| mem_info.used * 100 / mem_info.total);
+                ui.label(device_label_job(
+                    ui,
+                    self.selected_device,
+                    &device_state.name,
+                ));
+                ui.separator();
+                ui.label(device::format_metric(device_state.temperature, "°C"));
+                ui.separator();
+                ui.label(format!(
+                    "GPU {}",
+                    device::format_metric(device::gpu_utilization(device_state), "%")
+                ));
+                ui.separator();
+                ui.label(format!(
+                    "Memory {}",
+                    device::format_metric(memory_percent, "%")
+                ));
+                ui.separator();
+                ui.label(device::format_power(
+                    device_state,
+                    self.device_view.smoothed_power(self.selected_device),
+                ));
+            } else {
+                ui.label("No data");
+            }
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.weak(self.source.name())
+                    .on_hover_text("Where the readings come from");
+                ui.separator();
+                if read_only::enabled() {
+                    ui.weak("🔒 Read-only").on_hover_text(
+                        "Killing processes, changing the power limit and resetting the GPU are \
+                         disabled",
+                    );
+                    ui.separator();
+                }
+                match (&self.last_poll_error, self.last_poll_duration) {
+                    (Some(err), _) => {
+                        ui.colored_label(egui::Color32::RED, format!("⚠ {err}"));
+                    }
+                    (None, Some(duration)) => {
+                        ui.weak(format!(
+                            "Polled in {:.0} ms",
+                            duration.as_secs_f64() * 1000.0
+                        ));
+                    }
+                    (None, None) => {}
+                }
+                if let Some(age) = self.stale_age() {
+                    ui.separator();
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("Last updated {} ago", format_age(age)),
+                    );
+                }
+            });
+        });
+    }
+
+    /// How old the shown readings are, if they are older than polling should allow.
+    fn stale_age(&self) -> Option<Duration> {
+        let age = self.state_received_at?.elapsed();
+        let limit = (self.settings.update_interval() * STALE_INTERVALS).max(MIN_STALE_AGE);
+        (age >= limit).then_some(age)
+    }
+
+    fn export_session(&mut self) {
+        let samples = self
+            .device_view
+            .device_stats_plot(self.selected_device)
+            .samples();
+        self.session_status = Some(
+            match recording::export(
+                Path::new(&self.session_path),
+                &samples,
+                SystemTime::now() - self.started_at.elapsed(),
+            ) {
+                Ok(()) => format!("Exported {} samples", samples.len()),
+                Err(err) => format!("Export failed: {err}"),
+            },
+        );
+    }
+
+    fn take_snapshot(&mut self) {
+        if let Some(system_state) = &self.current_state {
+            let name = match self.snapshot_name.trim() {
+                "" => format!("Snapshot {}", self.snapshots.len() + 1),
+                name => name.to_string(),
+            };
+            self.snapshots.push((name, system_state.clone()));
+            self.snapshot_name.clear();
+        }
+    }
+
+    fn palette_actions(&self) -> Vec<(String, PaletteAction)> {
+        let mut actions: Vec<(String, PaletteAction)> = (0..self.device_names.len())
+            .map(|index| {
+                (
+                    format!("Switch to GPU {}", device_label(&self.device_names, index)),
+                    PaletteAction::SelectDevice(index),
+                )
+            })
+            .collect();
+        actions.extend([
+            (
+                String::from("Go to Device(s)"),
+                PaletteAction::ShowTab(Tab::Devices),
+            ),
+            (
+                String::from("Go to Processes"),
+                PaletteAction::ShowTab(Tab::Processes),
+            ),
+            (
+                String::from("Go to Dashboard"),
+                PaletteAction::ShowTab(Tab::Dashboard),
+            ),
+            (
+                String::from("Go to All GPUs"),
+                PaletteAction::ShowTab(Tab::AllGpus),
+            ),
+            (String::from("Refresh now"), PaletteAction::RefreshNow),
+            (String::from("Export session"), PaletteAction::ExportSession),
+            (String::from("Take snapshot"), PaletteAction::TakeSnapshot),
+            (
+                String::from("Clear plot history"),
+                PaletteAction::ClearHistory,
+            ),
+            (
+                String::from("Toggle always on top"),
+                PaletteAction::ToggleAlwaysOnTop,
+            ),
+            (
+                String::from("Toggle dark/light theme"),
+                PaletteAction::ToggleTheme,
+            ),
+            (String::from("Open settings"), PaletteAction::OpenSettings),
+            (
+                String::from("Copy diagnostics"),
+                PaletteAction::CopyDiagnostics,
+            ),
+            (String::from("Toggle overlay"), PaletteAction::ToggleOverlay),
+        ]);
+        actions
+    }
+
+    fn run_palette_action(&mut self, ctx: &egui::Context, action: PaletteAction) {
+        match action {
+            PaletteAction::SelectDevice(index) => self.select_device(index),
+            PaletteAction::ShowTab(tab) => self.current_tab = tab,
+            PaletteAction::RefreshNow => {
+                self.poller.poll_now();
+            }
+            PaletteAction::ExportSession => {
+                self.export_session();
+                if let Some(status) = &self.session_status {
+                    self.toasts.push(status.clone());
+                }
+            }
+            PaletteAction::TakeSnapshot => self.take_snapshot(),
+            PaletteAction::ClearHistory => self
+                .device_view
+                .device_stats_plot(self.selected_device)
+                .clear_history(),
+            PaletteAction::ToggleAlwaysOnTop => {
+                self.settings.always_on_top = !self.settings.always_on_top;
+            }
+            PaletteAction::ToggleTheme => {
+                ctx.set_visuals(theme::visuals(
+                    !ctx.style().visuals.dark_mode,
+                    self.settings.high_contrast,
+                ));
+            }
+            PaletteAction::OpenSettings => self.settings.show_settings_window = true,
+            PaletteAction::CopyDiagnostics => self.copy_diagnostics(ctx),
+            PaletteAction::ToggleOverlay => {
+                self.set_overlay(ctx, self.overlay_restore_size.is_none());
+            }
+        }
+    }
+
+    /// Puts the environment details for a bug report on the clipboard.
+    fn copy_diagnostics(&mut self, ctx: &egui::Context) {
+        let nvml = self.source.nvml();
+        let diagnostics = Diagnostics {
+            backend: self.source.name().to_string(),
+            nvml_version: nvml.and_then(|nvml| nvml.sys_nvml_version().ok()),
+            device_uuid: nvml.and_then(|nvml| {
+                nvml.device_by_index(self.selected_device as u32)
+                    .and_then(|device| device.uuid())
+                    .ok()
+            }),
+            device_state: self
+                .current_state
+                .as_ref()
+                .map(|system_state| system_state.device_state.clone()),
+        };
+        ctx.output_mut(|output| output.copied_text = diagnostics.report());
+        self.toasts
+            .push(String::from("Copied the diagnostics to the clipboard"));
+    }
+
+    fn snapshot_menu_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut self.snapshot_name);
+        });
+        if ui
+            .add_enabled(
+                self.current_state.is_some(),
+                egui::Button::new("Take snapshot"),
+            )
+            .clicked()
+        {
+            self.take_snapshot();
+        }
+
+        if !self.snapshots.is_empty() {
+            ui.separator();
+        }
+        let mut removed = None;
+        for (index, (name, _)) in self.snapshots.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let compared = self.compared_snapshot == Some(index);
+                if ui.selectable_label(compared, name).clicked() {
+                    self.compared_snapshot = if compared { None } else { Some(index) };
+                }
+                if ui.small_button("🗑").on_hover_text("Delete").clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = removed {
+            self.snapshots.remove(index);
+            self.compared_snapshot = match self.compared_snapshot {
+                Some(compared) if compared == index => None,
+                Some(compared) if compared > index => Some(compared - 1),
+                compared => compared,
+            };
         }
     }
+
+    fn snapshot_comparison_ui(&mut self, ctx: &egui::Context) {
+        let (Some(index), Some(live)) = (self.compared_snapshot, &self.current_state) else {
+            return;
+        };
+        let (name, snapshot) = &self.snapshots[index];
+        let diff = SnapshotDiff::new(
+            &snapshot.device_state,
+            &snapshot.process_state,
+            &live.device_state,
+            &live.process_state,
+        );
+
+        let mut open = true;
+        egui::Window::new(format!("Compare with {name}"))
+            .id(egui::Id::new("snapshot comparison"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                snapshot::comparison_ui(
+                    ui,
+                    &snapshot.device_state,
+                    &live.device_state,
+                    &diff,
+                    self.settings.memory_unit,
+                );
+            });
+        if !open {
+            self.compared_snapshot = None;
+        }
+    }
+
+    fn supported_clocks_ui(&mut self, ctx: &egui::Context) {
+        let Some((device_index, _)) = self.supported_clocks else {
+            return;
+        };
+        let source = Arc::clone(&self.source);
+        let Some(nvml) = source.nvml() else {
+            self.supported_clocks = None;
+            return;
+        };
+        // The table doesn't change while the app runs, so it is only read again when switching
+        // to another GPU
+        if device_index != self.selected_device {
+            self.supported_clocks = Some((
+                self.selected_device,
+                poll_supported_clocks(nvml, self.selected_device),
+            ));
+        }
+        let Some((_, supported_clocks)) = &self.supported_clocks else {
+            return;
+        };
+
+        let mut open = true;
+        egui::Window::new("Supported clocks").open(&mut open).show(
+            ctx,
+            |ui| match supported_clocks {
+                Ok(supported_clocks) if !supported_clocks.is_empty() => {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        device::supported_clocks_ui(ui, supported_clocks);
+                    });
+                }
+                Ok(_) | Err(NvmlError::NotSupported) => {
+                    ui.label("This GPU doesn't report its supported clocks.");
+                }
+                Err(err) => {
+                    ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+                }
+            },
+        );
+        if !open {
+            self.supported_clocks = None;
+        }
+    }
+
+    /// Saves the requested plots once the screenshot comes back from the backend.
+    fn handle_screenshot(&mut self, ctx: &egui::Context) {
+        let Some(target) = self.pending_capture else {
+            return;
+        };
+        let Some(screenshot) = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        }) else {
+            return;
+        };
+        self.pending_capture = None;
+
+        let plot_rects = self
+            .device_view
+            .device_stats_plot(self.selected_device)
+            .plot_rects();
+        let Some(rect) = target.rect(plot_rects) else {
+            self.toasts
+                .push(String::from("Nothing to save, the plots aren't visible"));
+            return;
+        };
+        let image = capture::crop(&screenshot, rect, ctx.pixels_per_point());
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let path = format!("nvsmi-{}-{timestamp}.png", target.file_name_part());
+        self.toasts
+            .push(match capture::save_png(Path::new(&path), &image) {
+                Ok(()) => format!("Saved {path}"),
+                Err(err) => format!("Failed to save {path}: {err}"),
+            });
+    }
+
+    fn is_busy(&self) -> bool {
+        self.pending_device_switch.is_some()
+            || self
+                .poller
+                .busy_for()
+                .is_some_and(|busy_for| busy_for >= BUSY_INDICATOR_DELAY)
+    }
+
+    fn device_selector_ui(&mut self, ui: &mut egui::Ui) {
+        let shown_device = self
+            .pending_device_switch
+            .map_or(self.selected_device, |(device_index, _)| device_index);
+        let mut selected = shown_device;
+        let device_tooltip = |index: usize| {
+            let name = self
+                .device_names
+                .get(index)
+                .map_or("Unknown GPU", String::as_str);
+            self.device_ids
+                .get(index)
+                .cloned()
+                .unwrap_or_default()
+                .describe(name)
+        };
+        egui::ComboBox::from_id_source("device selector")
+            .selected_text(device_label_job(
+                ui,
+                shown_device,
+                &device_label(&self.device_names, shown_device),
+            ))
+            .show_ui(ui, |ui| {
+                for index in 0..self.device_names.len() {
+                    let label =
+                        device_label_job(ui, index, &device_label(&self.device_names, index));
+                    ui.selectable_value(&mut selected, index, label)
+                        .on_hover_text(device_tooltip(index));
+
+                    // We only know about the MIG instances of the device we're polling
+                    if index != self.selected_device {
+                        continue;
+                    }
+                    if let Some(system_state) = &self.current_state {
+                        for instance in &system_state.device_state.mig_instances {
+                            ui.indent(("mig instance", instance.index), |ui| {
+                                ui.weak(mig_instance_label(instance, self.settings.memory_unit));
+                            });
+                        }
+                    }
+                }
+            })
+            .response
+            .on_hover_text(device_tooltip(shown_device));
+        if selected != shown_device {
+            self.select_device(selected);
+        }
+    }
+
+    fn apply_window_level(&mut self, ctx: &egui::Context) {
+        if self.applied_always_on_top == Some(self.settings.always_on_top) {
+            return;
+        }
+        let window_level = if self.settings.always_on_top {
+            egui::WindowLevel::AlwaysOnTop
+        } else {
+            egui::WindowLevel::Normal
+        };
+        ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(window_level));
+        self.applied_always_on_top = Some(self.settings.always_on_top);
+    }
+
+    /// Applies or removes the high-contrast visuals. They are checked every frame since
+    /// switching between dark and light, e.g. by following the system, resets them.
+    fn apply_theme(&mut self, ctx: &egui::Context) {
+        let high_contrast = self.settings.high_contrast;
+        theme::set_high_contrast(high_contrast);
+        if high_contrast || self.applied_high_contrast {
+            let visuals = theme::visuals(ctx.style().visuals.dark_mode, high_contrast);
+            if ctx.style().visuals != visuals {
+                ctx.set_visuals(visuals);
+            }
+        }
+        self.applied_high_contrast = high_contrast;
+    }
+
+    /// Switches between the normal window and a small borderless always-on-top overlay.
+    fn set_overlay(&mut self, ctx: &egui::Context, overlay: bool) {
+        if overlay == self.overlay_restore_size.is_some() {
+            return;
+        }
+        if overlay {
+            let size = ctx.input(|i| i.viewport().inner_rect.map(|rect| rect.size()));
+            self.overlay_restore_size = Some(size.unwrap_or(egui::vec2(800.0, 600.0)));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Decorations(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(
+                egui::WindowLevel::AlwaysOnTop,
+            ));
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(OVERLAY_SIZE));
+        } else if let Some(size) = self.overlay_restore_size.take() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Decorations(true));
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(size));
+            // Let `apply_window_level` restore the user's always-on-top setting
+            self.applied_always_on_top = None;
+        }
+    }
+
+    /// The overlay's only contents: the temperature, utilization and power of the selected GPU.
+    /// It's moved by dragging, and double-clicking it goes back to the normal window.
+    fn overlay_ui(&mut self, ctx: &egui::Context) {
+        let fill = ctx
+            .style()
+            .visuals
+            .panel_fill
+            .gamma_multiply(self.settings.overlay_opacity);
+        let mut leave_overlay = false;
+        egui::CentralPanel::default()
+            .frame(
+                egui::Frame::none()
+                    .fill(fill)
+                    .rounding(6.0)
+                    .inner_margin(8.0),
+            )
+            .show(ctx, |ui| {
+                let response = ui.interact(
+                    ui.max_rect(),
+                    egui::Id::new("overlay"),
+                    egui::Sense::click_and_drag(),
+                );
+                if response.drag_started() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::StartDrag);
+                }
+                if response.double_clicked() {
+                    leave_overlay = true;
+                }
+                response
+                    .on_hover_text("Double-click to leave the overlay")
+                    .context_menu(|ui| {
+                        ui.add(
+                            egui::Slider::new(&mut self.settings.overlay_opacity, 0.2..=1.0)
+                                .text("Opacity"),
+                        );
+                        if ui.button("Leave overlay").clicked() {
+                            leave_overlay = true;
+                            ui.close_menu();
+                        }
+                    });
+
+                ui.horizontal_centered(|ui| {
+                    let Some(system_state) = &self.current_state else {
+                        ui.add(egui::Label::new("No data").selectable(false));
+                        return;
+                    };
+                    let device_state = &system_state.device_state;
+                    let sensor = self.settings.thresholds.temperature_sensor;
+                    let temperature = egui::RichText::new(format!(
+                        "🌡 {}",
+                        device::format_metric(sensor.read(device_state), "°C")
+                    ))
+                    .strong();
+                    let temperature = if self.settings.thresholds.temperature_exceeded(device_state)
+                    {
+                        temperature.color(egui::Color32::RED)
+                    } else {
+                        temperature
+                    };
+                    for text in [
+                        temperature,
+                        egui::RichText::new(format!(
+                            "GPU {}",
+                            device::format_metric(device::gpu_utilization(device_state), "%")
+                        ))
+                        .strong(),
+                        egui::RichText::new(format!(
+                            "⚡ {}",
+                            device::format_power(
+                                device_state,
+                                self.device_view.smoothed_power(self.selected_device)
+                            )
+                        ))
+                        .strong(),
+                    ] {
+                        ui.add(egui::Label::new(text.size(16.0)).selectable(false));
+                    }
+                });
+            });
+        if leave_overlay {
+            self.set_overlay(ctx, false);
+        }
+    }
+
+    /// Puts the reading picked in the settings into the window title, sending it only when
+    /// it changes.
+    fn apply_window_title(&mut self, ctx: &egui::Context) {
+        let title = self.settings.title_metric.title(
+            self.current_state
+                .as_ref()
+                .map(|system_state| &system_state.device_state),
+            self.settings.thresholds.temperature_sensor,
+        );
+        if self.applied_title.as_ref() != Some(&title) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.applied_title = Some(title);
+        }
+    }
+
+    fn update_idle_state(&mut self, ctx: &egui::Context) {
+        let user_active = ctx.input(|i| i.pointer.is_moving() || !i.events.is_empty());
+        let gpu_active = self.current_state.as_ref().is_some_and(|system_state| {
+            device::gpu_utilization(&system_state.device_state)
+                .is_some_and(|utilization| utilization >= IDLE_UTILIZATION_THRESHOLD)
+        });
+        if user_active || gpu_active {
+            self.last_activity = Instant::now();
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.settings.idle_dimming && self.last_activity.elapsed() >= IDLE_TIMEOUT
+    }
+
+    /// Requests a repaint on the next frame, or keeps polling at a reduced rate when nobody is
+    /// looking at the window.
+    fn schedule_next_frame(&mut self, ctx: &egui::Context) {
+        match self.reduced_interval(ctx) {
+            Some(interval) => {
+                self.poller.set_interval(poller::backoff_interval(
+                    interval,
+                    self.consecutive_failures,
+                ));
+                ctx.request_repaint_after(interval);
+            }
+            None => {
+                self.poller.set_interval(poller::backoff_interval(
+                    self.settings.update_interval(),
+                    self.consecutive_failures,
+                ));
+                ctx.request_repaint();
+            }
+        }
+    }
+
+    /// The reduced poll and repaint interval to use when nobody needs live updates,
+    /// or `None` to run at full rate.
+    fn reduced_interval(&self, ctx: &egui::Context) -> Option<Duration> {
+        if self.is_backgrounded(ctx) {
+            Some(BACKGROUND_POLL_INTERVAL)
+        } else if self.is_idle() {
+            Some(IDLE_POLL_INTERVAL)
+        } else if self.is_unfocused(ctx) {
+            // Never poll faster than the configured interval
+            Some(UNFOCUSED_POLL_INTERVAL.max(self.settings.update_interval()))
+        } else {
+            None
+        }
+    }
+
+    fn is_backgrounded(&self, ctx: &egui::Context) -> bool {
+        let minimized = ctx.input(|i| i.viewport().minimized.unwrap_or(false));
+        #[cfg(feature = "tray")]
+        let minimized = minimized || !self.window_visible;
+        minimized
+    }
+
+    fn is_unfocused(&self, ctx: &egui::Context) -> bool {
+        // Platforms that don't report the focus count as focused
+        self.settings.slow_when_unfocused && !ctx.input(|i| i.viewport().focused.unwrap_or(true))
+    }
+
+    /// How far the transition to the current tab has come, from 0 right after switching to 1
+    /// once it is fully shown.
+    fn tab_transition(&mut self, ctx: &egui::Context) -> f32 {
+        let id = egui::Id::new("tab transition");
+        if self.transition_tab != self.current_tab {
+            self.transition_tab = self.current_tab.clone();
+            if self.settings.animate_tabs {
+                // Jumps back to 0 without animating, the next frame animates towards 1
+                ctx.animate_value_with_time(id, 0.0, 0.0);
+                ctx.request_repaint();
+                return 0.0;
+            }
+        }
+        ctx.animate_value_with_time(id, 1.0, TAB_TRANSITION_S)
+    }
+
+    /// Shows the window when another instance was started and exited in favor of this one.
+    fn handle_focus_request(&mut self, ctx: &egui::Context) {
+        let Some(single_instance) = &self.single_instance else {
+            return;
+        };
+        if !single_instance.take_focus_request() {
+            return;
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        #[cfg(feature = "tray")]
+        {
+            self.window_visible = true;
+        }
+    }
+
+    #[cfg(feature = "tray")]
+    fn handle_tray(&mut self, ctx: &egui::Context) {
+        let Some(tray) = &mut self.tray else {
+            return;
+        };
+
+        if let Some(system_state) = &self.current_state {
+            tray.set_tooltip(format!(
+                "{}: {}",
+                system_state.device_state.name,
+                device::format_metric(system_state.device_state.temperature, "°C")
+            ));
+        }
+
+        // Minimizing sends the window to the tray instead of the taskbar
+        if self.window_visible && ctx.input(|i| i.viewport().minimized.unwrap_or(false)) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+            self.window_visible = false;
+        }
+
+        while let Some(command) = tray.try_recv_command() {
+            match command {
+                TrayCommand::ToggleWindow => {
+                    self.window_visible = !self.window_visible;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(self.window_visible));
+                    if self.window_visible {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                    }
+                }
+                TrayCommand::Quit => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            }
+        }
+    }
+}
+
+/// `text` preceded by a dot in the GPU's color.
+fn device_label_job(ui: &egui::Ui, device_index: usize, text: &str) -> egui::text::LayoutJob {
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let mut job = egui::text::LayoutJob::default();
+    job.append(
+        "● ",
+        0.0,
+        egui::TextFormat::simple(font_id.clone(), device::device_color(device_index)),
+    );
+    // The placeholder color is replaced by the widget's own text color
+    job.append(
+        text,
+        0.0,
+        egui::TextFormat::simple(font_id, egui::Color32::PLACEHOLDER),
+    );
+    job
+}
+
+/// A short human readable age, e.g. "12 s" or "3 min 5 s".
+fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    if seconds < 60 {
+        format!("{seconds} s")
+    } else {
+        format!("{} min {} s", seconds / 60, seconds % 60)
+    }
+}
+
+fn device_label(device_names: &[String], device_index: usize) -> String {
+    match device_names.get(device_index) {
+        Some(name) => format!("{device_index}: {name}"),
+        None => format!("GPU {device_index}"),
+    }
+}
+
+fn mig_instance_label(instance: &MigInstance, memory_unit: UnitSystem) -> String {
+    let utilization = match &instance.utilization {
+        Some(utilization) => format!("GPU {}%", utilization.gpu),
+        None => String::from("utilization N/A"),
+    };
+    format!(
+        "MIG {}: {}, {} / {}, {}",
+        instance.index,
+        instance.name,
+        format_bytes(instance.mem_info.used, memory_unit),
+        format_bytes(instance.mem_info.total, memory_unit),
+        utilization
+    )
+}
+
+fn capture_menu_ui(ui: &mut egui::Ui, pending_capture: &mut Option<CaptureTarget>) {
+    let targets = std::iter::once(CaptureTarget::AllPlots)
+        .chain(PlotKind::all().into_iter().map(CaptureTarget::Plot));
+    for target in targets {
+        let label = match target {
+            CaptureTarget::AllPlots => String::from("All plots"),
+            CaptureTarget::Plot(kind) => kind.to_string(),
+        };
+        if ui.button(label).clicked() {
+            *pending_capture = Some(target);
+            ui.ctx()
+                .send_viewport_cmd(egui::ViewportCommand::Screenshot);
+            ui.close_menu();
+        }
+    }
+}
+
+/// Lays out a row of readouts side by side, or stacked in compact mode.
+fn info_row(ui: &mut egui::Ui, compact: bool, add_contents: impl FnOnce(&mut egui::Ui)) {
+    if compact {
+        ui.vertical(add_contents);
+    } else {
+        ui.horizontal(add_contents);
+    }
+}
+
+/// A label that is drawn in red while `warning` is set.
+fn warning_label(ui: &mut egui::Ui, warning: bool, text: String) {
+    if warning {
+        ui.colored_label(egui::Color32::RED, text);
+    } else {
+        ui.label(text);
+    }
 }
 
 impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let now = Instant::now();
-        let system_state = poll_device();
-        self.current_state = Some(system_state.clone());
-        self.process_table.processes = self
-            .current_state
-            .as_ref()
-            .unwrap()
-            .process_state
-            .processes
-            .clone();
-        self.process_table.sort_processes();
-
-        if now.duration_since(self.last_update) >= self.update_interval {
-            self.device_view
-                .device_stats_plot
-                .temperature_vals
-                .push_back(system_state.device_state.temperature);
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.settings);
+        let history = self.settings.restore_history.then(|| {
             self.device_view
-                .device_stats_plot
-                .memory_usage_vals
-                .push_back(system_state.device_state.mem_info.used / 1_000_000);
-            self.device_view
-                .device_stats_plot
-                .power_usage_vals
-                .push_back(system_state.device_state.power_usage / 1000);
-            self.last_update = now;
+                .saved_history(self.started_at.elapsed().as_secs_f64(), &self.device_names)
+        });
+        eframe::set_value(storage, HISTORY_KEY, &history);
+    }
+
+    fn clear_color(&self, visuals: &egui::Visuals) -> [f32; 4] {
+        // Only the overlay lets the desktop show through
+        if self.overlay_restore_size.is_some() {
+            egui::Color32::TRANSPARENT.to_normalized_gamma_f32()
+        } else {
+            visuals.panel_fill.to_normalized_gamma_f32()
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.apply_pending_device_switch();
+        self.receive_poll_results();
+        self.update_grid_poller(ctx);
+        self.update_all_processes_poller(ctx);
+        self.apply_window_title(ctx);
+        self.handle_screenshot(ctx);
+        self.update_idle_state(ctx);
+        self.handle_focus_request(ctx);
+        units::set_number_format(self.settings.number_format);
+        self.apply_theme(ctx);
+        read_only::set_enabled(self.settings.is_read_only());
+        self.source
+            .set_metric_intervals(self.settings.metric_intervals);
+        self.source
+            .set_process_name_length(self.settings.process_name_length);
+
+        let plot = self.device_view.device_stats_plot(self.selected_device);
+        // Thresholds dragged on the plots during the last frame
+        if let Some(current_state) = &self.current_state {
+            let total_mib = device::memory_total(&current_state.device_state) / MIB;
+            if let Some(lines) = plot.take_edited_thresholds() {
+                self.settings
+                    .thresholds
+                    .apply_threshold_lines(lines, total_mib);
+            }
+            plot.set_threshold_lines(self.settings.thresholds.threshold_lines(total_mib));
+        }
+        plot.set_memory_unit(self.settings.memory_unit);
+        plot.set_power_smoothing(self.settings.power_smoothing());
+        plot.set_pin_memory_to_total(self.settings.pin_memory_plot);
+        plot.set_plot_height(self.settings.plot_height());
+        plot.set_series_style(self.settings.series_style());
+        plot.set_retention_policy(self.settings.retention_policy());
+        plot.set_shown_metrics(self.settings.shown_metrics());
+        if let DataSource::Replay(replay) = &mut self.data_source {
+            replay.set_memory_unit(self.settings.memory_unit);
+            replay.set_plot_height(self.settings.plot_height());
+            replay.set_series_style(self.settings.series_style());
+            replay.advance();
+        }
+
+        self.process_table.memory_unit = self.settings.memory_unit;
+
+        if self.overlay_restore_size.is_some() {
+            self.overlay_ui(ctx);
+            self.toasts.show(ctx);
+            #[cfg(feature = "tray")]
+            self.handle_tray(ctx);
+            self.schedule_next_frame(ctx);
+            return;
         }
 
         egui::TopBottomPanel::top("tabs").show(ctx, |ui| {
@@ -187,72 +1618,512 @@ impl eframe::App for MyApp {
                 {
                     self.current_tab = Tab::Processes;
                 }
+                if ui
+                    .selectable_label(matches!(self.current_tab, Tab::Dashboard), "Dashboard")
+                    .clicked()
+                {
+                    self.current_tab = Tab::Dashboard;
+                }
+                if self.device_names.len() > 1
+                    && ui
+                        .selectable_label(matches!(self.current_tab, Tab::AllGpus), "All GPUs")
+                        .on_hover_text("The utilization of every GPU side by side")
+                        .clicked()
+                {
+                    self.current_tab = Tab::AllGpus;
+                }
+                ui.menu_button("Session", |ui| self.session_menu_ui(ui));
+                ui.menu_button("Snapshots", |ui| self.snapshot_menu_ui(ui));
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.toggle_value(&mut self.settings.show_settings_window, "⚙ Settings");
+                    ui.toggle_value(&mut self.settings.always_on_top, "📌")
+                        .on_hover_text("Always on top");
+                    if ui
+                        .button("🗖")
+                        .on_hover_text(
+                            "Overlay: a small borderless window with just the temperature, \
+                             utilization and power",
+                        )
+                        .clicked()
+                    {
+                        self.set_overlay(ctx, true);
+                    }
+                    if ui
+                        .button("📋")
+                        .on_hover_text("Copy the versions and device details for a bug report")
+                        .clicked()
+                    {
+                        self.copy_diagnostics(ctx);
+                    }
+                    if ui
+                        .button("⟳ Refresh now")
+                        .on_hover_text("Poll the device right away instead of waiting")
+                        .clicked()
+                    {
+                        self.poller.poll_now();
+                    }
+                    self.device_selector_ui(ui);
+                    if let Some(err) = &self.last_poll_error {
+                        ui.colored_label(egui::Color32::RED, "⚠ Polling failed")
+                            .on_hover_text(format!(
+                                "{err}\n{} failed polls in a row, retrying every {:.1} s",
+                                self.consecutive_failures,
+                                poller::backoff_interval(
+                                    self.settings.update_interval(),
+                                    self.consecutive_failures
+                                )
+                                .as_secs_f64()
+                            ));
+                    }
+                    if self.is_busy() {
+                        ui.add(egui::Spinner::new().size(14.0))
+                            .on_hover_text("Waiting for the driver to respond");
+                    }
+                });
             });
         });
 
+        egui::TopBottomPanel::bottom("status bar").show(ctx, |ui| self.status_bar_ui(ui));
+
+        let stale_age = self.stale_age();
+        if stale_age.is_some() {
+            // Keep counting the age up while no new readings arrive
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
+            if let Some(age) = stale_age {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!(
+                        "⏸ Last updated {} ago, the readings below are stale",
+                        format_age(age)
+                    ),
+                );
+                ui.visuals_mut().override_text_color = Some(ui.visuals().weak_text_color());
+            }
+            let transition = self.tab_transition(ctx);
+            if transition < 1.0 {
+                ui.multiply_opacity(transition);
+                ui.add_space((1.0 - transition) * TAB_TRANSITION_SLIDE);
+            }
             if let Some(system_state) = &mut self.current_state {
                 match self.current_tab {
                     Tab::Devices => {
                         ui.heading("Device Information");
                         ui.add_space(10.0);
-                        ui.horizontal(|ui| {
-                            ui.label(format!("Device: {}", system_state.device_state.name));
-                            ui.label(format!(
-                                "Driver version: {}",
-                                system_state.device_state.driver_version
-                            ));
-                            ui.label(format!(
-                                "CUDA version: {}",
-                                system_state.device_state.cuda_driver_version
-                            ));
-                        });
-                        ui.add_space(10.0);
+                        if self.settings.profile == Profile::Server {
+                            device::server_view_ui(
+                                ui,
+                                &system_state.device_state,
+                                &self.settings.thresholds,
+                                self.settings.memory_unit,
+                                self.device_view.smoothed_power(self.selected_device),
+                            );
+                            ui.add_space(10.0);
+                            process::compute_processes_ui(
+                                ui,
+                                &system_state.process_state.processes,
+                                self.settings.memory_unit,
+                            );
+                        } else {
+                            let compact = device::is_compact(ui);
+                            let plot = self.device_view.device_stats_plot(self.selected_device);
+                            let (temperature_trend, power_trend, utilization_trend) = (
+                                plot.temperature_trend(),
+                                plot.power_trend(),
+                                plot.utilization_trend(),
+                            );
+                            let time_to_throttle = plot.time_to_throttle();
+                            info_row(ui, compact, |ui| {
+                                ui.label(device_label_job(
+                                    ui,
+                                    self.selected_device,
+                                    &format!("Device: {}", system_state.device_state.name),
+                                ));
+                                device::display_badge_ui(
+                                    ui,
+                                    &system_state.device_state,
+                                    &system_state.process_state.processes,
+                                );
+                                ui.label(format!(
+                                    "Driver version: {}",
+                                    system_state.device_state.driver_version
+                                ));
+                                ui.label(format!(
+                                    "CUDA version: {}",
+                                    system_state
+                                        .device_state
+                                        .cuda_driver_version
+                                        .as_ref()
+                                        .map_or_else(|| String::from("N/A"), ToString::to_string)
+                                ));
+                                if let Some(compute_capability) =
+                                    device::format_compute_capability(&system_state.device_state)
+                                {
+                                    ui.label(compute_capability);
+                                }
+                            });
+                            ui.add_space(10.0);
 
-                        ui.horizontal(|ui| {
-                            ui.label(format!(
-                                "Temperature: {}°C",
-                                system_state.device_state.temperature
-                            ));
-                            ui.label(format!(
-                                "Memory usage: {} MiB / {} MiB",
-                                system_state.device_state.mem_info.used / 1_000_000,
-                                system_state.device_state.mem_info.total / 1_000_000
-                            ));
-                        });
+                            let thresholds = &self.settings.thresholds;
+                            info_row(ui, compact, |ui| {
+                                let temperature_exceeded =
+                                    thresholds.temperature_exceeded(&system_state.device_state);
+                                let sensor = thresholds.temperature_sensor;
+                                warning_label(
+                                    ui,
+                                    temperature_exceeded && sensor == TemperatureSensor::Core,
+                                    device::with_trend(
+                                        format!(
+                                            "Temperature: {}",
+                                            device::format_metric(
+                                                system_state.device_state.temperature,
+                                                "°C"
+                                            )
+                                        ),
+                                        temperature_trend,
+                                    ),
+                                );
+                                if let Some(time_to_throttle) = time_to_throttle {
+                                    ui.colored_label(
+                                        ui.visuals().warn_fg_color,
+                                        format!(
+                                            "{} until throttle at current rate",
+                                            device::format_time_to_throttle(time_to_throttle)
+                                        ),
+                                    );
+                                }
+                                if let Some(memory_temperature) =
+                                    system_state.device_state.memory_temperature
+                                {
+                                    warning_label(
+                                        ui,
+                                        temperature_exceeded && sensor == TemperatureSensor::Memory,
+                                        format!("Memory temperature: {memory_temperature}°C"),
+                                    );
+                                }
+                                if let Some(memory_slowdown_temperature) =
+                                    system_state.device_state.memory_slowdown_temperature
+                                {
+                                    ui.label(format!(
+                                        "Memory slowdown at: {memory_slowdown_temperature}°C"
+                                    ));
+                                }
+                                if let Some(slowdown_temperature) =
+                                    system_state.device_state.slowdown_temperature
+                                {
+                                    ui.label(format!("Slowdown at: {slowdown_temperature}°C"));
+                                }
+                                if let Some(shutdown_temperature) =
+                                    system_state.device_state.shutdown_temperature
+                                {
+                                    ui.label(format!("Shutdown at: {shutdown_temperature}°C"));
+                                }
+                                warning_label(
+                                    ui,
+                                    thresholds.memory_exceeded(&system_state.device_state),
+                                    match &system_state.device_state.mem_info {
+                                        Some(mem_info) => format!(
+                                            "Memory usage: {} / {}",
+                                            format_bytes(mem_info.used, self.settings.memory_unit),
+                                            format_bytes(mem_info.total, self.settings.memory_unit)
+                                        ),
+                                        None => String::from("Memory usage: N/A"),
+                                    },
+                                );
+                            });
+
+                            info_row(ui, compact, |ui| {
+                                match &system_state.device_state.utilization {
+                                    Some(utilization) => ui.label(format!(
+                                        "Utilization: {}, Memory {}%",
+                                        device::with_trend(
+                                            format!("GPU {}%", utilization.gpu),
+                                            utilization_trend
+                                        ),
+                                        utilization.memory
+                                    )),
+                                    None => ui.label("Utilization: N/A"),
+                                };
+                                if let Some(pstate) = system_state.device_state.pstate {
+                                    ui.label(format!("Performance state: P{pstate}"))
+                                        .on_hover_text(
+                                            "P0 is maximum performance, higher states save power",
+                                        );
+                                }
+                            });
+
+                            info_row(ui, compact, |ui| {
+                                let fan_speeds = &system_state.device_state.fan_speeds;
+                                if fan_speeds.is_empty() {
+                                    ui.label("No controllable fans");
+                                }
+                                let anomalies = self
+                                    .device_view
+                                    .device_stats_plot(self.selected_device)
+                                    .fan_history
+                                    .anomalies(system_state.device_state.temperature);
+                                for (i, fan) in fan_speeds.iter().enumerate() {
+                                    match fan {
+                                        Some(speed) => {
+                                            ui.label(format!("Fan {} speed: {}%", i + 1, speed))
+                                        }
+                                        None => ui.label(format!("Fan {} speed: N/A", i + 1)),
+                                    };
+                                    if let Some(anomaly) = anomalies.get(i).copied().flatten() {
+                                        ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                                            .on_hover_text(format!(
+                                                "{anomaly}, the fan may be failing"
+                                            ));
+                                    }
+                                }
+                            });
+
+                            ui.horizontal(|ui| {
+                                warning_label(
+                                    ui,
+                                    self.settings
+                                        .thresholds
+                                        .power_exceeded(&system_state.device_state),
+                                    device::with_trend(
+                                        format!(
+                                            "Power usage: {}",
+                                            device::format_power(
+                                                &system_state.device_state,
+                                                self.device_view
+                                                    .smoothed_power(self.selected_device)
+                                            )
+                                        ),
+                                        power_trend,
+                                    ),
+                                );
+                                let baseline = self.energy_baselines.get(&self.selected_device);
+                                if let (Some(&baseline), Some(total_energy)) =
+                                    (baseline, system_state.device_state.total_energy)
+                                {
+                                    ui.toggle_value(&mut self.settings.show_energy, "∑")
+                                        .on_hover_text(
+                                            "Show the energy used since the app started",
+                                        );
+                                    if self.settings.show_energy {
+                                        ui.label(format!(
+                                            "Energy since start: {} Wh",
+                                            units::number_format().format(
+                                                device::energy_wh(baseline, total_energy),
+                                                3
+                                            )
+                                        ));
+                                    }
+                                }
+                            });
+
+                            if let (Some(power_limit), Some(constraints)) = (
+                                system_state.device_state.power_limit,
+                                &system_state.device_state.power_limit_constraints,
+                            ) {
+                                ui.label(format!(
+                                    "Power limit: {} (min {}, max {})",
+                                    device::format_watts(device::milliwatts_to_watts(power_limit)),
+                                    device::format_watts(device::milliwatts_to_watts(
+                                        constraints.min_limit
+                                    )),
+                                    device::format_watts(device::milliwatts_to_watts(
+                                        constraints.max_limit
+                                    ))
+                                ));
+                            }
+
+                            // Hidden in read-only mode, which the actions enforce as well
+                            #[cfg(feature = "power-control")]
+                            if !read_only::enabled() {
+                                if let Some(limit) = self
+                                    .device_view
+                                    .power_limit_control
+                                    .ui(ui, &system_state.device_state)
+                                {
+                                    self.device_view.power_limit_control.error =
+                                        match self.source.nvml().map(|nvml| {
+                                            set_power_limit(nvml, self.selected_device, limit)
+                                        }) {
+                                            Some(Ok(())) => None,
+                                            Some(Err(SourceError::Nvml(
+                                                NvmlError::NoPermission,
+                                            ))) => Some(String::from(
+                                                "Changing the power limit requires \
+                                                 root/administrator privileges",
+                                            )),
+                                            Some(Err(err)) => {
+                                                Some(format!("Failed to set power limit: {err}"))
+                                            }
+                                            None => Some(String::from(
+                                                "Changing the power limit requires NVML",
+                                            )),
+                                        };
+                                }
+                            }
 
-                        ui.horizontal(|ui| {
-                            for (i, fan) in system_state.device_state.fan_speeds.iter().enumerate()
+                            #[cfg(feature = "admin")]
+                            if !read_only::enabled()
+                                && self.device_view.gpu_reset_control.ui(
+                                    ui,
+                                    &system_state.device_state,
+                                    system_state.process_state.processes.len(),
+                                )
                             {
-                                ui.label(format!("Fan {} speed: {}%", i + 1, fan));
+                                self.device_view.gpu_reset_control.error =
+                                    match source::reset_gpu(self.selected_device) {
+                                        Ok(()) => {
+                                            self.toasts.push(format!(
+                                                "Reset {}",
+                                                system_state.device_state.name
+                                            ));
+                                            self.poller.poll_now();
+                                            None
+                                        }
+                                        Err(err) => Some(format!("Failed to reset the GPU: {err}")),
+                                    };
                             }
-                        });
 
-                        ui.label(format!(
-                            "Power usage: {}W",
-                            system_state.device_state.power_usage / 1000
-                        ));
+                            info_row(ui, compact, |ui| {
+                                metric::readouts_ui(
+                                    ui,
+                                    &system_state.device_state.metrics,
+                                    &self.settings.shown_metrics(),
+                                );
+                                if let Some(nvml) = self.source.nvml() {
+                                    if ui.button("Supported clocks...").clicked() {
+                                        self.supported_clocks = Some((
+                                            self.selected_device,
+                                            poll_supported_clocks(nvml, self.selected_device),
+                                        ));
+                                    }
+                                }
+                            });
+
+                            let mig_instances = &system_state.device_state.mig_instances;
+                            if !mig_instances.is_empty() {
+                                ui.add_space(10.0);
+                                ui.label(format!("MIG instances: {}", mig_instances.len()));
+                                for instance in mig_instances {
+                                    ui.label(mig_instance_label(
+                                        instance,
+                                        self.settings.memory_unit,
+                                    ));
+                                }
+                            }
+
+                            ui.add_space(10.0);
+                            process::top_processes_ui(
+                                ui,
+                                &system_state.process_state.processes,
+                                device::memory_total(&system_state.device_state),
+                                self.settings.memory_unit,
+                            );
+                        }
 
                         ui.add_space(10.0);
 
-                        self.device_view.device_stats_plot.plot_ui(ui);
+                        match &mut self.data_source {
+                            DataSource::Live => {
+                                let plot = self.device_view.device_stats_plot(self.selected_device);
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .button("🗑 Clear history")
+                                        .on_hover_text("Start the plots over from now")
+                                        .clicked()
+                                    {
+                                        plot.clear_history();
+                                    }
+                                    ui.menu_button("📷 Save plot", |ui| {
+                                        capture_menu_ui(ui, &mut self.pending_capture)
+                                    });
+                                });
+                                self.device_view.plot_ui(ui, self.selected_device);
+                            }
+                            DataSource::Replay(replay) => {
+                                let stop_replay = replay.controls_ui(ui);
+                                replay.plot_ui(ui);
+                                if stop_replay {
+                                    self.data_source = DataSource::Live;
+                                }
+                            }
+                        }
                     }
                     Tab::Processes => {
                         ui.heading("Process Information");
                         ui.add_space(10.0);
 
-                        self.process_table.table_ui(ui);
+                        let messages = if read_only::enabled() {
+                            Vec::new()
+                        } else {
+                            self.bulk_kill.ui(
+                                ui,
+                                &system_state.process_state.processes,
+                                device::memory_total(&system_state.device_state),
+                                self.settings.memory_unit,
+                            )
+                        };
+                        if !messages.is_empty() {
+                            for message in messages {
+                                self.toasts.push(message);
+                            }
+                            self.poller.poll_now();
+                        }
+                        ui.add_space(10.0);
 
-                        if self.process_table.show_plot_window {}
+                        self.process_table.table_ui(ui);
                     }
+                    Tab::Dashboard => device::dashboard_ui(
+                        ui,
+                        &system_state.device_state,
+                        device::device_color(self.selected_device),
+                        &self.settings.thresholds,
+                        self.settings.memory_unit,
+                        self.device_view.smoothed_power(self.selected_device),
+                    ),
+                    Tab::AllGpus => self.device_view.grid_ui(ui, &self.device_names),
                 }
             } else {
                 ui.label("Waiting for data...");
             }
         });
 
-        // Request a repaint on the next frame
-        ctx.request_repaint();
+        self.settings.window_ui(ctx);
+        self.snapshot_comparison_ui(ctx);
+        self.supported_clocks_ui(ctx);
+        self.process_table.plot_window_ui(ctx);
+        if let DataSource::Live = self.data_source {
+            self.device_view
+                .detached_plots_ui(ctx, self.selected_device);
+        }
+        self.apply_window_level(ctx);
+        self.toasts.show(ctx);
+
+        if self.is_idle() {
+            ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("idle dimming"),
+            ))
+            .rect_filled(ctx.screen_rect(), 0.0, egui::Color32::from_black_alpha(140));
+        }
+
+        let actions = self.palette_actions();
+        if let Some(action) = self.command_palette.ui(ctx, &actions) {
+            self.run_palette_action(ctx, action);
+        }
+
+        if ctx.input(|i| i.key_pressed(egui::Key::F12)) {
+            self.show_diagnostics = !self.show_diagnostics;
+        }
+        if self.show_diagnostics {
+            self.diagnostics_ui(ctx);
+        }
+
+        #[cfg(feature = "tray")]
+        self.handle_tray(ctx);
+
+        self.schedule_next_frame(ctx);
 
         // Do potential cleanup stuff here
         if ctx.input(|i| i.viewport().close_requested()) {}
@@ -0,0 +1,153 @@
+use eframe::egui;
+use nvml_wrapper::enum_wrappers::device::Clock;
+use nvml_wrapper::error::NvmlError;
+use nvml_wrapper::Device;
+use serde::ser::Serializer;
+
+use crate::device;
+use crate::theme::Series;
+
+/// A secondary reading described in one place. Every metric in [`METRICS`] is polled by each
+/// backend into [`DeviceState::metrics`](crate::device::DeviceState::metrics), then shown as a
+/// readout, recorded as a CSV column, served by the HTTP API and, if it has a series, plotted,
+/// all without code of its own.
+///
+/// The core readings (temperature, memory, power, utilization and throttling) keep their own
+/// fields and plots, because the thresholds, alerts, trends and dedicated plots are built on
+/// them.
+#[derive(Debug, Clone, Copy)]
+pub struct Metric {
+    /// Stable identifier, used as the CSV column, the key in the JSON readings and to remember
+    /// whether it is shown.
+    pub id: &'static str,
+    pub name: &'static str,
+    /// The unit of the plot's y axis.
+    pub unit: &'static str,
+    /// Polls the metric through NVML, `None` if the device doesn't report it.
+    pub poll_nvml: fn(&Device) -> Option<f64>,
+    /// The `nvidia-smi --query-gpu` field reporting it, read without units.
+    pub nvidia_smi_field: &'static str,
+    /// Formats a reading for the readouts, including its unit.
+    pub format: fn(Option<f64>) -> String,
+    /// The color it is plotted in, `None` for metrics that are only shown as a readout.
+    pub plot: Option<Series>,
+}
+
+pub const METRIC_COUNT: usize = 4;
+
+/// The value of every metric in [`METRICS`], in the same order.
+pub type MetricValues = [Option<f64>; METRIC_COUNT];
+
+pub static METRICS: [Metric; METRIC_COUNT] = [
+    Metric {
+        id: "graphics_clock_mhz",
+        name: "Graphics clock",
+        unit: "MHz",
+        poll_nvml: |device| poll_clock(device, Clock::Graphics),
+        nvidia_smi_field: "clocks.gr",
+        format: format_clock,
+        plot: Some(Series::Clock),
+    },
+    Metric {
+        id: "sm_clock_mhz",
+        name: "SM clock",
+        unit: "MHz",
+        poll_nvml: |device| poll_clock(device, Clock::SM),
+        nvidia_smi_field: "clocks.sm",
+        format: format_clock,
+        plot: None,
+    },
+    Metric {
+        id: "memory_clock_mhz",
+        name: "Memory clock",
+        unit: "MHz",
+        poll_nvml: |device| poll_clock(device, Clock::Memory),
+        nvidia_smi_field: "clocks.mem",
+        format: format_clock,
+        plot: None,
+    },
+    Metric {
+        id: "video_clock_mhz",
+        name: "Video clock",
+        unit: "MHz",
+        poll_nvml: |device| poll_clock(device, Clock::Video),
+        nvidia_smi_field: "clocks.video",
+        format: format_clock,
+        plot: None,
+    },
+];
+
+fn poll_clock(device: &Device, clock: Clock) -> Option<f64> {
+    match device.clock_info(clock.clone()) {
+        Ok(mhz) => Some(f64::from(mhz)),
+        Err(NvmlError::NotSupported) => None,
+        Err(err) => {
+            log::warn!("Failed to query {clock:?} clock: {err}");
+            None
+        }
+    }
+}
+
+fn format_clock(mhz: Option<f64>) -> String {
+    device::format_clock(mhz.map(|mhz| mhz as u32))
+}
+
+/// Polls every metric through NVML.
+pub fn poll_nvml(device: &Device) -> MetricValues {
+    METRICS.map(|metric| (metric.poll_nvml)(device))
+}
+
+/// Serializes the values as an object keyed by metric id, so readers of the JSON don't have
+/// to know the order of [`METRICS`].
+pub fn serialize_by_id<S: Serializer>(
+    values: &MetricValues,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(METRICS.iter().map(|metric| metric.id).zip(values))
+}
+
+/// Which metrics are shown, indexed like [`METRICS`].
+pub type MetricVisibility = [bool; METRIC_COUNT];
+
+/// Shows every metric except the ones in `hidden`, which holds metric ids.
+pub fn visibility(hidden: &[String]) -> MetricVisibility {
+    METRICS.map(|metric| !hidden.iter().any(|id| id == metric.id))
+}
+
+/// The readouts of the shown metrics.
+pub fn readouts_ui(ui: &mut egui::Ui, values: &MetricValues, visibility: &MetricVisibility) {
+    for ((metric, value), _) in METRICS
+        .iter()
+        .zip(values)
+        .zip(visibility)
+        .filter(|(_, &shown)| shown)
+    {
+        ui.label(format!("{}: {}", metric.name, (metric.format)(*value)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids_are_unique() {
+        for (index, metric) in METRICS.iter().enumerate() {
+            assert!(METRICS[..index].iter().all(|other| other.id != metric.id));
+            assert!(!metric.id.contains(','));
+            assert!(!metric.nvidia_smi_field.contains(','));
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!((METRICS[0].format)(Some(1800.0)), "1,800 MHz");
+        assert_eq!((METRICS[0].format)(None), "N/A");
+    }
+
+    #[test]
+    fn test_visibility() {
+        let visibility = visibility(&[String::from("sm_clock_mhz")]);
+        assert_eq!(visibility, [true, false, true, true]);
+    }
+}
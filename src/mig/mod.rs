@@ -0,0 +1,115 @@
+use std::os::raw::c_uint;
+
+use nvml_wrapper::error::{nvml_try, NvmlError};
+use nvml_wrapper::struct_wrappers::device::{MemoryInfo, Utilization};
+use nvml_wrapper::Device;
+use nvml_wrapper_sys::bindings::{nvmlDevice_t, NvmlLib, NVML_DEVICE_MIG_ENABLE};
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+#[cfg(target_os = "windows")]
+const LIB_PATH: &str = "nvml.dll";
+#[cfg(not(target_os = "windows"))]
+const LIB_PATH: &str = "libnvidia-ml.so";
+
+// `nvml-wrapper` doesn't wrap the MIG functions and doesn't give access to its own copy of the
+// library, so we load it a second time. NVML is already initialized by then, and the device
+// handles are shared between both copies.
+static NVML_LIB: Lazy<Option<NvmlLib>> = Lazy::new(|| {
+    unsafe { NvmlLib::new(LIB_PATH) }
+        .inspect_err(|err| log::warn!("Failed to load NVML for MIG queries: {err}"))
+        .ok()
+});
+
+/// A MIG (Multi-Instance GPU) instance of a physical GPU.
+#[derive(Debug, Clone, Serialize)]
+pub struct MigInstance {
+    pub index: u32,
+    pub name: String,
+    pub mem_info: MemoryInfo,
+    /// Most drivers don't report utilization per MIG instance.
+    pub utilization: Option<Utilization>,
+}
+
+fn mig_enabled(lib: &NvmlLib, device: &Device) -> Result<bool, NvmlError> {
+    let mut current_mode: c_uint = 0;
+    let mut pending_mode: c_uint = 0;
+    unsafe {
+        nvml_try(lib.nvmlDeviceGetMigMode(device.handle(), &mut current_mode, &mut pending_mode))?;
+    }
+    Ok(current_mode == NVML_DEVICE_MIG_ENABLE)
+}
+
+fn mig_devices<'nvml>(
+    lib: &NvmlLib,
+    device: &Device<'nvml>,
+) -> Result<Vec<(u32, Device<'nvml>)>, NvmlError> {
+    let mut max_count: c_uint = 0;
+    unsafe { nvml_try(lib.nvmlDeviceGetMaxMigDeviceCount(device.handle(), &mut max_count))? };
+
+    let mut mig_devices = Vec::new();
+    for index in 0..max_count {
+        let mut handle: nvmlDevice_t = std::ptr::null_mut();
+        match unsafe {
+            nvml_try(lib.nvmlDeviceGetMigDeviceHandleByIndex(device.handle(), index, &mut handle))
+        } {
+            Ok(()) => {}
+            // Slots without an instance configured
+            Err(NvmlError::NotFound) => continue,
+            Err(err) => return Err(err),
+        }
+
+        let mut is_mig_handle: c_uint = 0;
+        unsafe { nvml_try(lib.nvmlDeviceIsMigDeviceHandle(handle, &mut is_mig_handle))? };
+        if is_mig_handle != 0 {
+            // Safety: the handle was just handed out by NVML
+            mig_devices.push((index, unsafe { Device::new(handle, device.nvml()) }));
+        }
+    }
+    Ok(mig_devices)
+}
+
+/// The MIG instances of `device`, or nothing if MIG mode isn't enabled or supported.
+pub fn poll_mig_instances(device: &Device) -> Vec<MigInstance> {
+    // Drivers that predate MIG don't export the functions at all
+    let Some(lib) = NVML_LIB
+        .as_ref()
+        .filter(|lib| lib.nvmlDeviceGetMigMode.is_ok())
+    else {
+        return Vec::new();
+    };
+
+    match mig_enabled(lib, device) {
+        Ok(true) => {}
+        Ok(false) | Err(NvmlError::NotSupported) => return Vec::new(),
+        Err(err) => {
+            log::warn!("Failed to query MIG mode: {err}");
+            return Vec::new();
+        }
+    }
+
+    let mig_devices = match mig_devices(lib, device) {
+        Ok(mig_devices) => mig_devices,
+        Err(err) => {
+            log::warn!("Failed to enumerate MIG instances: {err}");
+            return Vec::new();
+        }
+    };
+
+    mig_devices
+        .into_iter()
+        .filter_map(|(index, mig_device)| {
+            let mem_info = mig_device
+                .memory_info()
+                .inspect_err(|err| log::warn!("Failed to query MIG instance {index}: {err}"))
+                .ok()?;
+            Some(MigInstance {
+                index,
+                name: mig_device.name().unwrap_or_else(|_| format!("MIG {index}")),
+                mem_info,
+                utilization: mig_device.utilization_rates().ok(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,109 @@
+use eframe::egui;
+
+/// A searchable list of actions, opened with Ctrl+P (Cmd+P on macOS).
+#[derive(Debug, Clone, Default)]
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    /// Position of the highlighted entry among the matching actions.
+    highlighted: usize,
+}
+
+impl CommandPalette {
+    /// Draws the palette if it's open. Returns the action the user picked, if any.
+    pub fn ui<A: Clone>(&mut self, ctx: &egui::Context, actions: &[(String, A)]) -> Option<A> {
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::P)) {
+            self.open = !self.open;
+            self.query.clear();
+            self.highlighted = 0;
+        }
+        if !self.open {
+            return None;
+        }
+
+        // Taken before the search field gets to see them
+        let (down, up, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+
+        let matching: Vec<&(String, A)> = actions
+            .iter()
+            .filter(|(label, _)| matches(label, &self.query))
+            .collect();
+        if down {
+            self.highlighted += 1;
+        }
+        if up {
+            self.highlighted = self.highlighted.saturating_sub(1);
+        }
+        self.highlighted = self.highlighted.min(matching.len().saturating_sub(1));
+
+        let mut picked = enter
+            .then(|| matching.get(self.highlighted))
+            .flatten()
+            .map(|(_, action)| action.clone());
+
+        egui::Window::new("Command palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Type to search actions")
+                        .desired_width(300.0),
+                );
+                response.request_focus();
+                if response.changed() {
+                    self.highlighted = 0;
+                }
+
+                ui.separator();
+                if matching.is_empty() {
+                    ui.weak("No matching actions");
+                }
+                for (position, (label, action)) in matching.iter().enumerate() {
+                    let response = ui.selectable_label(position == self.highlighted, label);
+                    if (up || down) && position == self.highlighted {
+                        response.scroll_to_me(None);
+                    }
+                    if response.clicked() {
+                        picked = Some(action.clone());
+                    }
+                }
+            });
+
+        if picked.is_some() || escape {
+            self.open = false;
+        }
+        picked
+    }
+}
+
+/// Whether `label` contains every word of `query`, ignoring case.
+fn matches(label: &str, query: &str) -> bool {
+    let label = label.to_lowercase();
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .all(|word| label.contains(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("Export CSV", ""));
+        assert!(matches("Export CSV", "csv"));
+        assert!(matches("Switch to GPU 1: RTX 4090", "gpu 4090"));
+        assert!(!matches("Switch to GPU 1: RTX 4090", "gpu 3090"));
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+/// The longest interval polling backs off to while polls keep failing.
+const MAX_BACKOFF_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy)]
+enum PollerCommand {
+    SetDevice(usize),
+    SetInterval(Duration),
+    /// Wakes the poller up without changing anything, so that it polls right away.
+    PollNow,
+}
+
+#[derive(Debug, Clone)]
+pub struct PollResult<T> {
+    pub device_index: usize,
+    pub value: T,
+    /// How long the poll function took to run.
+    pub duration: Duration,
+}
+
+/// Runs a poll function on a background thread so that slow NVML calls don't block the UI.
+///
+/// The poll function is called with the currently selected device index once per interval,
+/// and immediately whenever the device is changed or a poll is asked for. Changing the
+/// interval only moves the next poll.
+pub struct Poller<T> {
+    command_sender: Sender<PollerCommand>,
+    result_receiver: Receiver<PollResult<T>>,
+    in_flight_since: Arc<Mutex<Option<Instant>>>,
+    interval: Duration,
+}
+
+impl<T: Send + 'static> Poller<T> {
+    pub fn spawn<F>(
+        ctx: egui::Context,
+        device_index: usize,
+        interval: Duration,
+        mut poll: F,
+    ) -> Self
+    where
+        F: FnMut(usize) -> T + Send + 'static,
+    {
+        let (command_sender, command_receiver) = mpsc::channel();
+        let (result_sender, result_receiver) = mpsc::channel();
+        let in_flight_since = Arc::new(Mutex::new(None));
+
+        let thread_in_flight_since = Arc::clone(&in_flight_since);
+        thread::spawn(move || {
+            let mut device_index = device_index;
+            let mut interval = interval;
+
+            loop {
+                let started_at = Instant::now();
+                *thread_in_flight_since.lock().unwrap() = Some(started_at);
+                let value = poll(device_index);
+                let duration = started_at.elapsed();
+                *thread_in_flight_since.lock().unwrap() = None;
+
+                if result_sender
+                    .send(PollResult {
+                        device_index,
+                        value,
+                        duration,
+                    })
+                    .is_err()
+                {
+                    // The app has shut down
+                    return;
+                }
+                ctx.request_repaint();
+
+                // Sleep until the next poll. A new interval only moves that deadline, so
+                // backing off from failing polls doesn't poll again on the spot.
+                let polled_at = Instant::now();
+                loop {
+                    let timeout = (polled_at + interval).saturating_duration_since(Instant::now());
+                    match command_receiver.recv_timeout(timeout) {
+                        Ok(PollerCommand::SetDevice(index)) => {
+                            device_index = index;
+                            break;
+                        }
+                        Ok(PollerCommand::SetInterval(new_interval)) => interval = new_interval,
+                        Ok(PollerCommand::PollNow) | Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+
+                // Only the latest device selection matters if several were queued up
+                loop {
+                    match command_receiver.try_recv() {
+                        Ok(PollerCommand::SetDevice(index)) => device_index = index,
+                        Ok(PollerCommand::SetInterval(new_interval)) => interval = new_interval,
+                        Ok(PollerCommand::PollNow) => {}
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => return,
+                    }
+                }
+            }
+        });
+
+        Self {
+            command_sender,
+            result_receiver,
+            in_flight_since,
+            interval,
+        }
+    }
+
+    pub fn set_device(&self, device_index: usize) {
+        let _ = self
+            .command_sender
+            .send(PollerCommand::SetDevice(device_index));
+    }
+
+    pub fn set_interval(&mut self, interval: Duration) {
+        if interval != self.interval {
+            self.interval = interval;
+            let _ = self
+                .command_sender
+                .send(PollerCommand::SetInterval(interval));
+        }
+    }
+
+    pub fn poll_now(&self) {
+        let _ = self.command_sender.send(PollerCommand::PollNow);
+    }
+
+    pub fn try_recv(&self) -> Option<PollResult<T>> {
+        self.result_receiver.try_recv().ok()
+    }
+
+    /// How long the currently running poll has been in flight, if there is one.
+    pub fn busy_for(&self) -> Option<Duration> {
+        self.in_flight_since
+            .lock()
+            .unwrap()
+            .map(|since| since.elapsed())
+    }
+}
+
+/// Doubles `interval` for every consecutive failure, up to `MAX_BACKOFF_INTERVAL`.
+pub fn backoff_interval(interval: Duration, consecutive_failures: u32) -> Duration {
+    if consecutive_failures == 0 {
+        return interval;
+    }
+    interval
+        .saturating_mul(2u32.saturating_pow(consecutive_failures))
+        .min(MAX_BACKOFF_INTERVAL)
+        .max(interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_interval() {
+        let interval = Duration::from_millis(100);
+        assert_eq!(backoff_interval(interval, 0), interval);
+        assert_eq!(backoff_interval(interval, 1), Duration::from_millis(200));
+        assert_eq!(backoff_interval(interval, 3), Duration::from_millis(800));
+        assert_eq!(backoff_interval(interval, 64), MAX_BACKOFF_INTERVAL);
+        // Intervals that are already longer than the cap aren't shortened
+        let long_interval = Duration::from_secs(60);
+        assert_eq!(backoff_interval(long_interval, 2), long_interval);
+    }
+
+    #[test]
+    fn test_backs_off_from_failing_polls() {
+        let interval = Duration::from_millis(10);
+        let mut poller = Poller::spawn(egui::Context::default(), 0, interval, |_| {
+            Err::<(), _>("driver gone")
+        });
+        let mut polled_at = Vec::new();
+        while polled_at.len() < 4 {
+            if poller
+                .try_recv()
+                .is_some_and(|result| result.value.is_err())
+            {
+                polled_at.push(Instant::now());
+                poller.set_interval(backoff_interval(interval, polled_at.len() as u32));
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        let gaps: Vec<Duration> = polled_at.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        // 20, 40 and 80 ms apart, instead of polling again as soon as the interval changes
+        assert!(gaps[0] >= Duration::from_millis(15), "{gaps:?}");
+        assert!(gaps[2] >= Duration::from_millis(60), "{gaps:?}");
+        assert!(gaps[2] > gaps[0], "{gaps:?}");
+    }
+}
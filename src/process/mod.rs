@@ -1,21 +1,88 @@
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt::Display;
+use std::process::Command;
+use std::time::{Duration, Instant};
 
+use circular_buffer::CircularBuffer;
 use eframe::egui::{self, Color32, Label, RichText};
 use egui_extras::{Column, TableBuilder};
+use egui_plot::{Legend, Line, Plot, PlotPoints};
 
 use nvml_wrapper::enums::device::UsedGpuMemory;
 use nvml_wrapper::struct_wrappers::device::ProcessInfo;
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+use crate::read_only;
+use crate::units::{format_bytes, UnitSystem, MIB};
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ProcessState {
     pub processes: Vec<ProcessData>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+impl ProcessState {
+    /// Combines the graphics and compute process lists. A process that appears in both is
+    /// merged into a single entry of kind [`ProcessKind::Both`].
+    pub fn from_lists(graphics: Vec<ProcessData>, compute: Vec<ProcessData>) -> Self {
+        let mut processes = graphics;
+        for process in compute {
+            match processes
+                .iter_mut()
+                .find(|existing| existing.process_info.pid == process.process_info.pid)
+            {
+                Some(existing) => existing.merge(process),
+                None => processes.push(process),
+            }
+        }
+        Self { processes }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ProcessKind {
     Compute,
     Graphics,
+    /// Uses the GPU for both compute and graphics.
+    Both,
+}
+
+impl ProcessKind {
+    /// The kinds that get a badge of their own, `Both` shows the compute and graphics badges.
+    fn badge_kinds(&self) -> &'static [ProcessKind] {
+        match self {
+            ProcessKind::Compute => &[ProcessKind::Compute],
+            ProcessKind::Graphics => &[ProcessKind::Graphics],
+            ProcessKind::Both => &[ProcessKind::Compute, ProcessKind::Graphics],
+        }
+    }
+
+    fn badge_color(&self) -> Color32 {
+        match self {
+            ProcessKind::Compute => Color32::from_rgb(118, 185, 0),
+            ProcessKind::Graphics | ProcessKind::Both => Color32::from_rgb(70, 130, 220),
+        }
+    }
+
+    /// The color of the kind's memory sub-total, which unlike the badges tells `Both` apart
+    /// from `Graphics`.
+    fn total_color(&self) -> Color32 {
+        match self {
+            ProcessKind::Both => ProcessKind::Compute
+                .badge_color()
+                .lerp_to_gamma(ProcessKind::Graphics.badge_color(), 0.5),
+            kind => kind.badge_color(),
+        }
+    }
+
+    fn badge_letter(&self) -> &'static str {
+        match self {
+            ProcessKind::Compute => "C",
+            ProcessKind::Graphics => "G",
+            ProcessKind::Both => "CG",
+        }
+    }
 }
 
 impl Display for ProcessKind {
@@ -23,23 +90,175 @@ impl Display for ProcessKind {
         match self {
             ProcessKind::Compute => write!(f, "Compute"),
             ProcessKind::Graphics => write!(f, "Graphics"),
+            ProcessKind::Both => write!(f, "Compute + Graphics"),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// The process that started a GPU process, which doesn't have to use the GPU itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParentProcess {
+    pub pid: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ProcessData {
     pub process_info: ProcessInfo,
     pub process_kind: ProcessKind,
     pub process_name: String,
+    pub parent: Option<ParentProcess>,
+    /// The full command line, `None` if it couldn't be read (e.g. for other users' processes).
+    /// Left out of the JSON readings, since arguments often carry tokens and passwords.
+    #[serde(skip)]
+    pub command_line: Option<String>,
+    /// Index of the GPU the process runs on, only set in the table of all GPUs.
+    pub gpu: Option<usize>,
+}
+
+impl ProcessData {
+    /// Merges the entry of the same process from the other process list into this one.
+    ///
+    /// Both entries report the memory of the whole process, so the larger figure is kept
+    /// instead of adding them up.
+    fn merge(&mut self, other: ProcessData) {
+        if self.process_kind != other.process_kind {
+            self.process_kind = ProcessKind::Both;
+        }
+        self.process_info.used_gpu_memory = match (
+            &self.process_info.used_gpu_memory,
+            &other.process_info.used_gpu_memory,
+        ) {
+            (UsedGpuMemory::Used(a), UsedGpuMemory::Used(b)) => UsedGpuMemory::Used(*a.max(b)),
+            (UsedGpuMemory::Used(val), UsedGpuMemory::Unavailable)
+            | (UsedGpuMemory::Unavailable, UsedGpuMemory::Used(val)) => UsedGpuMemory::Used(*val),
+            (UsedGpuMemory::Unavailable, UsedGpuMemory::Unavailable) => UsedGpuMemory::Unavailable,
+        };
+    }
+
+    /// Used GPU memory in bytes, treating unavailable values as zero.
+    pub fn used_memory(&self) -> u64 {
+        match self.process_info.used_gpu_memory {
+            UsedGpuMemory::Used(val) => val,
+            UsedGpuMemory::Unavailable => 0,
+        }
+    }
+
+    /// Whether the process holds too little GPU memory to matter, including processes whose
+    /// usage can't be read.
+    pub fn is_idle(&self) -> bool {
+        self.used_memory() < IDLE_PROCESS_MEMORY
+    }
+
+    /// Share of the device's total memory used by this process, in percent.
+    pub fn memory_percentage(&self, total_memory: u64) -> Option<f64> {
+        match self.process_info.used_gpu_memory {
+            UsedGpuMemory::Used(val) if total_memory > 0 => {
+                Some(val as f64 / total_memory as f64 * 100.0)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl ProcessData {
+    /// A compute process named "python", for tests.
+    pub fn mock(pid: u32, used_gpu_memory: UsedGpuMemory) -> Self {
+        Self {
+            process_info: ProcessInfo {
+                pid,
+                used_gpu_memory,
+                gpu_instance_id: None,
+                compute_instance_id: None,
+            },
+            process_kind: ProcessKind::Compute,
+            process_name: String::from("python"),
+            parent: None,
+            command_line: None,
+            gpu: None,
+        }
+    }
 }
 
+/// How many processes the top memory users list on the Devices tab shows.
+pub const TOP_PROCESS_COUNT: usize = 5;
+
+/// Height of the bar above the table showing each process's share of the device memory.
+const MEMORY_BAR_HEIGHT: f32 = 16.0;
+
+/// Processes using less GPU memory than this are hidden by "Hide idle processes".
+const IDLE_PROCESS_MEMORY: u64 = 16 * MIB;
+
+/// The smallest change in memory usage between two refreshes that gets highlighted. Processes
+/// using more than ten times this have to change by a tenth.
+const SIGNIFICANT_MEMORY_CHANGE: u64 = 64 * MIB;
+
+/// How long a new process or a change in memory usage stays highlighted.
+const CHANGE_HIGHLIGHT_DURATION: Duration = Duration::from_secs(2);
+
+/// How long a confirmed kill waits before it fires, to give a last chance to cancel.
+const KILL_COUNTDOWN: Duration = Duration::from_secs(3);
+
+/// Default time span of the memory delta column, in seconds.
+const DEFAULT_DELTA_WINDOW_S: u32 = 60;
+
+/// How a process differs from the previous refresh of the list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProcessChange {
+    New,
+    MemoryGrew,
+    MemoryShrank,
+}
+
+impl ProcessChange {
+    fn color(self) -> Color32 {
+        match self {
+            ProcessChange::New => Color32::from_rgba_unmultiplied(60, 200, 90, 90),
+            ProcessChange::MemoryGrew => Color32::from_rgba_unmultiplied(230, 150, 40, 70),
+            ProcessChange::MemoryShrank => Color32::from_rgba_unmultiplied(80, 140, 230, 70),
+        }
+    }
+}
+
+/// The processes that are new or whose memory usage changed significantly since `previous`,
+/// the memory usage of each pid at the last refresh.
+fn process_changes(
+    previous: &HashMap<u32, u64>,
+    processes: &[ProcessData],
+) -> Vec<(u32, ProcessChange)> {
+    processes
+        .iter()
+        .filter_map(|process| {
+            let pid = process.process_info.pid;
+            let used = process.used_memory();
+            let Some(&previous_used) = previous.get(&pid) else {
+                return Some((pid, ProcessChange::New));
+            };
+            let threshold = SIGNIFICANT_MEMORY_CHANGE.max(previous_used / 10);
+            if used >= previous_used + threshold {
+                Some((pid, ProcessChange::MemoryGrew))
+            } else if used + threshold <= previous_used {
+                Some((pid, ProcessChange::MemoryShrank))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// The pid and device of a process, which is listed once per GPU it uses.
+type ProcessKey = (u32, Option<usize>);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum SortKind {
     Pid,
     Type,
     ProcessName,
     Memory,
+    MemoryPercentage,
+    Gpu,
+    MemoryDelta,
 }
 
 #[derive(Debug, Clone)]
@@ -49,9 +268,49 @@ pub struct ProcessTable {
     clickable: bool,
     sort_descending: bool,
     sort_kind: Option<SortKind>,
+    group_by_kind: bool,
+    /// Nest processes under their parents, grouped by the process that launched them.
+    tree_view: bool,
+    /// Show the process kind as colored badges instead of text.
+    kind_badges: bool,
+    /// Leave out processes using next to no GPU memory, unless they are pinned.
+    hide_idle: bool,
     pub processes: Vec<ProcessData>,
+    /// Total memory of the device the processes are running on, in bytes.
+    pub total_memory: u64,
+    pub memory_unit: UnitSystem,
     pub show_plot_window: bool,
-    selection: HashSet<usize>,
+    /// Pids of the selected processes. Keyed on the pid rather than the row, so that the same
+    /// processes stay selected when the table is sorted or refreshed.
+    selection: HashSet<u32>,
+    /// Pid of the process that keyboard navigation operates on.
+    focused_pid: Option<u32>,
+    /// Pids of the processes that are shown above the others regardless of the sort order.
+    pinned: HashSet<u32>,
+    /// Show only the process with this pid.
+    pid_filter: Option<u32>,
+    /// A pid to select once it shows up in the list, see [`ProcessTable::watch`].
+    pending_watch: Option<u32>,
+    /// The process under the pointer, highlighted in the memory bar.
+    hovered_pid: Option<u32>,
+    /// Seconds since the start of the session and used memory in MiB of each selected
+    /// process, for the memory plot window.
+    memory_history: HashMap<u32, CircularBuffer<1000, [f64; 2]>>,
+    /// Seconds since the start of the session and used memory in bytes of every process over
+    /// the last `delta_window_s`, keyed on the pid and device, for the memory delta column.
+    memory_samples: HashMap<ProcessKey, VecDeque<(f64, u64)>>,
+    /// How far back the memory delta column looks, in seconds.
+    delta_window_s: u32,
+    /// Memory usage of each process at the previous refresh, `None` before the first one.
+    previous_memory: Option<HashMap<u32, u64>>,
+    /// Processes that changed recently and when, to highlight their rows.
+    changes: HashMap<u32, (ProcessChange, Instant)>,
+    /// List the processes of every device in one table, see [`ProcessTable::shows_all_gpus`].
+    pub all_gpus: bool,
+    /// Names of the devices, for the GPU column of the table of all GPUs.
+    pub device_names: Vec<String>,
+    /// Total memory of each device in bytes, for the table of all GPUs.
+    pub gpu_total_memory: HashMap<usize, u64>,
 }
 
 impl Default for ProcessTable {
@@ -62,93 +321,777 @@ impl Default for ProcessTable {
             clickable: true,
             sort_descending: true,
             sort_kind: None,
+            group_by_kind: false,
+            tree_view: false,
+            kind_badges: false,
+            hide_idle: false,
             processes: Vec::new(),
+            total_memory: 0,
+            memory_unit: UnitSystem::default(),
             show_plot_window: false,
             selection: Default::default(),
+            focused_pid: None,
+            pinned: HashSet::new(),
+            pid_filter: None,
+            pending_watch: None,
+            hovered_pid: None,
+            memory_history: HashMap::new(),
+            memory_samples: HashMap::new(),
+            delta_window_s: DEFAULT_DELTA_WINDOW_S,
+            previous_memory: None,
+            changes: HashMap::new(),
+            all_gpus: false,
+            device_names: Vec::new(),
+            gpu_total_memory: HashMap::new(),
         }
     }
 }
 
+/// Fills the background of a table cell, behind its contents.
+fn tint_cell(ui: &egui::Ui, tint: Option<Color32>) {
+    if let Some(tint) = tint {
+        ui.painter().rect_filled(ui.max_rect(), 0.0, tint);
+    }
+}
+
+fn kind_badge_ui(ui: &mut egui::Ui, kind: &ProcessKind) {
+    egui::Frame::none()
+        .fill(kind.badge_color())
+        .rounding(4.0)
+        .inner_margin(egui::Margin::symmetric(5.0, 1.0))
+        .show(ui, |ui| {
+            ui.label(
+                RichText::new(kind.badge_letter())
+                    .strong()
+                    .color(Color32::WHITE),
+            );
+        })
+        .response
+        .on_hover_text(kind.to_string());
+}
+
+/// Looks up the parent of `pid` in `/proc`.
+#[cfg(target_os = "linux")]
+pub fn parent_process(pid: u32) -> Option<ParentProcess> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let pid = parse_parent_pid(&stat).filter(|&pid| pid != 0)?;
+    let name = std::fs::read_to_string(format!("/proc/{pid}/comm"))
+        .map(|name| name.trim().to_string())
+        .unwrap_or_else(|_| String::from("Unknown"));
+    Some(ParentProcess { pid, name })
+}
+
+/// Parent processes are only looked up on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn parent_process(_pid: u32) -> Option<ParentProcess> {
+    None
+}
+
+/// Reads the command line of `pid` from `/proc`.
+#[cfg(target_os = "linux")]
+pub fn command_line(pid: u32) -> Option<String> {
+    let cmdline = std::fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    parse_command_line(&cmdline)
+}
+
+/// Command lines are only looked up on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn command_line(_pid: u32) -> Option<String> {
+    None
+}
+
+/// Joins the NUL separated arguments of `/proc/<pid>/cmdline` with spaces.
+#[cfg(target_os = "linux")]
+fn parse_command_line(cmdline: &[u8]) -> Option<String> {
+    let args: Vec<String> = cmdline
+        .split(|&byte| byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    (!args.is_empty()).then(|| args.join(" "))
+}
+
+/// The parent pid from the contents of `/proc/<pid>/stat`.
+#[cfg(target_os = "linux")]
+fn parse_parent_pid(stat: &str) -> Option<u32> {
+    // The name comes second and may contain spaces and parentheses, so the fields are counted
+    // from the last closing parenthesis. The state comes first, then the parent pid.
+    let fields = &stat[stat.rfind(')')? + 1..];
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// GPU processes that share a launcher, in depth-first order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProcessTreeGroup {
+    /// The parent of the group's top-level processes, `None` if it isn't known.
+    launcher: Option<ParentProcess>,
+    /// Indices into the process list, paired with how deep they are nested.
+    rows: Vec<(usize, usize)>,
+}
+
+/// Arranges `processes` as a tree. A process whose parent also uses the GPU is nested under
+/// it, the others are grouped by their parent. Siblings keep their order in `processes`.
+fn process_tree(processes: &[ProcessData]) -> Vec<ProcessTreeGroup> {
+    let index_by_pid: HashMap<u32, usize> = processes
+        .iter()
+        .enumerate()
+        .map(|(index, process)| (process.process_info.pid, index))
+        .collect();
+    let parent_index = |index: usize| {
+        processes[index]
+            .parent
+            .as_ref()
+            .and_then(|parent| index_by_pid.get(&parent.pid).copied())
+            .filter(|&parent_index| parent_index != index)
+    };
+
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut groups: Vec<ProcessTreeGroup> = Vec::new();
+    for (index, process) in processes.iter().enumerate() {
+        match parent_index(index) {
+            Some(parent_index) => children.entry(parent_index).or_default().push(index),
+            None => {
+                let launcher = process.parent.clone();
+                match groups.iter_mut().find(|group| group.launcher == launcher) {
+                    Some(group) => group.rows.push((index, 0)),
+                    None => groups.push(ProcessTreeGroup {
+                        launcher,
+                        rows: vec![(index, 0)],
+                    }),
+                }
+            }
+        }
+    }
+
+    let mut visited = HashSet::new();
+    for group in &mut groups {
+        let mut rows = Vec::new();
+        let mut stack: Vec<(usize, usize)> = group.rows.iter().rev().copied().collect();
+        while let Some((index, depth)) = stack.pop() {
+            if !visited.insert(index) {
+                continue;
+            }
+            rows.push((index, depth));
+            if let Some(children) = children.get(&index) {
+                stack.extend(children.iter().rev().map(|&child| (child, depth + 1)));
+            }
+        }
+        group.rows = rows;
+    }
+    groups
+}
+
+/// Splits the indices of `processes` into the pinned ones and the rest, both in list order.
+fn partition_pinned(processes: &[ProcessData], pinned: &HashSet<u32>) -> (Vec<usize>, Vec<usize>) {
+    (0..processes.len()).partition(|&index| pinned.contains(&processes[index].process_info.pid))
+}
+
+/// How many bytes of a process's name are read by default, which fits most executable paths.
+pub const DEFAULT_PROCESS_NAME_LENGTH: usize = 256;
+
+/// Ends a process name that was cut off at the name length.
+pub const TRUNCATION_MARKER: char = '…';
+
 pub fn get_process_name(full_proccess_name: &str) -> &str {
     let parts: Vec<&str> = full_proccess_name.split_whitespace().collect();
 
     if let Some(executable_path) = parts.first() {
         let splits: Vec<&str> = executable_path.split("/").collect();
-        splits.last().unwrap()
+        let name = splits.last().unwrap();
+        // Cut off right after a slash, the last directory says more than the marker alone
+        if name.starts_with(TRUNCATION_MARKER) {
+            executable_path
+        } else {
+            name
+        }
     } else {
         ""
     }
 }
 
+/// Combines the processes of several devices into one list, tagging each with the index of
+/// its device. A process is listed once per device it runs on.
+pub fn merge_device_processes(
+    devices: impl IntoIterator<Item = (usize, Vec<ProcessData>)>,
+) -> Vec<ProcessData> {
+    let mut seen = HashSet::new();
+    let mut merged = Vec::new();
+    for (device_index, processes) in devices {
+        for mut process in processes {
+            if seen.insert((process.process_info.pid, device_index)) {
+                process.gpu = Some(device_index);
+                merged.push(process);
+            }
+        }
+    }
+    merged
+}
+
 impl ProcessTable {
+    /// Whether the processes of every device are listed, which is only offered when there are
+    /// several.
+    pub fn shows_all_gpus(&self) -> bool {
+        self.all_gpus && self.device_names.len() > 1
+    }
+
+    /// The share of its device's memory that `process` uses.
+    fn memory_percentage_of(&self, process: &ProcessData) -> Option<f64> {
+        let total_memory = process
+            .gpu
+            .and_then(|gpu| self.gpu_total_memory.get(&gpu).copied())
+            .unwrap_or(self.total_memory);
+        process.memory_percentage(total_memory)
+    }
+
+    /// Shows only the process with `pid` and selects it as soon as it uses the GPU, which
+    /// opens its memory plot.
+    pub fn watch(&mut self, pid: u32) {
+        self.pid_filter = Some(pid);
+        self.pending_watch = Some(pid);
+    }
+
+    /// Adds the current memory usage of the selected processes to their plots. Called whenever
+    /// `processes` is refreshed.
+    pub fn record_memory(&mut self, time: f64) {
+        if let Some(pid) = self.pending_watch {
+            if self
+                .processes
+                .iter()
+                .any(|process| process.process_info.pid == pid)
+            {
+                self.selection.insert(pid);
+                self.focused_pid = Some(pid);
+                self.pending_watch = None;
+            }
+        }
+        self.memory_history
+            .retain(|pid, _| self.selection.contains(pid));
+        for process in &self.processes {
+            let pid = process.process_info.pid;
+            if self.selection.contains(&pid) {
+                self.memory_history
+                    .entry(pid)
+                    .or_default()
+                    .push_back([time, (process.used_memory() / MIB) as f64]);
+            }
+        }
+
+        let keys: HashSet<ProcessKey> = self
+            .processes
+            .iter()
+            .map(|process| (process.process_info.pid, process.gpu))
+            .collect();
+        self.memory_samples.retain(|key, _| keys.contains(key));
+        let window_start = time - self.delta_window_s as f64;
+        for process in &self.processes {
+            let UsedGpuMemory::Used(used) = process.process_info.used_gpu_memory else {
+                continue;
+            };
+            let samples = self
+                .memory_samples
+                .entry((process.process_info.pid, process.gpu))
+                .or_default();
+            samples.push_back((time, used));
+            // Keeps the last sample from before the window, to measure from its start
+            while samples
+                .get(1)
+                .is_some_and(|&(sample_time, _)| sample_time <= window_start)
+            {
+                samples.pop_front();
+            }
+        }
+    }
+
+    /// How much the memory usage of `process` changed over the last `delta_window_s` in bytes,
+    /// or since it showed up if that was more recently. `None` until it has been refreshed
+    /// twice with its memory known.
+    fn memory_delta(&self, process: &ProcessData) -> Option<i64> {
+        let samples = self
+            .memory_samples
+            .get(&(process.process_info.pid, process.gpu))?;
+        let (&(first_time, first), &(last_time, last)) = (samples.front()?, samples.back()?);
+        (last_time > first_time).then(|| last as i64 - first as i64)
+    }
+
+    /// Compares `processes` to the previous refresh to highlight what changed. Called whenever
+    /// `processes` is refreshed.
+    pub fn track_changes(&mut self) {
+        let now = Instant::now();
+        if let Some(previous) = &self.previous_memory {
+            for (pid, change) in process_changes(previous, &self.processes) {
+                self.changes.insert(pid, (change, now));
+            }
+        }
+        self.changes.retain(|_, (_, changed_at)| {
+            now.duration_since(*changed_at) < CHANGE_HIGHLIGHT_DURATION
+        });
+        self.previous_memory = Some(
+            self.processes
+                .iter()
+                .map(|process| (process.process_info.pid, process.used_memory()))
+                .collect(),
+        );
+    }
+
+    /// Starts tracking changes over, e.g. when another device is selected, so that its
+    /// processes aren't all highlighted as new.
+    pub fn reset_changes(&mut self) {
+        self.previous_memory = None;
+        self.changes.clear();
+    }
+
+    /// The tint of the row of `pid`, fading out as its change gets older.
+    fn change_tint(&self, pid: u32) -> Option<Color32> {
+        let (change, changed_at) = self.changes.get(&pid)?;
+        let remaining =
+            1.0 - changed_at.elapsed().as_secs_f32() / CHANGE_HIGHLIGHT_DURATION.as_secs_f32();
+        (remaining > 0.0).then(|| change.color().gamma_multiply(remaining))
+    }
+
+    /// A window plotting the memory usage of the selected processes. Closing it clears the
+    /// selection.
+    pub fn plot_window_ui(&mut self, ctx: &egui::Context) {
+        if !self.show_plot_window {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Process memory")
+            .open(&mut open)
+            .default_size([400.0, 250.0])
+            .show(ctx, |ui| {
+                Plot::new("process memory")
+                    .legend(Legend::default())
+                    .include_y(0)
+                    .y_axis_label("MiB")
+                    .allow_scroll(false)
+                    .show(ui, |plot_ui| {
+                        for process in &self.processes {
+                            let pid = process.process_info.pid;
+                            if let Some(history) = self.memory_history.get(&pid) {
+                                let points: Vec<[f64; 2]> = history.iter().copied().collect();
+                                plot_ui.line(
+                                    Line::new(PlotPoints::from(points))
+                                        .name(format!("{} ({pid})", process.process_name)),
+                                );
+                            }
+                        }
+                    });
+            });
+        if !open {
+            self.selection.clear();
+            self.show_plot_window = false;
+        }
+    }
+
     pub fn table_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add_enabled(
+                !self.tree_view,
+                egui::Checkbox::new(&mut self.group_by_kind, "Group by type"),
+            );
+            ui.checkbox(&mut self.tree_view, "Tree view")
+                .on_hover_text("Nest the processes under the process that launched them");
+            ui.checkbox(&mut self.kind_badges, "Type badges");
+            ui.checkbox(&mut self.hide_idle, "Hide idle processes")
+                .on_hover_text(format!(
+                    "Hide processes using less than {} of GPU memory",
+                    format_bytes(IDLE_PROCESS_MEMORY, self.memory_unit)
+                ));
+            ui.label("Δ over")
+                .on_hover_text("The time span of the memory delta column");
+            ui.add(
+                egui::DragValue::new(&mut self.delta_window_s)
+                    .range(5..=3600)
+                    .suffix(" s"),
+            );
+            if self.device_names.len() > 1 {
+                ui.checkbox(&mut self.all_gpus, "All GPUs")
+                    .on_hover_text("List the processes of every GPU in one table");
+            }
+            ui.weak("Right-click a process to pin it to the top");
+        });
+        if self
+            .changes
+            .values()
+            .any(|(_, changed_at)| changed_at.elapsed() < CHANGE_HIGHLIGHT_DURATION)
+        {
+            // Keep the highlights fading out while nothing else repaints
+            ui.ctx().request_repaint();
+        }
+        // The rows below record the hovered process again for the next frame
+        let highlighted_pid = self.hovered_pid.take();
+        // The bar shows the memory of a single device
+        if !self.shows_all_gpus() {
+            memory_bar_ui(
+                ui,
+                &self.processes,
+                self.total_memory,
+                highlighted_pid,
+                self.memory_unit,
+            );
+        }
+        memory_by_kind_ui(ui, &self.processes, None, self.memory_unit);
+
+        // Each group is drawn as its own table under a collapsing header, with an id and
+        // header label per group
+        let mut depths: HashMap<usize, usize> = HashMap::new();
+        let (mut pinned, mut unpinned) = partition_pinned(&self.processes, &self.pinned);
+        let hidden: HashSet<usize> = if self.hide_idle {
+            unpinned
+                .iter()
+                .copied()
+                .filter(|&index| self.processes[index].is_idle())
+                .collect()
+        } else {
+            HashSet::new()
+        };
+        unpinned.retain(|index| !hidden.contains(index));
+        if let Some(pid) = self.pid_filter {
+            let other_process = |index: &usize| self.processes[*index].process_info.pid != pid;
+            pinned.retain(|index| !other_process(index));
+            unpinned.retain(|index| !other_process(index));
+            let mut show_all = false;
+            ui.horizontal(|ui| {
+                if pinned.is_empty() && unpinned.is_empty() {
+                    ui.spinner();
+                    ui.label(format!("Waiting for process {pid} to use the GPU..."));
+                } else {
+                    ui.label(format!("Showing only process {pid}"));
+                }
+                show_all = ui.button("Show all").clicked();
+            });
+            if show_all {
+                self.pid_filter = None;
+                self.pending_watch = None;
+            }
+        }
+        let mut groups: Option<Vec<(String, String, Vec<usize>)>> = if self.tree_view {
+            let groups = process_tree(&self.processes)
+                .into_iter()
+                .map(|group| {
+                    let (id, label) = match &group.launcher {
+                        Some(launcher) => (
+                            launcher.pid.to_string(),
+                            format!("{} ({})", launcher.name, launcher.pid),
+                        ),
+                        None => (String::from("unknown"), String::from("Unknown parent")),
+                    };
+                    depths.extend(group.rows.iter().copied());
+                    let indices = group.rows.into_iter().map(|(index, _)| index).collect();
+                    (id, label, indices)
+                })
+                .collect();
+            Some(groups)
+        } else if self.group_by_kind {
+            let mut groups: BTreeMap<ProcessKind, Vec<usize>> = BTreeMap::new();
+            for (index, process) in self.processes.iter().enumerate() {
+                groups
+                    .entry(process.process_kind.clone())
+                    .or_default()
+                    .push(index);
+            }
+            let groups = groups
+                .into_iter()
+                .map(|(kind, indices)| (kind.to_string(), kind.to_string(), indices))
+                .collect();
+            Some(groups)
+        } else {
+            None
+        };
+        // Pinned processes only show up in the pinned table, hidden ones in neither
+        if let Some(groups) = &mut groups {
+            for (_, _, indices) in groups.iter_mut() {
+                indices.retain(|index| unpinned.contains(index));
+            }
+            groups.retain(|(_, _, indices)| !indices.is_empty());
+        }
+
+        if !hidden.is_empty() {
+            ui.weak(format!("{} idle processes hidden", hidden.len()));
+        }
+
+        // The order rows appear in on screen, which is what the arrow keys move through
+        let unpinned_order: Vec<usize> = match &groups {
+            Some(groups) => groups
+                .iter()
+                .flat_map(|(_, _, indices)| indices)
+                .copied()
+                .collect(),
+            None => unpinned,
+        };
+        let display_order: Vec<usize> = pinned.iter().chain(&unpinned_order).copied().collect();
+        let focus_moved = self.handle_keyboard_navigation(ui, &display_order);
+
+        let mut focused_rect = None;
+        if !pinned.is_empty() {
+            ui.label(RichText::new(format!("📌 Pinned ({})", pinned.len())).strong());
+            if let Some(rect) = ui
+                .push_id("pinned processes", |ui| {
+                    self.process_rows_ui(ui, &pinned, &HashMap::new(), focus_moved)
+                })
+                .inner
+            {
+                focused_rect = Some(rect);
+            }
+            ui.separator();
+        }
+        if let Some(groups) = groups {
+            for (id, label, indices) in groups {
+                egui::CollapsingHeader::new(format!("{} ({})", label, indices.len()))
+                    .id_source(&id)
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        ui.push_id(&id, |ui| {
+                            if let Some(rect) =
+                                self.process_rows_ui(ui, &indices, &depths, focus_moved)
+                            {
+                                focused_rect = Some(rect);
+                            }
+                        });
+                    });
+            }
+        } else {
+            // A fixed id keeps the column widths, which egui persists, the same between runs
+            if let Some(rect) = ui
+                .push_id("processes", |ui| {
+                    self.process_rows_ui(ui, &unpinned_order, &depths, focus_moved)
+                })
+                .inner
+            {
+                focused_rect = Some(rect);
+            }
+        }
+
+        if let Some(rect) = focused_rect {
+            let stroke = egui::Stroke::new(1.5, ui.visuals().strong_text_color());
+            ui.painter().rect_stroke(rect.shrink(1.0), 2.0, stroke);
+        }
+
+        // Forget processes that have exited, their pid may be reused by an unrelated process
+        let pids: HashSet<u32> = self
+            .processes
+            .iter()
+            .map(|process| process.process_info.pid)
+            .collect();
+        self.selection.retain(|pid| pids.contains(pid));
+        self.pinned.retain(|pid| pids.contains(pid));
+        self.show_plot_window = !self.selection.is_empty();
+    }
+
+    /// Moves the focused row with the arrow keys and toggles its selection with Enter or Space.
+    /// Returns `true` if the focus moved.
+    fn handle_keyboard_navigation(&mut self, ui: &egui::Ui, display_order: &[usize]) -> bool {
+        // Don't steal keys from text fields or other focused widgets
+        if display_order.is_empty() || ui.memory(|memory| memory.focused().is_some()) {
+            return false;
+        }
+
+        let (down, up, toggle) = ui.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter)
+                    || i.consume_key(egui::Modifiers::NONE, egui::Key::Space),
+            )
+        });
+
+        let pid_at = |position: usize| self.processes[display_order[position]].process_info.pid;
+        let position = self.focused_pid.and_then(|focused| {
+            (0..display_order.len()).find(|&position| pid_at(position) == focused)
+        });
+        let new_position = match position {
+            Some(position) if down => Some((position + 1).min(display_order.len() - 1)),
+            Some(position) if up => Some(position.saturating_sub(1)),
+            None if down || up => Some(0),
+            position => position,
+        };
+        let new_focus = new_position.map(pid_at);
+
+        let focus_moved = new_focus != self.focused_pid;
+        self.focused_pid = new_focus;
+
+        if toggle {
+            if let Some(focused) = self.focused_pid {
+                self.toggle_selection(focused);
+            }
+        }
+
+        focus_moved
+    }
+
+    /// Draws a table containing the processes at `indices`.
+    ///
+    /// Returns the rect of the focused row if it's part of this table.
+    fn process_rows_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        indices: &[usize],
+        depths: &HashMap<usize, usize>,
+        scroll_to_focus: bool,
+    ) -> Option<egui::Rect> {
+        let all_gpus = self.shows_all_gpus();
         let mut table = TableBuilder::new(ui)
             .striped(self.striped)
             .resizable(self.resizable)
             .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
             .column(Column::auto())
+            .columns(Column::auto(), usize::from(all_gpus))
             .column(Column::auto())
             .column(Column::remainder())
+            .column(Column::remainder())
+            .column(Column::remainder())
             .column(Column::remainder());
 
         if self.clickable {
             table = table.sense(egui::Sense::click());
         }
 
-        let mut rows_to_toggle: Vec<(usize, egui::Response)> = Vec::new();
+        let mut rows_to_toggle: Vec<(u32, egui::Response)> = Vec::new();
+        let mut pins_to_toggle: Vec<u32> = Vec::new();
+        let mut focused_rect = None;
+        let mut hovered_pid = None;
 
         table
             .header(20.0, |mut header| {
                 self.create_sortable_header(&mut header, "PID", SortKind::Pid);
+                if all_gpus {
+                    self.create_sortable_header(&mut header, "GPU", SortKind::Gpu);
+                }
                 self.create_sortable_header(&mut header, "Type", SortKind::Type);
                 self.create_sortable_header(&mut header, "Process name", SortKind::ProcessName);
                 self.create_sortable_header(&mut header, "GPU Memory Usage", SortKind::Memory);
+                self.create_sortable_header(&mut header, "% of VRAM", SortKind::MemoryPercentage);
+                let delta_label = format!("Δ {} s", self.delta_window_s);
+                self.create_sortable_header(&mut header, &delta_label, SortKind::MemoryDelta);
             })
             .body(|mut body| {
-                for process in &self.processes {
+                for &process_index in indices {
+                    let process = &self.processes[process_index];
                     let row_height = 30.0;
                     body.row(row_height, |mut row| {
-                        let row_index = row.index();
-                        row.set_selected(self.selection.contains(&row_index));
+                        let pid = process.process_info.pid;
+                        let tint = self.change_tint(pid);
+                        row.set_selected(self.selection.contains(&pid));
                         row.col(|ui| {
+                            tint_cell(ui, tint);
                             ui.label(process.process_info.pid.to_string());
                         });
+                        if all_gpus {
+                            row.col(|ui| {
+                                tint_cell(ui, tint);
+                                if let Some(gpu) = process.gpu {
+                                    let response = ui.label(gpu.to_string());
+                                    if let Some(name) = self.device_names.get(gpu) {
+                                        response.on_hover_text(name);
+                                    }
+                                }
+                            });
+                        }
                         row.col(|ui| {
-                            ui.label(process.process_kind.to_string());
+                            tint_cell(ui, tint);
+                            if self.kind_badges {
+                                for kind in process.process_kind.badge_kinds() {
+                                    kind_badge_ui(ui, kind);
+                                }
+                            } else {
+                                ui.label(process.process_kind.to_string());
+                            }
                         });
                         row.col(|ui| {
-                            ui.label(&process.process_name);
+                            tint_cell(ui, tint);
+                            let response = match depths.get(&process_index) {
+                                Some(&depth) if depth > 0 => {
+                                    ui.add_space(12.0 * (depth - 1) as f32);
+                                    ui.label(format!("└ {}", process.process_name))
+                                }
+                                _ => ui.label(&process.process_name),
+                            };
+                            if let Some(command_line) = &process.command_line {
+                                response.on_hover_text(command_line);
+                            }
                         });
                         row.col(|ui| {
+                            tint_cell(ui, tint);
                             let mem_str = match process.process_info.used_gpu_memory {
-                                UsedGpuMemory::Used(val) => format!("{} MiB", (val / 1_000_000)),
+                                UsedGpuMemory::Used(val) => format_bytes(val, self.memory_unit),
                                 UsedGpuMemory::Unavailable => String::from("Unavailable"),
                             };
                             ui.label(mem_str);
                         });
+                        row.col(|ui| {
+                            tint_cell(ui, tint);
+                            match self.memory_percentage_of(process) {
+                                Some(percentage) => ui.label(format!("{percentage:.1}%")),
+                                None => ui.label("-"),
+                            };
+                        });
+                        row.col(|ui| {
+                            tint_cell(ui, tint);
+                            match self.memory_delta(process) {
+                                Some(delta) if delta > 0 => ui.colored_label(
+                                    ui.visuals().warn_fg_color,
+                                    format_memory_delta(delta, self.memory_unit),
+                                ),
+                                Some(delta) => {
+                                    ui.label(format_memory_delta(delta, self.memory_unit))
+                                }
+                                None => ui.label("-"),
+                            };
+                        });
                         let response = row.response();
+                        if self.focused_pid == Some(pid) {
+                            focused_rect = Some(response.rect);
+                            if scroll_to_focus {
+                                response.scroll_to_me(None);
+                            }
+                        }
+                        response.context_menu(|ui| {
+                            let label = if self.pinned.contains(&pid) {
+                                "Unpin"
+                            } else {
+                                "📌 Pin to top"
+                            };
+                            if ui.button(label).clicked() {
+                                pins_to_toggle.push(pid);
+                                ui.close_menu();
+                            }
+                        });
+                        if response.hovered() {
+                            hovered_pid = Some(pid);
+                        }
                         if response.clicked() {
-                            rows_to_toggle.push((row_index, response));
+                            rows_to_toggle.push((pid, response));
                         }
                     });
                 }
             });
 
+        if hovered_pid.is_some() {
+            self.hovered_pid = hovered_pid;
+        }
         // Toggle row selection after the table has been drawn
-        for (row_index, response) in rows_to_toggle {
-            self.toggle_row_selection(row_index, &response);
+        for (pid, response) in rows_to_toggle {
+            self.toggle_row_selection(pid, &response);
+        }
+        for pid in pins_to_toggle {
+            if !self.pinned.remove(&pid) {
+                self.pinned.insert(pid);
+            }
         }
 
-        self.show_plot_window = !self.selection.is_empty();
+        focused_rect
     }
 
-    fn toggle_row_selection(&mut self, row_index: usize, row_response: &egui::Response) {
+    fn toggle_row_selection(&mut self, pid: u32, row_response: &egui::Response) {
         if row_response.clicked() {
-            if self.selection.contains(&row_index) {
-                self.selection.remove(&row_index);
-            } else {
-                self.selection.insert(row_index);
-            }
+            self.focused_pid = Some(pid);
+            self.toggle_selection(pid);
+        }
+    }
+
+    fn toggle_selection(&mut self, pid: u32) {
+        if !self.selection.remove(&pid) {
+            self.selection.insert(pid);
         }
     }
 
@@ -159,7 +1102,7 @@ impl ProcessTable {
         sort_kind: SortKind,
     ) {
         header.col(|ui| {
-            let rich_text = RichText::new(label).color(Color32::WHITE);
+            let rich_text = RichText::new(label).color(ui.visuals().strong_text_color());
             let label = Label::new(rich_text);
             let response = ui.add(label.sense(egui::Sense::hover()));
 
@@ -188,22 +1131,20 @@ impl ProcessTable {
 
     pub fn sort_processes(&mut self) {
         if let Some(sort_kind) = self.sort_kind {
-            self.processes.sort_by(|a, b| {
+            let mut processes = std::mem::take(&mut self.processes);
+            processes.sort_by(|a, b| {
                 let cmp = match sort_kind {
-                    SortKind::Pid => a.process_info.pid.cmp(&b.process_info.pid),
-                    SortKind::Type => a.process_kind.cmp(&b.process_kind),
-                    SortKind::ProcessName => a.process_name.cmp(&b.process_name),
-                    SortKind::Memory => {
-                        let memory_a = match a.process_info.used_gpu_memory {
-                            UsedGpuMemory::Used(val) => val,
-                            UsedGpuMemory::Unavailable => 0,
+                    SortKind::MemoryPercentage if self.shows_all_gpus() => {
+                        // Every device has its own total
+                        let percentage = |process| {
+                            self.memory_percentage_of(process)
+                                .unwrap_or(f64::NEG_INFINITY)
                         };
-                        let memory_b = match b.process_info.used_gpu_memory {
-                            UsedGpuMemory::Used(val) => val,
-                            UsedGpuMemory::Unavailable => 0,
-                        };
-                        memory_a.cmp(&memory_b)
+                        percentage(a).total_cmp(&percentage(b))
                     }
+                    // Processes without a delta yet go last when sorting by the largest growth
+                    SortKind::MemoryDelta => self.memory_delta(a).cmp(&self.memory_delta(b)),
+                    _ => compare_processes(sort_kind, a, b),
                 };
                 if self.sort_descending {
                     cmp.reverse()
@@ -211,10 +1152,450 @@ impl ProcessTable {
                     cmp
                 }
             });
+            self.processes = processes;
+        }
+    }
+}
+
+fn compare_processes(sort_kind: SortKind, a: &ProcessData, b: &ProcessData) -> Ordering {
+    match sort_kind {
+        SortKind::Pid => a.process_info.pid.cmp(&b.process_info.pid),
+        SortKind::Type => a.process_kind.cmp(&b.process_kind),
+        SortKind::ProcessName => a.process_name.cmp(&b.process_name),
+        // On a single device the percentage is relative to the same total for every
+        // process, so it orders the same way as the absolute usage
+        SortKind::Memory | SortKind::MemoryPercentage => a.used_memory().cmp(&b.used_memory()),
+        SortKind::Gpu => a
+            .gpu
+            .cmp(&b.gpu)
+            .then_with(|| a.process_info.pid.cmp(&b.process_info.pid)),
+        // Needs the memory history, see `ProcessTable::sort_processes`
+        SortKind::MemoryDelta => Ordering::Equal,
+    }
+}
+
+/// A signed memory change, e.g. "+512 MiB" or "-1.5 GiB".
+fn format_memory_delta(delta: i64, memory_unit: UnitSystem) -> String {
+    let sign = match delta.cmp(&0) {
+        Ordering::Greater => "+",
+        Ordering::Less => "-",
+        Ordering::Equal => "",
+    };
+    format!("{sign}{}", format_bytes(delta.unsigned_abs(), memory_unit))
+}
+
+/// The `count` processes using the most GPU memory, largest first.
+pub fn top_by_memory(processes: &[ProcessData], count: usize) -> Vec<&ProcessData> {
+    let mut processes: Vec<&ProcessData> = processes.iter().collect();
+    processes.sort_by(|a, b| compare_processes(SortKind::Memory, b, a));
+    processes.truncate(count);
+    processes
+}
+
+/// A compact list of the processes using the most GPU memory, below their memory split by
+/// kind out of the device's `total_memory`.
+pub fn top_processes_ui(
+    ui: &mut egui::Ui,
+    processes: &[ProcessData],
+    total_memory: u64,
+    memory_unit: UnitSystem,
+) {
+    let top_processes = top_by_memory(processes, TOP_PROCESS_COUNT);
+    if top_processes.is_empty() {
+        return;
+    }
+
+    memory_by_kind_ui(ui, processes, Some(total_memory), memory_unit);
+    ui.label("Top memory users:");
+    egui::Grid::new("top processes")
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            for process in top_processes {
+                ui.label(process.process_info.pid.to_string());
+                ui.label(&process.process_name);
+                ui.label(format_bytes(process.used_memory(), memory_unit));
+                ui.end_row();
+            }
+        });
+}
+
+/// GPU memory used by the processes of each kind in bytes, leaving out the kinds without
+/// processes.
+fn memory_by_kind(processes: &[ProcessData]) -> Vec<(ProcessKind, u64)> {
+    let mut totals: BTreeMap<ProcessKind, u64> = BTreeMap::new();
+    for process in processes {
+        *totals.entry(process.process_kind.clone()).or_default() += process.used_memory();
+    }
+    totals.into_iter().collect()
+}
+
+/// The memory sub-total of each kind of process, followed by a bar stacking them if the
+/// device's `total_memory` is given.
+fn memory_by_kind_ui(
+    ui: &mut egui::Ui,
+    processes: &[ProcessData],
+    total_memory: Option<u64>,
+    memory_unit: UnitSystem,
+) {
+    let totals = memory_by_kind(processes);
+    if totals.is_empty() {
+        return;
+    }
+    ui.horizontal_wrapped(|ui| {
+        for (kind, used) in &totals {
+            ui.label(RichText::new("■").color(kind.total_color()));
+            ui.label(format!("{kind}: {}", format_bytes(*used, memory_unit)));
+            ui.add_space(6.0);
+        }
+    });
+    let Some(total_memory) = total_memory.filter(|&total| total > 0) else {
+        return;
+    };
+    let size = egui::vec2(ui.available_width(), MEMORY_BAR_HEIGHT / 2.0);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let mut start = rect.left();
+    for (kind, used) in &totals {
+        let end = (start + *used as f32 / total_memory as f32 * rect.width()).min(rect.right());
+        painter.rect_filled(
+            egui::Rect::from_x_y_ranges(start..=end, rect.y_range()),
+            0.0,
+            kind.total_color(),
+        );
+        start = end;
+    }
+    let used: u64 = totals.iter().map(|(_, used)| used).sum();
+    response.on_hover_text(format!(
+        "Processes use {} of {}",
+        format_bytes(used, memory_unit),
+        format_bytes(total_memory, memory_unit)
+    ));
+}
+
+/// The processes running compute work, using the most memory first.
+fn compute_processes(processes: &[ProcessData]) -> Vec<&ProcessData> {
+    let mut processes: Vec<&ProcessData> = processes
+        .iter()
+        .filter(|process| process.process_kind != ProcessKind::Graphics)
+        .collect();
+    processes.sort_by(|a, b| compare_processes(SortKind::Memory, b, a));
+    processes
+}
+
+/// A table of just the compute processes, for the server profile.
+pub fn compute_processes_ui(ui: &mut egui::Ui, processes: &[ProcessData], memory_unit: UnitSystem) {
+    let compute_processes = compute_processes(processes);
+    ui.label(format!("Compute processes: {}", compute_processes.len()));
+    if compute_processes.is_empty() {
+        return;
+    }
+    egui::Grid::new("compute processes")
+        .num_columns(4)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("PID");
+            ui.strong("Name");
+            ui.strong("Memory");
+            ui.strong("Command line");
+            ui.end_row();
+            for process in compute_processes {
+                ui.label(process.process_info.pid.to_string());
+                ui.label(&process.process_name);
+                ui.label(format_bytes(process.used_memory(), memory_unit));
+                ui.label(process.command_line.as_deref().unwrap_or(""));
+                ui.end_row();
+            }
+        });
+}
+
+/// Where each process with known memory usage sits in a bar of the device's `total_memory`,
+/// as its pid and its start and end as fractions of the bar.
+fn memory_segments(processes: &[ProcessData], total_memory: u64) -> Vec<(u32, f32, f32)> {
+    if total_memory == 0 {
+        return Vec::new();
+    }
+    let mut start = 0.0;
+    processes
+        .iter()
+        .filter(|process| process.used_memory() > 0)
+        .map(|process| {
+            let width = process.used_memory() as f32 / total_memory as f32;
+            let end = (start + width).min(1.0);
+            let segment = (process.process_info.pid, start, end);
+            start = end;
+            segment
+        })
+        .collect()
+}
+
+/// A bar of the device memory split into the share of each process. The process with
+/// `highlighted_pid` stands out from the others.
+fn memory_bar_ui(
+    ui: &mut egui::Ui,
+    processes: &[ProcessData],
+    total_memory: u64,
+    highlighted_pid: Option<u32>,
+    memory_unit: UnitSystem,
+) {
+    let segments = memory_segments(processes, total_memory);
+    if segments.is_empty() {
+        return;
+    }
+    let size = egui::vec2(ui.available_width(), MEMORY_BAR_HEIGHT);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let x_at = |fraction: f32| rect.left() + fraction * rect.width();
+    let pointer_x = response.hover_pos().map(|pos| pos.x);
+    let mut hovered = None;
+    for ((pid, start, end), process) in segments
+        .iter()
+        .zip(processes.iter().filter(|process| process.used_memory() > 0))
+    {
+        let segment = egui::Rect::from_x_y_ranges(x_at(*start)..=x_at(*end), rect.y_range());
+        let color = process.process_kind.total_color();
+        let color = match highlighted_pid {
+            Some(highlighted) if highlighted != *pid => color.gamma_multiply(0.3),
+            _ => color,
+        };
+        painter.rect_filled(segment, 0.0, color);
+        painter.vline(
+            segment.right(),
+            rect.y_range(),
+            egui::Stroke::new(1.0, ui.visuals().panel_fill),
+        );
+        if highlighted_pid == Some(*pid) {
+            painter.rect_stroke(
+                segment,
+                0.0,
+                egui::Stroke::new(2.0, ui.visuals().strong_text_color()),
+            );
+        }
+        if pointer_x.is_some_and(|x| segment.x_range().contains(x)) {
+            hovered = Some(process);
+        }
+    }
+    if let Some(process) = hovered {
+        response.on_hover_text_at_pointer(format!(
+            "{} ({}): {}",
+            process.process_name,
+            process.process_info.pid,
+            format_bytes(process.used_memory(), memory_unit)
+        ));
+    }
+}
+
+/// The processes using more than `threshold` bytes of GPU memory.
+pub fn processes_over(processes: &[ProcessData], threshold: u64) -> Vec<&ProcessData> {
+    processes
+        .iter()
+        .filter(|process| process.used_memory() > threshold)
+        .collect()
+}
+
+/// Asks the process to terminate, with `kill` on Unix and `taskkill` on Windows.
+pub fn kill_process(pid: u32) -> std::io::Result<()> {
+    read_only::check()
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::PermissionDenied, err))?;
+    #[cfg(unix)]
+    let output = Command::new("kill").arg(pid.to_string()).output()?;
+    #[cfg(windows)]
+    let output = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/F"])
+        .output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(std::io::Error::other(stderr.trim().to_string()))
+    }
+}
+
+/// A process the user is about to kill, as it was when the list was made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PendingKill {
+    pid: u32,
+    name: String,
+    used_memory: u64,
+    command_line: Option<String>,
+}
+
+impl PendingKill {
+    /// Whether `pid` still belongs to the same process in `processes`. The pid may have been
+    /// reused by an unrelated process since the list was made.
+    fn is_still_running(&self, processes: &[ProcessData]) -> bool {
+        processes.iter().any(|process| {
+            process.process_info.pid == self.pid
+                && process.process_name == self.name
+                && process.command_line == self.command_line
+        })
+    }
+}
+
+/// Kills every process above a memory threshold at once, after the user confirmed the list.
+#[derive(Debug, Clone)]
+pub struct BulkKill {
+    /// Processes using more than this many MiB are killed.
+    threshold_mib: u64,
+    /// The processes awaiting confirmation.
+    pending: Option<Vec<PendingKill>>,
+    /// When the kill was confirmed. It fires once `KILL_COUNTDOWN` has passed, unless it is
+    /// cancelled first.
+    armed_at: Option<Instant>,
+}
+
+impl Default for BulkKill {
+    fn default() -> Self {
+        Self {
+            threshold_mib: 1024,
+            pending: None,
+            armed_at: None,
         }
     }
 }
 
+impl BulkKill {
+    /// Returns a message for every process that was killed or couldn't be, empty until the
+    /// user confirms and the countdown runs out. `total_memory` is the memory of the GPU in
+    /// bytes, which bounds the threshold unless it is unknown.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        processes: &[ProcessData],
+        total_memory: u64,
+        memory_unit: UnitSystem,
+    ) -> Vec<String> {
+        // A zero bound would turn the threshold into "kill everything"
+        let max_mib = match total_memory / MIB {
+            0 => u64::MAX / MIB,
+            total_mib => total_mib,
+        };
+        ui.horizontal(|ui| {
+            ui.label("Kill all processes over");
+            ui.add(
+                egui::DragValue::new(&mut self.threshold_mib)
+                    .range(0..=max_mib)
+                    .suffix(" MiB"),
+            );
+            if ui.button("Kill...").clicked() {
+                let pending = processes_over(processes, self.threshold_mib.saturating_mul(MIB))
+                    .into_iter()
+                    .map(|process| PendingKill {
+                        pid: process.process_info.pid,
+                        name: process.process_name.clone(),
+                        used_memory: process.used_memory(),
+                        command_line: process.command_line.clone(),
+                    })
+                    .collect();
+                self.pending = Some(pending);
+                self.armed_at = None;
+            }
+        });
+
+        let mut messages = Vec::new();
+        let Some(pending) = &self.pending else {
+            return messages;
+        };
+        let mut open = true;
+        let mut armed = false;
+        let mut cancelled = false;
+        egui::Window::new("Kill processes")
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut open)
+            .show(ui.ctx(), |ui| {
+                if pending.is_empty() {
+                    ui.label(format!(
+                        "No process uses more than {} MiB.",
+                        self.threshold_mib
+                    ));
+                    return;
+                }
+
+                ui.label("The following processes will be killed:");
+                egui::Grid::new("bulk kill")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for process in pending {
+                            ui.label(process.pid.to_string());
+                            ui.label(RichText::new(&process.name).strong());
+                            ui.label(format_bytes(process.used_memory, memory_unit));
+                            ui.end_row();
+                            // The full command line tells apart processes with the same name
+                            ui.label("");
+                            ui.label(
+                                RichText::new(
+                                    process.command_line.as_deref().unwrap_or("(unknown)"),
+                                )
+                                .monospace()
+                                .weak(),
+                            );
+                            ui.end_row();
+                        }
+                    });
+                ui.horizontal(|ui| match self.armed_at {
+                    Some(armed_at) => {
+                        let remaining = KILL_COUNTDOWN.saturating_sub(armed_at.elapsed());
+                        ui.spinner();
+                        ui.label(
+                            RichText::new(format!(
+                                "Killing in {:.1} s...",
+                                remaining.as_secs_f32()
+                            ))
+                            .color(ui.visuals().error_fg_color),
+                        );
+                        cancelled = ui.button("Cancel").clicked();
+                        ui.ctx().request_repaint();
+                    }
+                    None => {
+                        let kill = egui::Button::new(
+                            RichText::new(format!("Kill {} processes", pending.len()))
+                                .color(ui.visuals().error_fg_color),
+                        );
+                        armed = ui
+                            .add(kill)
+                            .on_hover_text(format!(
+                                "Starts a {} s countdown that can still be cancelled",
+                                KILL_COUNTDOWN.as_secs()
+                            ))
+                            .clicked();
+                        cancelled = ui.button("Cancel").clicked();
+                    }
+                });
+            });
+
+        let fired = self
+            .armed_at
+            .is_some_and(|armed_at| armed_at.elapsed() >= KILL_COUNTDOWN);
+        if fired && !cancelled {
+            for process in pending {
+                let PendingKill { pid, name, .. } = process;
+                if !process.is_still_running(processes) {
+                    messages.push(format!("Skipped {name} ({pid}), it has exited"));
+                    continue;
+                }
+                match kill_process(*pid) {
+                    Ok(()) => messages.push(format!("Killed {name} ({pid})")),
+                    Err(err) => messages.push(format!("Failed to kill {name} ({pid}): {err}")),
+                }
+            }
+        }
+        if armed {
+            self.armed_at = Some(Instant::now());
+        }
+        if !open || fired || cancelled {
+            self.pending = None;
+            self.armed_at = None;
+        }
+        messages
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,5 +1613,330 @@ mod tests {
             let process_name = get_process_name(full_process_name);
             assert_eq!(process_name, "Discord");
         }
+        {
+            assert_eq!(get_process_name("/opt/app/bin/long-na…"), "long-na…");
+            assert_eq!(get_process_name("/opt/app/bin/app --flag=ve…"), "app");
+            assert_eq!(get_process_name("/opt/app/…"), "/opt/app/…");
+        }
+    }
+
+    fn child_of(pid: u32, parent_pid: u32) -> ProcessData {
+        let mut process = ProcessData::mock(pid, UsedGpuMemory::Used(0));
+        process.parent = Some(ParentProcess {
+            pid: parent_pid,
+            name: String::from("launcher"),
+        });
+        process
+    }
+
+    #[test]
+    fn test_process_tree() {
+        // Two workers of one launcher, one of which started a child of its own, and an
+        // unrelated process
+        let processes = vec![
+            child_of(11, 10),
+            child_of(20, 2),
+            child_of(12, 10),
+            child_of(13, 11),
+        ];
+        let tree = process_tree(&processes);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].launcher.as_ref().map(|parent| parent.pid), Some(10));
+        assert_eq!(tree[0].rows, vec![(0, 0), (3, 1), (2, 0)]);
+        assert_eq!(tree[1].rows, vec![(1, 0)]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_command_line() {
+        assert_eq!(
+            parse_command_line(b"python\0train.py\0--epochs=10\0"),
+            Some(String::from("python train.py --epochs=10"))
+        );
+        assert_eq!(parse_command_line(b""), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_parent_pid() {
+        let stat = "4242 (python (worker) 1) S 4200 4242 4200 0 -1 4194560";
+        assert_eq!(parse_parent_pid(stat), Some(4200));
+        assert_eq!(parse_parent_pid("garbage"), None);
+    }
+
+    #[test]
+    fn test_from_lists_merges_duplicate_pids() {
+        let mut graphics = ProcessData::mock(1, UsedGpuMemory::Used(100));
+        graphics.process_kind = ProcessKind::Graphics;
+        let compute = vec![
+            ProcessData::mock(1, UsedGpuMemory::Used(300)),
+            ProcessData::mock(2, UsedGpuMemory::Unavailable),
+        ];
+
+        let processes = ProcessState::from_lists(vec![graphics], compute).processes;
+        assert_eq!(processes.len(), 2);
+        assert_eq!(processes[0].process_kind, ProcessKind::Both);
+        assert_eq!(processes[0].used_memory(), 300);
+        assert_eq!(processes[1].process_kind, ProcessKind::Compute);
+    }
+
+    #[test]
+    fn test_top_by_memory() {
+        let processes = vec![
+            ProcessData::mock(1, UsedGpuMemory::Used(100)),
+            ProcessData::mock(2, UsedGpuMemory::Unavailable),
+            ProcessData::mock(3, UsedGpuMemory::Used(300)),
+            ProcessData::mock(4, UsedGpuMemory::Used(200)),
+        ];
+        let pids: Vec<u32> = top_by_memory(&processes, 2)
+            .iter()
+            .map(|process| process.process_info.pid)
+            .collect();
+        assert_eq!(pids, vec![3, 4]);
+        assert_eq!(top_by_memory(&processes, 10).len(), 4);
+    }
+
+    #[test]
+    fn test_merge_device_processes() {
+        let merged = merge_device_processes([
+            (
+                0,
+                vec![
+                    ProcessData::mock(1, UsedGpuMemory::Used(100)),
+                    ProcessData::mock(1, UsedGpuMemory::Used(100)),
+                ],
+            ),
+            (1, vec![ProcessData::mock(1, UsedGpuMemory::Used(200))]),
+        ]);
+        let rows: Vec<(u32, Option<usize>)> = merged
+            .iter()
+            .map(|process| (process.process_info.pid, process.gpu))
+            .collect();
+        assert_eq!(rows, vec![(1, Some(0)), (1, Some(1))]);
+    }
+
+    #[test]
+    fn test_sort_by_gpu_and_percentage() {
+        let mut table = ProcessTable {
+            device_names: vec![String::from("A"), String::from("B")],
+            all_gpus: true,
+            gpu_total_memory: HashMap::from([(0, 1000), (1, 100)]),
+            processes: merge_device_processes([
+                (0, vec![ProcessData::mock(2, UsedGpuMemory::Used(500))]),
+                (1, vec![ProcessData::mock(1, UsedGpuMemory::Used(90))]),
+            ]),
+            ..ProcessTable::default()
+        };
+        let pids = |table: &ProcessTable| -> Vec<u32> {
+            table
+                .processes
+                .iter()
+                .map(|process| process.process_info.pid)
+                .collect()
+        };
+
+        table.sort_kind = Some(SortKind::MemoryPercentage);
+        table.sort_processes();
+        // 90% of the small device comes before 50% of the large one
+        assert_eq!(pids(&table), vec![1, 2]);
+
+        table.sort_kind = Some(SortKind::Gpu);
+        table.sort_descending = false;
+        table.sort_processes();
+        assert_eq!(pids(&table), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_pending_kill_is_still_running() {
+        let process = ProcessData::mock(42, UsedGpuMemory::Used(100));
+        let pending = PendingKill {
+            pid: 42,
+            name: String::from("python"),
+            used_memory: 100,
+            command_line: None,
+        };
+        assert!(pending.is_still_running(std::slice::from_ref(&process)));
+        assert!(!pending.is_still_running(&[]));
+
+        // The pid was reused by another process
+        let mut reused = process;
+        reused.process_name = String::from("Xorg");
+        assert!(!pending.is_still_running(&[reused]));
+    }
+
+    #[test]
+    fn test_compute_processes() {
+        let mut graphics = ProcessData::mock(2, UsedGpuMemory::Used(500));
+        graphics.process_kind = ProcessKind::Graphics;
+        let mut both = ProcessData::mock(3, UsedGpuMemory::Used(300));
+        both.process_kind = ProcessKind::Both;
+        let processes = vec![
+            ProcessData::mock(1, UsedGpuMemory::Used(100)),
+            graphics,
+            both,
+        ];
+        let pids: Vec<u32> = compute_processes(&processes)
+            .iter()
+            .map(|process| process.process_info.pid)
+            .collect();
+        assert_eq!(pids, vec![3, 1]);
+    }
+
+    #[test]
+    fn test_process_changes() {
+        let previous = HashMap::from([(1, 100 * MIB), (2, 1000 * MIB), (3, 2000 * MIB)]);
+        let processes = vec![
+            ProcessData::mock(1, UsedGpuMemory::Used(120 * MIB)),
+            ProcessData::mock(2, UsedGpuMemory::Used(1100 * MIB)),
+            ProcessData::mock(3, UsedGpuMemory::Used(1700 * MIB)),
+            ProcessData::mock(4, UsedGpuMemory::Used(0)),
+        ];
+        assert_eq!(
+            process_changes(&previous, &processes),
+            vec![
+                (2, ProcessChange::MemoryGrew),
+                (3, ProcessChange::MemoryShrank),
+                (4, ProcessChange::New),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_partition_pinned() {
+        let processes = vec![
+            ProcessData::mock(10, UsedGpuMemory::Used(100)),
+            ProcessData::mock(20, UsedGpuMemory::Used(200)),
+            ProcessData::mock(30, UsedGpuMemory::Used(300)),
+        ];
+        let pinned = HashSet::from([30, 10, 99]);
+        assert_eq!(partition_pinned(&processes, &pinned), (vec![0, 2], vec![1]));
+        assert_eq!(
+            partition_pinned(&processes, &HashSet::new()),
+            (vec![], vec![0, 1, 2])
+        );
+    }
+
+    #[test]
+    fn test_processes_over() {
+        let processes = vec![
+            ProcessData::mock(1, UsedGpuMemory::Used(100)),
+            ProcessData::mock(2, UsedGpuMemory::Unavailable),
+            ProcessData::mock(3, UsedGpuMemory::Used(300)),
+        ];
+        let pids: Vec<u32> = processes_over(&processes, 100)
+            .iter()
+            .map(|process| process.process_info.pid)
+            .collect();
+        assert_eq!(pids, vec![3]);
+    }
+
+    #[test]
+    fn test_watch_picks_up_process_when_it_appears() {
+        let mut table = ProcessTable::default();
+        table.watch(7);
+        table.processes = vec![ProcessData::mock(1, UsedGpuMemory::Used(MIB))];
+        table.record_memory(0.0);
+        assert!(table.selection.is_empty());
+
+        table
+            .processes
+            .push(ProcessData::mock(7, UsedGpuMemory::Used(512 * MIB)));
+        table.record_memory(1.0);
+        table.record_memory(2.0);
+        assert!(table.selection.contains(&7));
+        assert_eq!(table.pid_filter, Some(7));
+        let history: Vec<[f64; 2]> = table.memory_history[&7].iter().copied().collect();
+        assert_eq!(history, vec![[1.0, 512.0], [2.0, 512.0]]);
+    }
+
+    #[test]
+    fn test_memory_delta_over_window() {
+        let mut table = ProcessTable {
+            delta_window_s: 10,
+            ..ProcessTable::default()
+        };
+        let refresh = |table: &mut ProcessTable, time: f64, leaking_mib: u64| {
+            table.processes = vec![
+                ProcessData::mock(1, UsedGpuMemory::Used(leaking_mib * MIB)),
+                ProcessData::mock(2, UsedGpuMemory::Used(100 * MIB)),
+                ProcessData::mock(3, UsedGpuMemory::Unavailable),
+            ];
+            table.record_memory(time);
+        };
+        refresh(&mut table, 0.0, 100);
+        assert_eq!(table.memory_delta(&table.processes[0]), None);
+        refresh(&mut table, 5.0, 150);
+        refresh(&mut table, 10.0, 200);
+        refresh(&mut table, 15.0, 300);
+        // Measured from the sample at 5 s, the start of the window
+        assert_eq!(
+            table.memory_delta(&table.processes[0]),
+            Some(150 * MIB as i64)
+        );
+        assert_eq!(table.memory_delta(&table.processes[1]), Some(0));
+        assert_eq!(table.memory_delta(&table.processes[2]), None);
+
+        table.sort_kind = Some(SortKind::MemoryDelta);
+        table.sort_processes();
+        let pids: Vec<u32> = table
+            .processes
+            .iter()
+            .map(|process| process.process_info.pid)
+            .collect();
+        assert_eq!(pids, vec![1, 2, 3]);
+
+        assert_eq!(
+            format_memory_delta(-3 * MIB as i64, UnitSystem::Mebibytes),
+            "-3 MiB"
+        );
+    }
+
+    #[test]
+    fn test_memory_segments() {
+        let processes = vec![
+            ProcessData::mock(1, UsedGpuMemory::Used(250)),
+            ProcessData::mock(2, UsedGpuMemory::Unavailable),
+            ProcessData::mock(3, UsedGpuMemory::Used(500)),
+        ];
+        assert_eq!(
+            memory_segments(&processes, 1000),
+            vec![(1, 0.0, 0.25), (3, 0.25, 0.75)]
+        );
+        assert!(memory_segments(&processes, 0).is_empty());
+    }
+
+    #[test]
+    fn test_memory_by_kind() {
+        let mut graphics = ProcessData::mock(2, UsedGpuMemory::Used(300));
+        graphics.process_kind = ProcessKind::Graphics;
+        let processes = vec![
+            ProcessData::mock(1, UsedGpuMemory::Used(250)),
+            graphics,
+            ProcessData::mock(3, UsedGpuMemory::Unavailable),
+            ProcessData::mock(4, UsedGpuMemory::Used(500)),
+        ];
+        assert_eq!(
+            memory_by_kind(&processes),
+            vec![(ProcessKind::Compute, 750), (ProcessKind::Graphics, 300)]
+        );
+        assert!(memory_by_kind(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_is_idle() {
+        assert!(ProcessData::mock(1, UsedGpuMemory::Unavailable).is_idle());
+        assert!(ProcessData::mock(1, UsedGpuMemory::Used(MIB)).is_idle());
+        assert!(!ProcessData::mock(1, UsedGpuMemory::Used(IDLE_PROCESS_MEMORY)).is_idle());
+    }
+
+    #[test]
+    fn test_memory_percentage() {
+        let process = ProcessData::mock(1, UsedGpuMemory::Used(2_000_000_000));
+        assert_eq!(process.memory_percentage(8_000_000_000), Some(25.0));
+        assert_eq!(process.memory_percentage(0), None);
+
+        let process = ProcessData::mock(2, UsedGpuMemory::Unavailable);
+        assert_eq!(process.memory_percentage(8_000_000_000), None);
+        assert_eq!(process.used_memory(), 0);
     }
 }
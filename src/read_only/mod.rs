@@ -0,0 +1,70 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Turns read-only mode on or off. While it is on, every action that changes the hardware or
+/// kills a process fails with [`ReadOnly`], whichever part of the UI asks for it.
+pub fn set_enabled(read_only: bool) {
+    READ_ONLY.store(read_only, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// Called first by every mutating action.
+pub fn check() -> Result<(), ReadOnly> {
+    if enabled() {
+        Err(ReadOnly)
+    } else {
+        Ok(())
+    }
+}
+
+/// A mutating action was refused because read-only mode is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOnly;
+
+impl Display for ReadOnly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "disabled in read-only mode")
+    }
+}
+
+impl std::error::Error for ReadOnly {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refuses_mutating_actions() {
+        // A child of our own rather than a made-up pid, so nothing else is killed if the
+        // flag is off by the time `kill_process` runs
+        #[cfg(unix)]
+        let mut child = std::process::Command::new("sleep")
+            .arg("60")
+            .spawn()
+            .unwrap();
+
+        set_enabled(true);
+        assert_eq!(check(), Err(ReadOnly));
+        #[cfg(unix)]
+        let killed = crate::process::kill_process(child.id());
+        set_enabled(false);
+        assert_eq!(check(), Ok(()));
+
+        #[cfg(unix)]
+        {
+            let still_running = child.try_wait().unwrap().is_none();
+            child.kill().unwrap();
+            child.wait().unwrap();
+            assert_eq!(
+                killed.unwrap_err().kind(),
+                std::io::ErrorKind::PermissionDenied
+            );
+            assert!(still_running);
+        }
+    }
+}
@@ -0,0 +1,558 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::device::{self, DeviceStatsPlot, Sample, SeriesStyle, DEFAULT_PLOT_HEIGHT};
+use crate::metric::{MetricValues, METRICS, METRIC_COUNT};
+use crate::units::{format_bytes, UnitSystem, MIB};
+
+const CSV_HEADER: &str = "timestamp,seconds_since_start,temperature,memory_temperature,\
+                          memory_usage_mib,power_usage_w,gpu_utilization,throttled,\
+                          memory_utilization";
+
+/// The columns of [`CSV_HEADER`] followed by one per registry metric.
+fn csv_header() -> String {
+    let mut header = String::from(CSV_HEADER);
+    for metric in &METRICS {
+        header.push(',');
+        header.push_str(metric.id);
+    }
+    header
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    Empty,
+}
+
+impl Display for RecordingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingError::Io(err) => write!(f, "{err}"),
+            RecordingError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            RecordingError::Empty => write!(f, "the recording doesn't contain any samples"),
+        }
+    }
+}
+
+impl From<io::Error> for RecordingError {
+    fn from(err: io::Error) -> Self {
+        RecordingError::Io(err)
+    }
+}
+
+/// Writes one row per sample, starting with its UTC wall-clock time and the seconds between
+/// `started_at` and the sample.
+pub fn write_csv<W: Write>(
+    mut writer: W,
+    samples: &[(f64, Sample)],
+    started_at: SystemTime,
+) -> io::Result<()> {
+    writeln!(writer, "{}", csv_header())?;
+    for (seconds, sample) in samples {
+        write!(
+            writer,
+            "{},{seconds:.3},{},{},{},{},{},{},{}",
+            humantime::format_rfc3339_millis(wall_clock_time(started_at, *seconds)),
+            csv_field(sample.temperature),
+            csv_field(sample.memory_temperature),
+            csv_field(sample.memory_usage),
+            csv_field(
+                sample
+                    .power_usage_mw
+                    .map(|mw| format!("{:.3}", device::milliwatts_to_watts(mw)))
+            ),
+            csv_field(sample.gpu_utilization),
+            u8::from(sample.throttled),
+            csv_field(sample.memory_utilization)
+        )?;
+        for value in sample.metrics {
+            match value {
+                Some(value) => write!(writer, ",{value}")?,
+                None => write!(writer, ",")?,
+            }
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// When a sample taken `seconds` after `started_at` was taken.
+fn wall_clock_time(started_at: SystemTime, seconds: f64) -> SystemTime {
+    // Restored history was taken before the session started
+    let offset = Duration::from_secs_f64(seconds.abs());
+    if seconds < 0.0 {
+        started_at - offset
+    } else {
+        started_at + offset
+    }
+}
+
+/// Readings the device didn't report are left empty.
+fn csv_field(value: Option<impl Display>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// Parses a field written by [`csv_field`].
+fn parse_field<T: FromStr>(field: &str) -> Result<Option<T>, T::Err> {
+    match field {
+        "" => Ok(None),
+        value => value.parse().map(Some),
+    }
+}
+
+/// Reads the samples of a recording with their seconds since the start of the session. The
+/// wall-clock timestamps are only there for correlating with other logs.
+pub fn read_csv<R: BufRead>(reader: R) -> Result<Vec<(f64, Sample)>, RecordingError> {
+    let mut samples = Vec::new();
+    let header = csv_header();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = index + 1;
+        let parse_error = |message: String| RecordingError::Parse {
+            line: line_number,
+            message,
+        };
+        if index == 0 {
+            if line != header {
+                return Err(parse_error(String::from("not an nvsmi-gui recording")));
+            }
+            continue;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let wrong_field_count = || {
+            parse_error(format!(
+                "expected {} fields, found {}",
+                9 + METRIC_COUNT,
+                fields.len()
+            ))
+        };
+        let [_timestamp, seconds, temperature, memory_temperature, memory_usage, power_usage, gpu_utilization, throttled, memory_utilization, ref metric_fields @ ..] =
+            fields[..]
+        else {
+            return Err(wrong_field_count());
+        };
+        if metric_fields.len() != METRIC_COUNT {
+            return Err(wrong_field_count());
+        }
+        let mut metrics: MetricValues = [None; METRIC_COUNT];
+        for ((value, field), metric) in metrics.iter_mut().zip(metric_fields).zip(&METRICS) {
+            if !field.is_empty() {
+                *value = Some(field.parse().map_err(|err| {
+                    parse_error(format!("invalid {}: {err}", metric.name.to_lowercase()))
+                })?);
+            }
+        }
+
+        let seconds = seconds
+            .parse()
+            .map_err(|err| parse_error(format!("invalid seconds since start: {err}")))?;
+        let sample = Sample {
+            temperature: parse_field(temperature)
+                .map_err(|err| parse_error(format!("invalid temperature: {err}")))?,
+            memory_temperature: parse_field(memory_temperature)
+                .map_err(|err| parse_error(format!("invalid memory temperature: {err}")))?,
+            memory_usage: parse_field(memory_usage)
+                .map_err(|err| parse_error(format!("invalid memory usage: {err}")))?,
+            power_usage_mw: parse_field::<f64>(power_usage)
+                .map(|watts| watts.map(|watts| (watts * 1000.0).round() as u32))
+                .map_err(|err| parse_error(format!("invalid power usage: {err}")))?,
+            gpu_utilization: parse_field(gpu_utilization)
+                .map_err(|err| parse_error(format!("invalid GPU utilization: {err}")))?,
+            memory_utilization: parse_field(memory_utilization)
+                .map_err(|err| parse_error(format!("invalid memory utilization: {err}")))?,
+            throttled: match throttled {
+                "0" => false,
+                "1" => true,
+                value => return Err(parse_error(format!("invalid throttled flag: {value}"))),
+            },
+            metrics,
+        };
+        samples.push((seconds, sample));
+    }
+
+    if samples.is_empty() {
+        return Err(RecordingError::Empty);
+    }
+
+    Ok(samples)
+}
+
+/// One line of a JSON lines recording, shaped like the samples of the HTTP API's
+/// `/api/history`.
+#[derive(Serialize, Deserialize)]
+struct JsonSample {
+    /// Only there for correlating with other logs, like the CSV column.
+    #[serde(default, skip_deserializing)]
+    timestamp: String,
+    time: f64,
+    #[serde(flatten)]
+    sample: Sample,
+}
+
+/// Writes one JSON object per line and sample, with the same readings as [`write_csv`].
+pub fn write_jsonl<W: Write>(
+    mut writer: W,
+    samples: &[(f64, Sample)],
+    started_at: SystemTime,
+) -> io::Result<()> {
+    for &(time, sample) in samples {
+        let line = JsonSample {
+            timestamp: humantime::format_rfc3339_millis(wall_clock_time(started_at, time))
+                .to_string(),
+            time,
+            sample,
+        };
+        serde_json::to_writer(&mut writer, &line)?;
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+/// Reads the samples of a recording written by [`write_jsonl`].
+pub fn read_jsonl<R: BufRead>(reader: R) -> Result<Vec<(f64, Sample)>, RecordingError> {
+    let mut samples = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let JsonSample { time, sample, .. } =
+            serde_json::from_str(&line).map_err(|err| RecordingError::Parse {
+                line: index + 1,
+                message: err.to_string(),
+            })?;
+        samples.push((time, sample));
+    }
+
+    if samples.is_empty() {
+        return Err(RecordingError::Empty);
+    }
+
+    Ok(samples)
+}
+
+/// Whether `path` names a JSON lines recording rather than a CSV one.
+fn is_jsonl(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("jsonl"))
+}
+
+/// Writes a JSON lines recording if `path` ends in `.jsonl`, a CSV one otherwise.
+pub fn export(
+    path: &Path,
+    samples: &[(f64, Sample)],
+    started_at: SystemTime,
+) -> Result<(), RecordingError> {
+    let writer = BufWriter::new(File::create(path)?);
+    if is_jsonl(path) {
+        write_jsonl(writer, samples, started_at)?;
+    } else {
+        write_csv(writer, samples, started_at)?;
+    }
+    Ok(())
+}
+
+/// Reads a recording written by [`export`], picking the format by the extension the same way.
+pub fn import(path: &Path) -> Result<Vec<(f64, Sample)>, RecordingError> {
+    let reader = BufReader::new(File::open(path)?);
+    if is_jsonl(path) {
+        read_jsonl(reader)
+    } else {
+        read_csv(reader)
+    }
+}
+
+/// A recorded session that is played back into the plots instead of the live data.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    name: String,
+    /// The samples with the seconds since the start of the recorded session they were taken
+    /// at, which places them on the plots' time axis.
+    samples: Vec<(f64, Sample)>,
+    position: usize,
+    playing: bool,
+    last_step: Instant,
+    /// How long playback stays on a sample before stepping to the next one.
+    step_interval: Duration,
+    memory_unit: UnitSystem,
+    plot_height: Option<f32>,
+    series_style: SeriesStyle,
+    plot: DeviceStatsPlot,
+}
+
+impl Replay {
+    pub fn new(name: String, samples: Vec<(f64, Sample)>, step_interval: Duration) -> Self {
+        let mut replay = Self {
+            name,
+            position: samples.len().saturating_sub(1),
+            samples,
+            playing: false,
+            last_step: Instant::now(),
+            step_interval,
+            memory_unit: UnitSystem::default(),
+            plot_height: Some(DEFAULT_PLOT_HEIGHT),
+            series_style: SeriesStyle::default(),
+            plot: DeviceStatsPlot::default(),
+        };
+        replay.rebuild_plot();
+        replay
+    }
+
+    fn rebuild_plot(&mut self) {
+        let max_memory_usage = self
+            .samples
+            .iter()
+            .filter_map(|(_, sample)| sample.memory_usage)
+            .max()
+            .unwrap_or(0);
+
+        self.plot = DeviceStatsPlot::default();
+        self.plot.set_max_memory_usage(max_memory_usage);
+        self.plot.set_memory_unit(self.memory_unit);
+        self.plot.set_plot_height(self.plot_height);
+        self.plot.set_series_style(self.series_style);
+        for (time, sample) in &self.samples[..=self.position] {
+            self.plot.push_sample(*time, sample);
+        }
+    }
+
+    pub fn set_memory_unit(&mut self, memory_unit: UnitSystem) {
+        self.memory_unit = memory_unit;
+        self.plot.set_memory_unit(memory_unit);
+    }
+
+    pub fn set_plot_height(&mut self, plot_height: Option<f32>) {
+        self.plot_height = plot_height;
+        self.plot.set_plot_height(plot_height);
+    }
+
+    pub fn set_series_style(&mut self, series_style: SeriesStyle) {
+        self.series_style = series_style;
+        self.plot.set_series_style(series_style);
+    }
+
+    /// Advances playback by one sample per `step_interval` while playing.
+    pub fn advance(&mut self) {
+        if !self.playing || self.last_step.elapsed() < self.step_interval {
+            return;
+        }
+        self.last_step = Instant::now();
+        if self.position + 1 < self.samples.len() {
+            self.position += 1;
+            let (time, sample) = &self.samples[self.position];
+            self.plot.push_sample(*time, sample);
+        } else {
+            self.playing = false;
+        }
+    }
+
+    /// Draws the timeline controls. Returns `true` if the user wants to go back to live data.
+    pub fn controls_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut stop_replay = false;
+        ui.horizontal(|ui| {
+            ui.label(format!("Replaying {}", self.name));
+
+            let play_label = if self.playing {
+                "⏸ Pause"
+            } else {
+                "▶ Play"
+            };
+            if ui.button(play_label).clicked() {
+                if !self.playing && self.position + 1 == self.samples.len() {
+                    // Start over when playing from the end
+                    self.position = 0;
+                    self.rebuild_plot();
+                }
+                self.playing = !self.playing;
+            }
+
+            let last_index = self.samples.len() - 1;
+            let samples = &self.samples;
+            // The scrubber shows when the sample was taken, which gaps in the recording
+            // keep from being proportional to its index
+            let response = ui.add(
+                egui::Slider::new(&mut self.position, 0..=last_index)
+                    .custom_formatter(|index, _| format!("{:.1} s", samples[index as usize].0))
+                    .text("time"),
+            );
+            if response.changed() {
+                self.rebuild_plot();
+            }
+
+            if ui.button("Back to live").clicked() {
+                stop_replay = true;
+            }
+        });
+
+        let (_, sample) = &self.samples[self.position];
+        let na = || String::from("N/A");
+        ui.label(format!(
+            "Temperature: {}, memory usage: {}, power usage: {}",
+            sample
+                .temperature
+                .map_or_else(na, |temperature| format!("{temperature}°C")),
+            sample
+                .memory_usage
+                .map_or_else(na, |memory_usage| format_bytes(
+                    memory_usage * MIB,
+                    self.memory_unit
+                )),
+            sample
+                .power_usage_mw
+                .map_or_else(na, |mw| device::format_watts(device::milliwatts_to_watts(
+                    mw
+                )))
+        ));
+
+        stop_replay
+    }
+
+    pub fn plot_ui(&mut self, ui: &mut egui::Ui) {
+        self.plot.plot_ui(ui, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_round_trip() {
+        let samples = [
+            Sample {
+                temperature: Some(45),
+                memory_temperature: Some(60),
+                memory_usage: Some(1024),
+                power_usage_mw: Some(120_400),
+                gpu_utilization: Some(80),
+                memory_utilization: Some(35),
+                throttled: true,
+                metrics: [Some(1800.0), Some(1750.5), None, None],
+            },
+            Sample {
+                temperature: Some(47),
+                memory_temperature: None,
+                memory_usage: Some(2048),
+                power_usage_mw: None,
+                gpu_utilization: Some(0),
+                memory_utilization: None,
+                throttled: false,
+                metrics: [None; METRIC_COUNT],
+            },
+        ];
+
+        let timed_samples: Vec<(f64, Sample)> = samples
+            .iter()
+            .enumerate()
+            .map(|(index, &sample)| (index as f64 * 0.5, sample))
+            .collect();
+        let started_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let mut buffer = Vec::new();
+        write_csv(&mut buffer, &timed_samples, started_at).unwrap();
+        let csv = String::from_utf8(buffer.clone()).unwrap();
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "2023-11-14T22:13:20.000Z,0.000,45,60,1024,120.400,80,1,35,1800,1750.5,,"
+        );
+        assert_eq!(
+            csv.lines().nth(2).unwrap(),
+            "2023-11-14T22:13:20.500Z,0.500,47,,2048,,0,0,,,,,"
+        );
+        let read_samples = read_csv(buffer.as_slice()).unwrap();
+        assert_eq!(read_samples, timed_samples);
+    }
+
+    #[test]
+    fn test_read_csv_errors() {
+        let header = csv_header();
+        assert!(matches!(
+            read_csv(format!("{header}\n").as_bytes()),
+            Err(RecordingError::Empty)
+        ));
+        assert!(matches!(
+            read_csv("45,,1024\n".as_bytes()),
+            Err(RecordingError::Parse { line: 1, .. })
+        ));
+        // A prefix of the header isn't the header
+        assert!(matches!(
+            read_csv("t\n".as_bytes()),
+            Err(RecordingError::Parse { line: 1, .. })
+        ));
+        assert!(matches!(
+            read_csv(format!("{header}\n45,,1024,hot,50,0\n").as_bytes()),
+            Err(RecordingError::Parse { line: 2, .. })
+        ));
+        // Rows without the timestamp columns don't lose their first readings
+        assert!(matches!(
+            read_csv(format!("{header}\n45,60,1024,120,80,1,35,1800,,,\n").as_bytes()),
+            Err(RecordingError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let samples = vec![
+            (
+                -2.5,
+                Sample {
+                    temperature: Some(45),
+                    memory_temperature: None,
+                    memory_usage: Some(1024),
+                    power_usage_mw: Some(120_400),
+                    gpu_utilization: Some(80),
+                    memory_utilization: Some(35),
+                    throttled: true,
+                    metrics: [Some(1800.0), None, None, None],
+                },
+            ),
+            (
+                4.0,
+                Sample {
+                    temperature: None,
+                    memory_temperature: Some(60),
+                    memory_usage: None,
+                    power_usage_mw: None,
+                    gpu_utilization: None,
+                    memory_utilization: None,
+                    throttled: false,
+                    metrics: [None; METRIC_COUNT],
+                },
+            ),
+        ];
+        let started_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let mut buffer = Vec::new();
+        write_jsonl(&mut buffer, &samples, started_at).unwrap();
+        let jsonl = String::from_utf8(buffer.clone()).unwrap();
+        assert!(jsonl.lines().next().unwrap().starts_with(
+            r#"{"timestamp":"2023-11-14T22:13:17.500Z","time":-2.5,"temperature":45,"#
+        ));
+        assert_eq!(read_jsonl(buffer.as_slice()).unwrap(), samples);
+
+        assert!(matches!(
+            read_jsonl("\n".as_bytes()),
+            Err(RecordingError::Empty)
+        ));
+        assert!(matches!(
+            read_jsonl(format!("{}\nnot json\n", jsonl.lines().next().unwrap()).as_bytes()),
+            Err(RecordingError::Parse { line: 2, .. })
+        ));
+        assert!(is_jsonl(Path::new("session.JSONL")));
+        assert!(!is_jsonl(Path::new("session.csv")));
+    }
+}
@@ -0,0 +1,570 @@
+use std::time::Duration;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::{self, TemperatureSensor, Thresholds};
+use crate::device::{self, DeviceState, RetentionPolicy, SeriesStyle, DEFAULT_PLOT_HEIGHT};
+use crate::metric::{self, MetricVisibility, METRICS};
+use crate::source::MetricIntervals;
+use crate::units::{NumberFormat, UnitSystem};
+
+/// The reading shown in the window title, so that it can be seen in the taskbar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TitleMetric {
+    #[default]
+    Off,
+    Temperature,
+    Utilization,
+    Power,
+}
+
+impl TitleMetric {
+    pub const ALL: [TitleMetric; 4] = [
+        TitleMetric::Off,
+        TitleMetric::Temperature,
+        TitleMetric::Utilization,
+        TitleMetric::Power,
+    ];
+
+    /// The window title, e.g. "54°C - nvsmi-gui".
+    /// `sensor` is the temperature shown for [`TitleMetric::Temperature`].
+    pub fn title(self, device_state: Option<&DeviceState>, sensor: TemperatureSensor) -> String {
+        let reading = device_state.and_then(|device_state| match self {
+            TitleMetric::Off => None,
+            TitleMetric::Temperature => {
+                Some(device::format_metric(sensor.read(device_state), "°C"))
+            }
+            TitleMetric::Utilization => Some(format!(
+                "GPU {}",
+                device::format_metric(device::gpu_utilization(device_state), "%")
+            )),
+            TitleMetric::Power => Some(device::format_power(device_state, None)),
+        });
+        match reading {
+            Some(reading) => format!("{reading} - {APP_NAME}"),
+            None => String::from(APP_NAME),
+        }
+    }
+}
+
+impl std::fmt::Display for TitleMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TitleMetric::Off => write!(f, "Nothing"),
+            TitleMetric::Temperature => write!(f, "Temperature"),
+            TitleMetric::Utilization => write!(f, "GPU utilization"),
+            TitleMetric::Power => write!(f, "Power usage"),
+        }
+    }
+}
+
+/// Which widgets the Devices tab shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Profile {
+    #[default]
+    Desktop,
+    /// A dense compute-only view for headless servers, leaving out clocks, fans and display.
+    Server,
+}
+
+impl Profile {
+    pub const ALL: [Profile; 2] = [Profile::Desktop, Profile::Server];
+
+    /// The value of `--profile`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "desktop" => Some(Profile::Desktop),
+            "server" => Some(Profile::Server),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Profile::Desktop => write!(f, "Desktop"),
+            Profile::Server => write!(f, "Server (compute only)"),
+        }
+    }
+}
+
+/// The window title without a reading.
+pub const APP_NAME: &str = "nvsmi-gui";
+
+/// User preferences, persisted between runs through eframe's storage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// How often the device is polled and a sample is added to the plots.
+    pub update_interval_ms: u64,
+    /// How often the slower changing readings are refreshed, at most once per update.
+    pub metric_intervals: MetricIntervals,
+    /// How many bytes of each process's name are read, longer names are cut off.
+    pub process_name_length: usize,
+    /// Index of the GPU that was selected when the app was last closed.
+    pub last_device: usize,
+    /// Dim the window and slow down repaints and polling while the GPU is idle and
+    /// nobody is interacting with the app.
+    pub idle_dimming: bool,
+    /// Slow down repaints and polling while another window has the focus.
+    pub slow_when_unfocused: bool,
+    /// Fade and slide in the content when switching tabs.
+    pub animate_tabs: bool,
+    /// Keep the window above all other windows.
+    pub always_on_top: bool,
+    /// Use black and white visuals with thick borders and a high-contrast plot palette.
+    pub high_contrast: bool,
+    /// Refuse every action that changes the hardware or kills processes.
+    pub read_only: bool,
+    pub title_metric: TitleMetric,
+    pub profile: Profile,
+    /// Show the energy used since the app started next to the power usage.
+    pub show_energy: bool,
+    /// Time constant in seconds of the average shown as the power usage, zero to show the
+    /// latest reading. The plots always show the raw readings.
+    pub power_smoothing_s: f32,
+    pub thresholds: Thresholds,
+    pub memory_unit: UnitSystem,
+    pub number_format: NumberFormat,
+    /// Scale the memory plot to the installed memory rather than to the plotted values.
+    pub pin_memory_plot: bool,
+    /// Height of a row of plots in points.
+    pub plot_height: f32,
+    /// Share the window's height between the plots instead of using `plot_height`.
+    pub fit_plots_to_window: bool,
+    /// Width of the plotted lines in points.
+    pub plot_line_width: f32,
+    /// Fill the area under the plotted lines, which is easier to make out from a distance.
+    pub fill_plots: bool,
+    /// Keep the whole session in the plots by downsampling old samples instead of dropping
+    /// them.
+    pub keep_full_history: bool,
+    /// Save the plots on exit and restore them on the next start.
+    pub restore_history: bool,
+    /// Ids of the registry metrics that are hidden from the readouts and plots.
+    pub hidden_metrics: Vec<String>,
+    /// Opacity of the overlay's background, from 0 to 1.
+    pub overlay_opacity: f32,
+    /// Where the HTTP API listens, read on startup.
+    #[cfg(feature = "http-api")]
+    pub http_api_address: String,
+    /// Where the metric stream's socket or named pipe is created, read on startup.
+    #[cfg(feature = "local-stream")]
+    pub stream_path: String,
+    /// Set by `--read-only`, which can't be turned off from the settings.
+    #[serde(skip)]
+    pub read_only_forced: bool,
+    #[serde(skip)]
+    pub show_settings_window: bool,
+    /// Result of the last save or load of the thresholds file.
+    #[serde(skip)]
+    config_status: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            update_interval_ms: 20,
+            metric_intervals: MetricIntervals::default(),
+            process_name_length: crate::process::DEFAULT_PROCESS_NAME_LENGTH,
+            last_device: 0,
+            idle_dimming: false,
+            slow_when_unfocused: false,
+            animate_tabs: true,
+            always_on_top: false,
+            high_contrast: false,
+            read_only: false,
+            title_metric: TitleMetric::default(),
+            profile: Profile::default(),
+            show_energy: true,
+            power_smoothing_s: 0.0,
+            thresholds: Thresholds::default(),
+            memory_unit: UnitSystem::default(),
+            number_format: NumberFormat::default(),
+            pin_memory_plot: true,
+            plot_height: DEFAULT_PLOT_HEIGHT,
+            fit_plots_to_window: false,
+            plot_line_width: 1.0,
+            fill_plots: false,
+            keep_full_history: false,
+            restore_history: false,
+            hidden_metrics: Vec::new(),
+            overlay_opacity: 0.75,
+            #[cfg(feature = "http-api")]
+            http_api_address: String::from(crate::http_api::DEFAULT_ADDRESS),
+            #[cfg(feature = "local-stream")]
+            stream_path: crate::stream::default_path()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+            read_only_forced: false,
+            show_settings_window: false,
+            config_status: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn update_interval(&self) -> Duration {
+        Duration::from_millis(self.update_interval_ms)
+    }
+
+    pub fn power_smoothing(&self) -> Duration {
+        Duration::from_secs_f32(self.power_smoothing_s.max(0.0))
+    }
+
+    /// The plot height to apply, `None` when the plots fill the window.
+    pub fn plot_height(&self) -> Option<f32> {
+        (!self.fit_plots_to_window).then_some(self.plot_height)
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only || self.read_only_forced
+    }
+
+    pub fn shown_metrics(&self) -> MetricVisibility {
+        metric::visibility(&self.hidden_metrics)
+    }
+
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        if self.keep_full_history {
+            RetentionPolicy::Downsample
+        } else {
+            RetentionPolicy::Ring
+        }
+    }
+
+    pub fn series_style(&self) -> SeriesStyle {
+        SeriesStyle {
+            width: self.plot_line_width,
+            fill: self.fill_plots,
+        }
+    }
+
+    /// Replaces the thresholds with the ones in the config file, if there is one. The file
+    /// takes precedence over the thresholds stored by egui, so that a shipped config applies.
+    pub fn load_thresholds_file(&mut self) {
+        let Some(path) = alerts::config_path().filter(|path| path.exists()) else {
+            return;
+        };
+        match Thresholds::load(&path) {
+            Ok(thresholds) => self.thresholds = thresholds,
+            Err(err) => log::warn!("Failed to load {}: {err}", path.display()),
+        }
+    }
+
+    fn config_file_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(path) = alerts::config_path() else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.label("Config file:");
+            ui.monospace(path.display().to_string())
+                .on_hover_text("The thresholds in this file are loaded on startup");
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Save thresholds").clicked() {
+                self.config_status = Some(match self.thresholds.save(&path) {
+                    Ok(()) => String::from("Saved"),
+                    Err(err) => format!("Saving failed: {err}"),
+                });
+            }
+            if ui.button("Load thresholds").clicked() {
+                self.config_status = Some(match Thresholds::load(&path) {
+                    Ok(thresholds) => {
+                        self.thresholds = thresholds;
+                        String::from("Loaded")
+                    }
+                    Err(err) => format!("Loading failed: {err}"),
+                });
+            }
+            if let Some(status) = &self.config_status {
+                ui.weak(status);
+            }
+        });
+    }
+
+    pub fn window_ui(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings_window;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Update interval:");
+                    ui.add(
+                        egui::Slider::new(&mut self.update_interval_ms, 20..=10_000)
+                            .logarithmic(true)
+                            .suffix(" ms"),
+                    );
+                });
+                ui.collapsing("Per-metric intervals", |ui| {
+                    let intervals = &mut self.metric_intervals;
+                    for (label, interval_ms) in [
+                        ("Temperatures and fans:", &mut intervals.temperature_ms),
+                        ("Processes:", &mut intervals.processes_ms),
+                        ("Device info:", &mut intervals.static_info_ms),
+                    ] {
+                        ui.horizontal(|ui| {
+                            ui.label(label);
+                            ui.add(
+                                egui::Slider::new(interval_ms, 20..=60_000)
+                                    .logarithmic(true)
+                                    .suffix(" ms"),
+                            );
+                        });
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Process name length:");
+                    ui.add(
+                        egui::DragValue::new(&mut self.process_name_length)
+                            .range(64..=4096)
+                            .suffix(" bytes"),
+                    )
+                    .on_hover_text(
+                        "How much of each process's path and arguments is read, \
+                         longer ones end in …",
+                    );
+                });
+                ui.checkbox(&mut self.idle_dimming, "Dim when idle")
+                    .on_hover_text(
+                        "Dim the window and poll less often while the GPU is idle \
+                         and the window isn't being used",
+                    );
+                ui.checkbox(&mut self.slow_when_unfocused, "Slow down when unfocused")
+                    .on_hover_text(
+                        "Poll and repaint less often while another window has the focus, \
+                         back at full rate once this one is focused again",
+                    );
+                ui.checkbox(&mut self.animate_tabs, "Animate switching tabs");
+                ui.checkbox(&mut self.always_on_top, "Always on top");
+                ui.checkbox(&mut self.high_contrast, "High contrast")
+                    .on_hover_text(
+                        "Black and white with strong borders and plot colors that stay \
+                         readable, in both the dark and the light theme",
+                    );
+                ui.add_enabled(
+                    !self.read_only_forced,
+                    egui::Checkbox::new(&mut self.read_only, "Read-only"),
+                )
+                .on_hover_text(
+                    "Disable killing processes, changing the power limit and resetting the GPU",
+                )
+                .on_disabled_hover_text("Started with --read-only");
+                ui.horizontal(|ui| {
+                    ui.label("Power smoothing:");
+                    ui.add(egui::Slider::new(&mut self.power_smoothing_s, 0.0..=10.0).suffix(" s"))
+                        .on_hover_text(
+                            "Average the power readout over about this long to steady it, \
+                             0 turns it off. The plots keep the raw readings",
+                        );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Layout:");
+                    egui::ComboBox::from_id_source("profile")
+                        .selected_text(self.profile.to_string())
+                        .show_ui(ui, |ui| {
+                            for profile in Profile::ALL {
+                                ui.selectable_value(
+                                    &mut self.profile,
+                                    profile,
+                                    profile.to_string(),
+                                );
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Show in the window title:");
+                    egui::ComboBox::from_id_source("title metric")
+                        .selected_text(self.title_metric.to_string())
+                        .show_ui(ui, |ui| {
+                            for title_metric in TitleMetric::ALL {
+                                ui.selectable_value(
+                                    &mut self.title_metric,
+                                    title_metric,
+                                    title_metric.to_string(),
+                                );
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Memory unit:");
+                    egui::ComboBox::from_id_source("memory unit")
+                        .selected_text(self.memory_unit.to_string())
+                        .show_ui(ui, |ui| {
+                            for unit_system in UnitSystem::ALL {
+                                ui.selectable_value(
+                                    &mut self.memory_unit,
+                                    unit_system,
+                                    unit_system.to_string(),
+                                );
+                            }
+                        });
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Number format:");
+                    egui::ComboBox::from_id_source("number format")
+                        .selected_text(self.number_format.to_string())
+                        .show_ui(ui, |ui| {
+                            for number_format in NumberFormat::ALL {
+                                ui.selectable_value(
+                                    &mut self.number_format,
+                                    number_format,
+                                    number_format.to_string(),
+                                );
+                            }
+                        });
+                });
+                ui.checkbox(
+                    &mut self.pin_memory_plot,
+                    "Scale the memory plot to the installed memory",
+                );
+                ui.horizontal(|ui| {
+                    ui.label("Plot height:");
+                    ui.add_enabled(
+                        !self.fit_plots_to_window,
+                        egui::Slider::new(&mut self.plot_height, 150.0..=1000.0).suffix(" pt"),
+                    );
+                    ui.checkbox(&mut self.fit_plots_to_window, "Fit to window");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Line width:");
+                    ui.add(egui::Slider::new(&mut self.plot_line_width, 0.5..=6.0).suffix(" pt"));
+                    ui.checkbox(&mut self.fill_plots, "Fill under lines");
+                });
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Metrics:");
+                    for (metric, shown) in METRICS.iter().zip(self.shown_metrics()) {
+                        let mut shown = shown;
+                        if ui.checkbox(&mut shown, metric.name).changed() {
+                            self.hidden_metrics.retain(|id| id != metric.id);
+                            if !shown {
+                                self.hidden_metrics.push(metric.id.to_string());
+                            }
+                        }
+                    }
+                });
+                ui.checkbox(&mut self.keep_full_history, "Keep the whole session")
+                    .on_hover_text(
+                        "Once the plots are full, merge the oldest samples instead of dropping \
+                         them, so the whole session stays visible at a coarser resolution",
+                    );
+                ui.checkbox(&mut self.restore_history, "Restore the plots on startup")
+                    .on_hover_text(
+                        "Save the plotted history on exit and show it before the new readings \
+                         next time, unless it is more than an hour old",
+                    );
+                ui.horizontal(|ui| {
+                    ui.label("Overlay opacity:");
+                    ui.add(egui::Slider::new(&mut self.overlay_opacity, 0.2..=1.0));
+                });
+                #[cfg(feature = "http-api")]
+                ui.horizontal(|ui| {
+                    ui.label("HTTP API address:");
+                    ui.text_edit_singleline(&mut self.http_api_address)
+                        .on_hover_text("Takes effect after a restart");
+                });
+                #[cfg(feature = "local-stream")]
+                ui.horizontal(|ui| {
+                    ui.label("Metric stream path:");
+                    ui.text_edit_singleline(&mut self.stream_path)
+                        .on_hover_text("Takes effect after a restart");
+                });
+
+                ui.separator();
+                ui.label("Warnings");
+                let thresholds = &mut self.thresholds;
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut thresholds.temperature_enabled, "Temperature above");
+                    ui.add_enabled(
+                        thresholds.temperature_enabled,
+                        egui::Slider::new(&mut thresholds.temperature, alerts::TEMPERATURE_RANGE)
+                            .suffix("°C"),
+                    );
+                    ui.label("on");
+                    egui::ComboBox::from_id_source("temperature sensor")
+                        .selected_text(thresholds.temperature_sensor.to_string())
+                        .show_ui(ui, |ui| {
+                            for sensor in TemperatureSensor::ALL {
+                                ui.selectable_value(
+                                    &mut thresholds.temperature_sensor,
+                                    sensor,
+                                    sensor.to_string(),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "Also the temperature shown in the window title. Cards with GDDR6X \
+                             or HBM memory usually run hottest at the memory junction",
+                        );
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut thresholds.memory_enabled, "Memory usage above");
+                    ui.add_enabled(
+                        thresholds.memory_enabled,
+                        egui::Slider::new(
+                            &mut thresholds.memory_percent,
+                            alerts::MEMORY_PERCENT_RANGE,
+                        )
+                        .suffix("%"),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut thresholds.power_enabled, "Power usage above");
+                    ui.add_enabled(
+                        thresholds.power_enabled,
+                        egui::Slider::new(&mut thresholds.power, alerts::POWER_RANGE).suffix(" W"),
+                    );
+                });
+                ui.checkbox(&mut thresholds.notify, "Notify when a threshold is crossed");
+                #[cfg(feature = "audio")]
+                ui.checkbox(
+                    &mut thresholds.sound,
+                    "Play a sound when a threshold is crossed",
+                );
+                self.config_file_ui(ui);
+            });
+        self.show_settings_window = open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title() {
+        let device_state = DeviceState::mock()
+            .with_temperature(54)
+            .with_memory(0, 0)
+            .with_power(150_000)
+            .with_gpu_utilization(80);
+        assert_eq!(
+            TitleMetric::Temperature.title(Some(&device_state), TemperatureSensor::Core),
+            "54°C - nvsmi-gui"
+        );
+        assert_eq!(
+            TitleMetric::Temperature.title(Some(&device_state), TemperatureSensor::Memory),
+            "N/A - nvsmi-gui"
+        );
+        assert_eq!(
+            TitleMetric::Utilization.title(Some(&device_state), TemperatureSensor::Core),
+            "GPU 80% - nvsmi-gui"
+        );
+        assert_eq!(
+            TitleMetric::Power.title(Some(&device_state), TemperatureSensor::Core),
+            "150.0 W - nvsmi-gui"
+        );
+        assert_eq!(
+            TitleMetric::Off.title(Some(&device_state), TemperatureSensor::Core),
+            "nvsmi-gui"
+        );
+        assert_eq!(
+            TitleMetric::Temperature.title(None, TemperatureSensor::Core),
+            "nvsmi-gui"
+        );
+    }
+}
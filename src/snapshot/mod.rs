@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+
+use eframe::egui;
+
+use crate::device::{self, format_metric, DeviceState};
+use crate::process::{ProcessData, ProcessState};
+use crate::units::{format_bytes, UnitSystem};
+
+/// How the live state differs from a snapshot. Deltas are live minus snapshot.
+#[derive(Debug, Clone)]
+pub struct SnapshotDiff {
+    /// `None` if either side doesn't have the reading, here and for the other metrics.
+    pub temperature: Option<i64>,
+    /// In bytes.
+    pub memory_used: Option<i64>,
+    /// In milliwatts.
+    pub power_usage: Option<i64>,
+    pub gpu_utilization: Option<i64>,
+    /// Processes that are running now but weren't in the snapshot.
+    pub new_processes: Vec<ProcessData>,
+    /// Processes from the snapshot that aren't running anymore.
+    pub gone_processes: Vec<ProcessData>,
+}
+
+impl SnapshotDiff {
+    pub fn new(
+        snapshot_device: &DeviceState,
+        snapshot_processes: &ProcessState,
+        live_device: &DeviceState,
+        live_processes: &ProcessState,
+    ) -> Self {
+        Self {
+            temperature: delta(snapshot_device.temperature, live_device.temperature),
+            memory_used: device::memory_used(live_device)
+                .zip(device::memory_used(snapshot_device))
+                .map(|(live, snapshot)| live as i64 - snapshot as i64),
+            power_usage: delta(snapshot_device.power_usage, live_device.power_usage),
+            gpu_utilization: delta(
+                device::gpu_utilization(snapshot_device),
+                device::gpu_utilization(live_device),
+            ),
+            new_processes: processes_missing_from(live_processes, snapshot_processes),
+            gone_processes: processes_missing_from(snapshot_processes, live_processes),
+        }
+    }
+}
+
+/// The processes in `processes` whose pid doesn't appear in `other`.
+fn processes_missing_from(processes: &ProcessState, other: &ProcessState) -> Vec<ProcessData> {
+    let other_pids: HashSet<u32> = other
+        .processes
+        .iter()
+        .map(|process| process.process_info.pid)
+        .collect();
+    processes
+        .processes
+        .iter()
+        .filter(|process| !other_pids.contains(&process.process_info.pid))
+        .cloned()
+        .collect()
+}
+
+fn delta(snapshot: Option<u32>, live: Option<u32>) -> Option<i64> {
+    Some(live? as i64 - snapshot? as i64)
+}
+
+fn format_delta(delta: Option<i64>, unit: &str) -> String {
+    match delta {
+        Some(delta) => format!("{delta:+}{unit}"),
+        None => String::from("N/A"),
+    }
+}
+
+fn format_delta_bytes(delta: Option<i64>, unit_system: UnitSystem) -> String {
+    match delta {
+        Some(delta) => {
+            let sign = if delta < 0 { "-" } else { "+" };
+            format!("{sign}{}", format_bytes(delta.unsigned_abs(), unit_system))
+        }
+        None => String::from("N/A"),
+    }
+}
+
+fn format_memory(device_state: &DeviceState, unit_system: UnitSystem) -> String {
+    device::memory_used(device_state).map_or_else(
+        || String::from("N/A"),
+        |used| format_bytes(used, unit_system),
+    )
+}
+
+/// Draws the snapshot next to the live values, followed by the processes that came and went.
+pub fn comparison_ui(
+    ui: &mut egui::Ui,
+    snapshot_device: &DeviceState,
+    live_device: &DeviceState,
+    diff: &SnapshotDiff,
+    unit_system: UnitSystem,
+) {
+    egui::Grid::new("snapshot comparison")
+        .num_columns(4)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("Metric");
+            ui.strong("Snapshot");
+            ui.strong("Live");
+            ui.strong("Delta");
+            ui.end_row();
+
+            ui.label("Temperature");
+            ui.label(format_metric(snapshot_device.temperature, "°C"));
+            ui.label(format_metric(live_device.temperature, "°C"));
+            ui.label(format_delta(diff.temperature, "°C"));
+            ui.end_row();
+
+            ui.label("Memory usage");
+            ui.label(format_memory(snapshot_device, unit_system));
+            ui.label(format_memory(live_device, unit_system));
+            ui.label(format_delta_bytes(diff.memory_used, unit_system));
+            ui.end_row();
+
+            ui.label("Power usage");
+            ui.label(device::format_power(snapshot_device, None));
+            ui.label(device::format_power(live_device, None));
+            ui.label(match diff.power_usage {
+                Some(milliwatts) => {
+                    let sign = if milliwatts < 0 { "" } else { "+" };
+                    format!("{sign}{}", device::format_watts(milliwatts as f64 / 1000.0))
+                }
+                None => String::from("N/A"),
+            });
+            ui.end_row();
+
+            ui.label("GPU utilization");
+            ui.label(format_metric(device::gpu_utilization(snapshot_device), "%"));
+            ui.label(format_metric(device::gpu_utilization(live_device), "%"));
+            ui.label(format_delta(diff.gpu_utilization, "%"));
+            ui.end_row();
+        });
+
+    ui.add_space(10.0);
+    process_list_ui(ui, "New processes", &diff.new_processes, unit_system);
+    process_list_ui(ui, "Gone processes", &diff.gone_processes, unit_system);
+}
+
+fn process_list_ui(
+    ui: &mut egui::Ui,
+    heading: &str,
+    processes: &[ProcessData],
+    unit_system: UnitSystem,
+) {
+    ui.strong(format!("{heading}: {}", processes.len()));
+    for process in processes {
+        ui.label(format!(
+            "{} {} ({}, {})",
+            process.process_info.pid,
+            process.process_name,
+            process.process_kind,
+            format_bytes(process.used_memory(), unit_system)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use nvml_wrapper::enums::device::UsedGpuMemory;
+
+    fn process_state(pids: &[u32]) -> ProcessState {
+        ProcessState {
+            processes: pids
+                .iter()
+                .map(|&pid| ProcessData::mock(pid, UsedGpuMemory::Used(1024)))
+                .collect(),
+        }
+    }
+
+    fn pids(processes: &[ProcessData]) -> Vec<u32> {
+        processes
+            .iter()
+            .map(|process| process.process_info.pid)
+            .collect()
+    }
+
+    #[test]
+    fn test_processes_missing_from() {
+        let snapshot = process_state(&[1, 2, 3]);
+        let live = process_state(&[2, 3, 4, 5]);
+        assert_eq!(pids(&processes_missing_from(&live, &snapshot)), vec![4, 5]);
+        assert_eq!(pids(&processes_missing_from(&snapshot, &live)), vec![1]);
+    }
+
+    #[test]
+    fn test_format_delta_bytes() {
+        assert_eq!(
+            format_delta_bytes(Some(-2 * 1024 * 1024), UnitSystem::Mebibytes),
+            "-2 MiB"
+        );
+        assert_eq!(format_delta_bytes(Some(0), UnitSystem::Mebibytes), "+0 MiB");
+        assert_eq!(format_delta_bytes(None, UnitSystem::Mebibytes), "N/A");
+    }
+
+    #[test]
+    fn test_delta_of_unsupported_metric() {
+        assert_eq!(delta(Some(40), Some(45)), Some(5));
+        assert_eq!(delta(None, Some(45)), None);
+        assert_eq!(format_delta(None, "W"), "N/A");
+    }
+}
@@ -0,0 +1,35 @@
+use std::error::Error;
+use std::time::Duration;
+
+use rodio::source::{SineWave, Source};
+use rodio::{OutputStream, OutputStreamHandle};
+
+const BEEP_FREQUENCY: f32 = 880.0;
+const BEEP_DURATION: Duration = Duration::from_millis(300);
+const BEEP_VOLUME: f32 = 0.2;
+
+/// Plays a short beep through the default audio output when a threshold is crossed.
+pub struct AlertSound {
+    // Playback stops as soon as the stream is dropped
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+}
+
+impl AlertSound {
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let (stream, handle) = OutputStream::try_default()?;
+        Ok(Self {
+            _stream: stream,
+            handle,
+        })
+    }
+
+    pub fn play(&self) {
+        let beep = SineWave::new(BEEP_FREQUENCY)
+            .take_duration(BEEP_DURATION)
+            .amplify(BEEP_VOLUME);
+        if let Err(err) = self.handle.play_raw(beep) {
+            log::warn!("Failed to play alert sound: {err}");
+        }
+    }
+}
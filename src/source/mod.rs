@@ -0,0 +1,1061 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use nvml_wrapper::bitmasks::device::ThrottleReasons;
+use nvml_wrapper::enum_wrappers::device::{
+    EccCounter, MemoryError, TemperatureSensor, TemperatureThreshold,
+};
+use nvml_wrapper::enums::device::{DeviceArchitecture, SampleValue, UsedGpuMemory};
+use nvml_wrapper::error::NvmlError;
+use nvml_wrapper::struct_wrappers::device::{MemoryInfo, ProcessInfo, Utilization};
+use nvml_wrapper::structs::device::{FieldId, PowerManagementConstraints};
+use nvml_wrapper::sys_exports::field_id::NVML_FI_DEV_MEMORY_TEMP;
+use nvml_wrapper::{Device, Nvml};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::DeviceSelector;
+use crate::device::{CudaDriverVersion, DeviceState, EccErrors};
+use crate::metric::{self, METRICS};
+use crate::mig::{self, MigInstance};
+use crate::process::{self, ProcessData, ProcessKind, ProcessState};
+use crate::read_only::ReadOnly;
+use crate::units::MIB;
+
+/// The fields read from `nvidia-smi --query-gpu`, in the order `parse_device_state` expects.
+/// The fields of the registry metrics follow them.
+const NVIDIA_SMI_GPU_FIELDS: &[&str] = &[
+    "name",
+    "driver_version",
+    "temperature.gpu",
+    "memory.total",
+    "memory.used",
+    "memory.free",
+    "fan.speed",
+    "power.draw",
+    "power.limit",
+    "utilization.gpu",
+    "utilization.memory",
+    "pstate",
+    "clocks_throttle_reasons.active",
+    "display_active",
+    "ecc.errors.corrected.volatile.total",
+    "ecc.errors.uncorrected.volatile.total",
+];
+
+/// The ids that tell identical GPUs apart, `None` where they couldn't be read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceIds {
+    pub pci_bus_id: Option<String>,
+    pub uuid: Option<String>,
+}
+
+impl DeviceIds {
+    /// The device name followed by its ids, one per line.
+    pub fn describe(&self, name: &str) -> String {
+        let unknown = || String::from("unknown");
+        format!(
+            "{name}\nPCI bus id: {}\nUUID: {}",
+            self.pci_bus_id.clone().unwrap_or_else(unknown),
+            self.uuid.clone().unwrap_or_else(unknown)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemState {
+    pub device_state: DeviceState,
+    pub process_state: ProcessState,
+}
+
+#[derive(Debug)]
+pub enum SourceError {
+    Nvml(NvmlError),
+    /// Running `nvidia-smi` failed or its output couldn't be parsed.
+    NvidiaSmi(String),
+    /// A mutating action was refused, see [`crate::read_only`].
+    ReadOnly(ReadOnly),
+}
+
+impl Display for SourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceError::Nvml(err) => write!(f, "{err}"),
+            SourceError::NvidiaSmi(message) => write!(f, "nvidia-smi: {message}"),
+            SourceError::ReadOnly(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+impl From<ReadOnly> for SourceError {
+    fn from(err: ReadOnly) -> Self {
+        SourceError::ReadOnly(err)
+    }
+}
+
+impl From<NvmlError> for SourceError {
+    fn from(err: NvmlError) -> Self {
+        SourceError::Nvml(err)
+    }
+}
+
+/// How often the slower changing readings are refreshed. Everything else, such as the
+/// utilization, clocks and power, is read on every poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricIntervals {
+    /// GPU and memory temperatures and fan speeds.
+    pub temperature_ms: u64,
+    /// The process list and MIG instances.
+    pub processes_ms: u64,
+    /// Name, driver version, temperature thresholds, power limits and the like.
+    pub static_info_ms: u64,
+}
+
+impl Default for MetricIntervals {
+    fn default() -> Self {
+        Self {
+            temperature_ms: 500,
+            processes_ms: 1000,
+            static_info_ms: 5000,
+        }
+    }
+}
+
+/// Where the readings come from.
+pub trait GpuSource: Send + Sync {
+    /// A short name for the backend, shown in the status bar.
+    fn name(&self) -> &'static str;
+
+    fn device_names(&self) -> Vec<String>;
+
+    /// The PCI bus id and UUID of each device, in the order of `device_names`.
+    fn device_ids(&self) -> Vec<DeviceIds> {
+        Vec::new()
+    }
+
+    /// The index of the GPU picked on the command line.
+    fn resolve_device(&self, selector: &DeviceSelector) -> Result<usize, SourceError>;
+
+    fn poll_device(&self, device_index: usize) -> Result<SystemState, SourceError>;
+
+    /// Backends that can read metrics separately use these to skip reads between polls.
+    fn set_metric_intervals(&self, _intervals: MetricIntervals) {}
+
+    /// How many bytes of a process's name are read, for backends that look them up by pid.
+    fn set_process_name_length(&self, _length: usize) {}
+
+    /// The NVML handle for features that only NVML provides, such as the supported clocks and
+    /// the power limit control. `None` for other backends.
+    fn nvml(&self) -> Option<&Nvml> {
+        None
+    }
+}
+
+/// Loads NVML, falling back to `nvidia-smi` on systems where the library can't be loaded.
+pub fn open() -> Result<Box<dyn GpuSource>, SourceError> {
+    let nvml_err = match NvmlSource::new() {
+        Ok(source) => return Ok(Box::new(source)),
+        Err(err) => err,
+    };
+    log::warn!("Failed to load NVML, falling back to nvidia-smi: {nvml_err}");
+    match NvidiaSmiSource::new() {
+        Ok(source) => Ok(Box::new(source)),
+        Err(err) => {
+            log::error!("nvidia-smi isn't usable either: {err}");
+            Err(nvml_err)
+        }
+    }
+}
+
+fn poll_fan_speeds(device: &Device) -> Vec<Option<u32>> {
+    let num_fans = match device.num_fans() {
+        Ok(num_fans) => num_fans,
+        Err(NvmlError::NotSupported) => 0,
+        Err(err) => {
+            log::warn!("Failed to query the number of fans: {err}");
+            0
+        }
+    };
+    (0..num_fans)
+        .map(|fan_idx| match device.fan_speed(fan_idx) {
+            Ok(speed) => Some(speed),
+            Err(NvmlError::NotSupported) => None,
+            Err(err) => {
+                log::warn!("Failed to query fan {fan_idx} speed: {err}");
+                None
+            }
+        })
+        .collect()
+}
+
+fn poll_performance_state(device: &Device) -> Option<u8> {
+    match device.performance_state() {
+        // The C enum numbers the states 0 to 15, with a separate value for unknown
+        Ok(pstate) => u8::try_from(pstate.as_c())
+            .ok()
+            .filter(|&pstate| pstate <= 15),
+        Err(NvmlError::NotSupported) => None,
+        Err(err) => {
+            log::warn!("Failed to query performance state: {err}");
+            None
+        }
+    }
+}
+
+fn poll_ecc_errors(device: &Device) -> Option<EccErrors> {
+    let total = |error_type| {
+        device
+            .total_ecc_errors(error_type, EccCounter::Volatile)
+            .ok()
+    };
+    Some(EccErrors {
+        corrected: total(MemoryError::Corrected)?,
+        uncorrected: total(MemoryError::Uncorrected)?,
+    })
+}
+
+fn poll_memory_temperature(device: &Device) -> Option<u32> {
+    let samples = device
+        .field_values_for(&[FieldId(NVML_FI_DEV_MEMORY_TEMP)])
+        .ok()?;
+    memory_temperature(samples.into_iter().next()?.ok()?.value.ok()?)
+}
+
+/// The temperature in a field value, `None` for readings no sensor could have taken, e.g.
+/// a negative one that would otherwise wrap around to billions of degrees.
+fn memory_temperature(value: SampleValue) -> Option<u32> {
+    match value {
+        SampleValue::U32(temp) => Some(temp),
+        SampleValue::U64(temp) => u32::try_from(temp).ok(),
+        SampleValue::I64(temp) => u32::try_from(temp).ok(),
+        // NaN and the infinities are outside of the range too
+        SampleValue::F64(temp) => (0.0..=f64::from(u32::MAX))
+            .contains(&temp)
+            .then_some(temp as u32),
+    }
+    // Some drivers report zero instead of an error when the sensor isn't there
+    .filter(|&temp| temp > 0)
+}
+
+/// Turns a query the device doesn't support into `None`, keeping other errors.
+fn supported<T>(result: Result<T, NvmlError>) -> Result<Option<T>, NvmlError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(NvmlError::NotSupported) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Returns the value cached in `slot` if it was read less than `interval` ago, otherwise reads
+/// and caches a new one. Failed reads aren't cached.
+fn cached<T: Clone, E>(
+    slot: &mut Option<(Instant, T)>,
+    interval: Duration,
+    now: Instant,
+    read: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E> {
+    if let Some((read_at, value)) = slot {
+        if now.duration_since(*read_at) < interval {
+            return Ok(value.clone());
+        }
+    }
+    let value = read()?;
+    *slot = Some((now, value.clone()));
+    Ok(value)
+}
+
+#[derive(Debug, Clone)]
+struct StaticInfo {
+    name: String,
+    driver_version: String,
+    cuda_driver_version: Option<CudaDriverVersion>,
+    compute_capability: Option<(i32, i32)>,
+    architecture: Option<DeviceArchitecture>,
+    slowdown_temperature: Option<u32>,
+    memory_slowdown_temperature: Option<u32>,
+    shutdown_temperature: Option<u32>,
+    power_limit: Option<u32>,
+    power_limit_constraints: Option<PowerManagementConstraints>,
+    display_active: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+struct Temperatures {
+    temperature: Option<u32>,
+    memory_temperature: Option<u32>,
+    fan_speeds: Vec<Option<u32>>,
+}
+
+/// The last reading of each group of slower changing metrics of a device.
+#[derive(Default)]
+struct DeviceCache {
+    static_info: Option<(Instant, StaticInfo)>,
+    temperatures: Option<(Instant, Temperatures)>,
+    processes: Option<(Instant, (ProcessState, Vec<MigInstance>))>,
+}
+
+pub struct NvmlSource {
+    nvml: Nvml,
+    intervals: Mutex<MetricIntervals>,
+    caches: Mutex<HashMap<usize, DeviceCache>>,
+    process_name_length: AtomicUsize,
+}
+
+impl NvmlSource {
+    pub fn new() -> Result<Self, SourceError> {
+        Ok(Self {
+            nvml: Nvml::init()?,
+            intervals: Mutex::new(MetricIntervals::default()),
+            caches: Mutex::new(HashMap::new()),
+            process_name_length: AtomicUsize::new(process::DEFAULT_PROCESS_NAME_LENGTH),
+        })
+    }
+
+    /// The process's executable path and arguments, ending in
+    /// [`process::TRUNCATION_MARKER`] if they didn't fit the process name length.
+    fn process_name(&self, pid: u32) -> String {
+        let length = self.process_name_length.load(Ordering::Relaxed);
+        match self.nvml.sys_process_name(pid, length) {
+            // The buffer includes the terminating nul
+            Ok(mut name) if name.len() + 1 >= length => {
+                name.push(process::TRUNCATION_MARKER);
+                name
+            }
+            Ok(name) => name,
+            Err(_) => String::from("Unknown"),
+        }
+    }
+
+    fn read_static_info(&self, device: &Device) -> Result<StaticInfo, SourceError> {
+        Ok(StaticInfo {
+            name: device.name()?,
+            driver_version: self.nvml.sys_driver_version()?,
+            cuda_driver_version: self
+                .nvml
+                .sys_cuda_driver_version()
+                .map(|version| CudaDriverVersion {
+                    major: nvml_wrapper::cuda_driver_version_major(version),
+                    minor: nvml_wrapper::cuda_driver_version_minor(version),
+                })
+                .inspect_err(|err| log::warn!("Failed to query the CUDA version: {err}"))
+                .ok(),
+            compute_capability: device
+                .cuda_compute_capability()
+                .map(|capability| (capability.major, capability.minor))
+                .ok(),
+            architecture: device.architecture().ok(),
+            slowdown_temperature: device
+                .temperature_threshold(TemperatureThreshold::Slowdown)
+                .ok(),
+            memory_slowdown_temperature: device
+                .temperature_threshold(TemperatureThreshold::MemoryMax)
+                .ok(),
+            shutdown_temperature: device
+                .temperature_threshold(TemperatureThreshold::Shutdown)
+                .ok(),
+            power_limit: device.power_management_limit().ok(),
+            power_limit_constraints: device.power_management_limit_constraints().ok(),
+            display_active: device.is_display_active().ok(),
+        })
+    }
+
+    fn read_temperatures(device: &Device) -> Result<Temperatures, SourceError> {
+        Ok(Temperatures {
+            temperature: supported(device.temperature(TemperatureSensor::Gpu))?,
+            memory_temperature: poll_memory_temperature(device),
+            fan_speeds: poll_fan_speeds(device),
+        })
+    }
+
+    fn read_processes(
+        &self,
+        device: &Device,
+    ) -> Result<(ProcessState, Vec<MigInstance>), SourceError> {
+        let running_graphics_processes =
+            supported(device.running_graphics_processes())?.unwrap_or_default();
+
+        let graphics_process_names: Vec<String> = running_graphics_processes
+            .iter()
+            .map(|process| self.process_name(process.pid))
+            .collect();
+
+        let graphics_process_data_vec: Vec<ProcessData> = running_graphics_processes
+            .iter()
+            .zip(graphics_process_names)
+            .map(|(process_info, process_name)| ProcessData {
+                process_info: process_info.clone(),
+                process_kind: ProcessKind::Graphics,
+                process_name: process::get_process_name(&process_name)
+                    .to_string()
+                    .to_lowercase(),
+                parent: process::parent_process(process_info.pid),
+                command_line: process::command_line(process_info.pid),
+                gpu: None,
+            })
+            .collect();
+
+        let running_compute_processes =
+            supported(device.running_compute_processes())?.unwrap_or_default();
+        let compute_process_names: Vec<String> = running_compute_processes
+            .iter()
+            .map(|process| self.process_name(process.pid))
+            .collect();
+
+        let compute_process_data_vec: Vec<ProcessData> = running_compute_processes
+            .iter()
+            .zip(compute_process_names)
+            .map(|(process_info, process_name)| ProcessData {
+                process_info: process_info.clone(),
+                process_kind: ProcessKind::Compute,
+                process_name: process::get_process_name(&process_name).to_string(),
+                parent: process::parent_process(process_info.pid),
+                command_line: process::command_line(process_info.pid),
+                gpu: None,
+            })
+            .collect();
+
+        let process_state =
+            ProcessState::from_lists(graphics_process_data_vec, compute_process_data_vec);
+
+        Ok((process_state, mig::poll_mig_instances(device)))
+    }
+}
+
+impl GpuSource for NvmlSource {
+    fn name(&self) -> &'static str {
+        "NVML"
+    }
+
+    fn device_names(&self) -> Vec<String> {
+        let device_count = self
+            .nvml
+            .device_count()
+            .inspect_err(|err| log::error!("Failed to query the number of GPUs: {err}"))
+            .unwrap_or(0);
+        (0..device_count)
+            .map(|index| {
+                self.nvml
+                    .device_by_index(index)
+                    .and_then(|device| device.name())
+                    .unwrap_or_else(|_| format!("GPU {index}"))
+            })
+            .collect()
+    }
+
+    fn device_ids(&self) -> Vec<DeviceIds> {
+        let device_count = self.nvml.device_count().unwrap_or(0);
+        (0..device_count)
+            .map(|index| match self.nvml.device_by_index(index) {
+                Ok(device) => DeviceIds {
+                    pci_bus_id: device.pci_info().map(|pci_info| pci_info.bus_id).ok(),
+                    uuid: device.uuid().ok(),
+                },
+                Err(_) => DeviceIds::default(),
+            })
+            .collect()
+    }
+
+    fn resolve_device(&self, selector: &DeviceSelector) -> Result<usize, SourceError> {
+        let device = match selector {
+            DeviceSelector::Index(index) => self.nvml.device_by_index(*index)?,
+            DeviceSelector::Uuid(uuid) => self.nvml.device_by_uuid(uuid.as_str())?,
+            DeviceSelector::PciBusId(pci_bus_id) => {
+                self.nvml.device_by_pci_bus_id(pci_bus_id.as_str())?
+            }
+        };
+        Ok(device.index()? as usize)
+    }
+
+    fn poll_device(&self, device_index: usize) -> Result<SystemState, SourceError> {
+        let device = self.nvml.device_by_index(device_index as u32)?;
+        let intervals = *self.intervals.lock().unwrap();
+        let now = Instant::now();
+        let mut caches = self.caches.lock().unwrap();
+        let cache = caches.entry(device_index).or_default();
+
+        let info = cached(
+            &mut cache.static_info,
+            Duration::from_millis(intervals.static_info_ms),
+            now,
+            || self.read_static_info(&device),
+        )?;
+        let temperatures = cached(
+            &mut cache.temperatures,
+            Duration::from_millis(intervals.temperature_ms),
+            now,
+            || Self::read_temperatures(&device),
+        )?;
+        let (process_state, mig_instances) = cached(
+            &mut cache.processes,
+            Duration::from_millis(intervals.processes_ms),
+            now,
+            || self.read_processes(&device),
+        )?;
+        drop(caches);
+
+        let device_state = DeviceState {
+            name: info.name,
+            driver_version: info.driver_version,
+            cuda_driver_version: info.cuda_driver_version,
+            compute_capability: info.compute_capability,
+            architecture: info.architecture,
+            temperature: temperatures.temperature,
+            memory_temperature: temperatures.memory_temperature,
+            slowdown_temperature: info.slowdown_temperature,
+            memory_slowdown_temperature: info.memory_slowdown_temperature,
+            shutdown_temperature: info.shutdown_temperature,
+            mem_info: device
+                .memory_info()
+                .inspect_err(|err| log::warn!("Failed to query the memory usage: {err}"))
+                .ok(),
+            fan_speeds: temperatures.fan_speeds,
+            power_usage: supported(device.power_usage())?,
+            total_energy: device.total_energy_consumption().ok(),
+            metrics: metric::poll_nvml(&device),
+            utilization: supported(device.utilization_rates())?,
+            pstate: poll_performance_state(&device),
+            throttle_reasons: device.current_throttle_reasons().ok(),
+            power_limit: info.power_limit,
+            power_limit_constraints: info.power_limit_constraints,
+            mig_instances,
+            display_active: info.display_active,
+            ecc_errors: poll_ecc_errors(&device),
+        };
+
+        Ok(SystemState {
+            device_state,
+            process_state,
+        })
+    }
+
+    fn set_metric_intervals(&self, intervals: MetricIntervals) {
+        *self.intervals.lock().unwrap() = intervals;
+    }
+
+    fn set_process_name_length(&self, length: usize) {
+        self.process_name_length.store(length, Ordering::Relaxed);
+    }
+
+    fn nvml(&self) -> Option<&Nvml> {
+        Some(&self.nvml)
+    }
+}
+
+/// Reads the GPUs by running `nvidia-smi`, for locked-down or containerized environments where
+/// NVML can't be loaded directly. It only reports what `--query-gpu` offers, so there are no
+/// temperature thresholds, energy counter, MIG instances or graphics processes.
+pub struct NvidiaSmiSource {
+    /// `--query-gpu` has no field for the CUDA version, so it is read once from `nvidia-smi -q`.
+    cuda_driver_version: Option<CudaDriverVersion>,
+}
+
+impl NvidiaSmiSource {
+    pub fn new() -> Result<Self, SourceError> {
+        let output = run_nvidia_smi(&["-q"])?;
+        let cuda_driver_version = parse_cuda_version(&output);
+        if cuda_driver_version.is_none() {
+            log::warn!("nvidia-smi didn't report a CUDA version");
+        }
+        Ok(Self {
+            cuda_driver_version,
+        })
+    }
+}
+
+impl GpuSource for NvidiaSmiSource {
+    fn name(&self) -> &'static str {
+        "nvidia-smi"
+    }
+
+    fn device_names(&self) -> Vec<String> {
+        match run_nvidia_smi(&["--query-gpu=name", "--format=csv,noheader"]) {
+            Ok(output) => output.lines().map(|name| name.trim().to_string()).collect(),
+            Err(err) => {
+                log::error!("Failed to list the GPUs: {err}");
+                Vec::new()
+            }
+        }
+    }
+
+    fn device_ids(&self) -> Vec<DeviceIds> {
+        match run_nvidia_smi(&["--query-gpu=pci.bus_id,uuid", "--format=csv,noheader"]) {
+            Ok(output) => output.lines().map(parse_device_ids).collect(),
+            Err(err) => {
+                log::error!("Failed to list the GPU ids: {err}");
+                Vec::new()
+            }
+        }
+    }
+
+    fn resolve_device(&self, selector: &DeviceSelector) -> Result<usize, SourceError> {
+        // `-i` takes an index, a UUID or a PCI bus id alike
+        let id = match selector {
+            DeviceSelector::Index(index) => index.to_string(),
+            DeviceSelector::Uuid(id) | DeviceSelector::PciBusId(id) => id.clone(),
+        };
+        let output = run_nvidia_smi(&["--query-gpu=index", "--format=csv,noheader", "-i", &id])?;
+        output
+            .trim()
+            .parse()
+            .map_err(|_| SourceError::NvidiaSmi(format!("unexpected GPU index {output:?}")))
+    }
+
+    fn poll_device(&self, device_index: usize) -> Result<SystemState, SourceError> {
+        let index = device_index.to_string();
+        let fields: Vec<&str> = NVIDIA_SMI_GPU_FIELDS
+            .iter()
+            .copied()
+            .chain(METRICS.iter().map(|metric| metric.nvidia_smi_field))
+            .collect();
+        let query = format!("--query-gpu={}", fields.join(","));
+        let output = run_nvidia_smi(&[&query, "--format=csv,noheader,nounits", "-i", &index])?;
+        let device_state = parse_device_state(output.trim(), self.cuda_driver_version)
+            .ok_or_else(|| SourceError::NvidiaSmi(format!("unexpected output {output:?}")))?;
+
+        let output = run_nvidia_smi(&[
+            "--query-compute-apps=pid,used_memory,process_name",
+            "--format=csv,noheader,nounits",
+            "-i",
+            &index,
+        ])?;
+        let processes = output.lines().filter_map(parse_compute_process).collect();
+
+        Ok(SystemState {
+            device_state,
+            process_state: ProcessState::from_lists(Vec::new(), processes),
+        })
+    }
+}
+
+/// Resets the GPU. NVML has no reset call, so this always goes through `nvidia-smi`.
+#[cfg(feature = "admin")]
+pub fn reset_gpu(device_index: usize) -> Result<(), SourceError> {
+    crate::read_only::check()?;
+    run_nvidia_smi(&["--gpu-reset", "-i", &device_index.to_string()]).map(|_| ())
+}
+
+/// Runs `nvidia-smi` and returns what it printed.
+fn run_nvidia_smi(args: &[&str]) -> Result<String, SourceError> {
+    let output = Command::new("nvidia-smi")
+        .args(args)
+        .output()
+        .map_err(|err| SourceError::NvidiaSmi(err.to_string()))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        // Most errors are printed to stdout
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(SourceError::NvidiaSmi(
+            format!("{} {}", stdout.trim(), stderr.trim())
+                .trim()
+                .to_string(),
+        ))
+    }
+}
+
+/// The CUDA version from the header of `nvidia-smi -q`, e.g. `CUDA Version : 12.2`.
+fn parse_cuda_version(output: &str) -> Option<CudaDriverVersion> {
+    let (_, version) = output
+        .lines()
+        .find(|line| line.trim_start().starts_with("CUDA Version"))?
+        .split_once(':')?;
+    let (major, minor) = version.trim().split_once('.')?;
+    Some(CudaDriverVersion {
+        major: major.parse().ok()?,
+        minor: minor.parse().ok()?,
+    })
+}
+
+/// A value from `nounits` CSV output, `None` for `[N/A]`, `[Not Supported]` and the like.
+fn parse_field<T: FromStr>(field: &str) -> Option<T> {
+    field.trim().parse().ok()
+}
+
+/// One line of `--query-gpu=pci.bus_id,uuid` output.
+fn parse_device_ids(line: &str) -> DeviceIds {
+    let mut fields = line.split(',').map(|field| {
+        Some(field.trim()).filter(|field| !field.is_empty() && !field.starts_with('['))
+    });
+    DeviceIds {
+        pci_bus_id: fields.next().flatten().map(String::from),
+        uuid: fields.next().flatten().map(String::from),
+    }
+}
+
+/// One line of `--query-gpu` output with the fields in `NVIDIA_SMI_GPU_FIELDS`, followed by
+/// those of the registry metrics.
+fn parse_device_state(
+    line: &str,
+    cuda_driver_version: Option<CudaDriverVersion>,
+) -> Option<DeviceState> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if fields.len() != NVIDIA_SMI_GPU_FIELDS.len() + METRICS.len() {
+        return None;
+    }
+    let (fields, metric_fields) = fields.split_at(NVIDIA_SMI_GPU_FIELDS.len());
+    let [name, driver_version, temperature, memory_total, memory_used, memory_free, fan_speed, power_draw, power_limit, gpu_utilization, memory_utilization, pstate, throttle_reasons, display_active, ecc_corrected, ecc_uncorrected] =
+        fields
+    else {
+        return None;
+    };
+
+    let mib = |field: &str| parse_field::<u64>(field).map(|mib| mib * MIB);
+    // Power is reported in watts with two decimals, NVML reports milliwatts
+    let milliwatts = |field: &str| parse_field::<f64>(field).map(|watts| (watts * 1000.0) as u32);
+    let utilization = match (
+        parse_field(gpu_utilization),
+        parse_field(memory_utilization),
+    ) {
+        (Some(gpu), Some(memory)) => Some(Utilization { gpu, memory }),
+        _ => None,
+    };
+
+    Some(DeviceState {
+        name: name.to_string(),
+        driver_version: driver_version.to_string(),
+        cuda_driver_version,
+        compute_capability: None,
+        architecture: None,
+        temperature: parse_field(temperature),
+        memory_temperature: None,
+        slowdown_temperature: None,
+        memory_slowdown_temperature: None,
+        shutdown_temperature: None,
+        mem_info: match (mib(memory_free), mib(memory_total), mib(memory_used)) {
+            (Some(free), Some(total), Some(used)) => Some(MemoryInfo { free, total, used }),
+            _ => None,
+        },
+        fan_speeds: match parse_field(fan_speed) {
+            Some(speed) => vec![Some(speed)],
+            None => Vec::new(),
+        },
+        power_usage: milliwatts(power_draw),
+        total_energy: None,
+        metrics: std::array::from_fn(|index| parse_field(metric_fields[index])),
+        utilization,
+        pstate: pstate.strip_prefix('P').and_then(parse_field),
+        throttle_reasons: throttle_reasons
+            .strip_prefix("0x")
+            .and_then(|bits| u64::from_str_radix(bits, 16).ok())
+            .map(ThrottleReasons::from_bits_truncate),
+        power_limit: milliwatts(power_limit),
+        power_limit_constraints: None,
+        mig_instances: Vec::new(),
+        display_active: match *display_active {
+            "Enabled" => Some(true),
+            "Disabled" => Some(false),
+            _ => None,
+        },
+        ecc_errors: match (parse_field(ecc_corrected), parse_field(ecc_uncorrected)) {
+            (Some(corrected), Some(uncorrected)) => Some(EccErrors {
+                corrected,
+                uncorrected,
+            }),
+            _ => None,
+        },
+    })
+}
+
+/// One line of `--query-compute-apps=pid,used_memory,process_name` output. The name comes last
+/// since paths may contain commas.
+fn parse_compute_process(line: &str) -> Option<ProcessData> {
+    let mut fields = line.splitn(3, ',').map(str::trim);
+    let pid = parse_field(fields.next()?)?;
+    let used_gpu_memory = match parse_field::<u64>(fields.next()?) {
+        Some(mib) => UsedGpuMemory::Used(mib * MIB),
+        None => UsedGpuMemory::Unavailable,
+    };
+    let process_name = fields.next()?;
+    Some(ProcessData {
+        process_info: ProcessInfo {
+            pid,
+            used_gpu_memory,
+            gpu_instance_id: None,
+            compute_instance_id: None,
+        },
+        process_kind: ProcessKind::Compute,
+        process_name: process::get_process_name(process_name).to_string(),
+        parent: process::parent_process(pid),
+        command_line: process::command_line(pid),
+        gpu: None,
+    })
+}
+
+/// Plays back scripted readings, so that the logic built on polling can be tested without a GPU.
+/// `None` entries make the poll fail. Once the script runs out the last entry is repeated.
+#[cfg(test)]
+pub struct MockSource {
+    script: Vec<Option<SystemState>>,
+    next: std::sync::Mutex<usize>,
+}
+
+#[cfg(test)]
+impl MockSource {
+    pub fn new(script: Vec<Option<SystemState>>) -> Self {
+        Self {
+            script,
+            next: std::sync::Mutex::new(0),
+        }
+    }
+}
+
+#[cfg(test)]
+impl GpuSource for MockSource {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn device_names(&self) -> Vec<String> {
+        vec![String::from("Mock GPU")]
+    }
+
+    fn resolve_device(&self, selector: &DeviceSelector) -> Result<usize, SourceError> {
+        match selector {
+            DeviceSelector::Index(0) => Ok(0),
+            _ => Err(SourceError::Nvml(NvmlError::NotFound)),
+        }
+    }
+
+    fn poll_device(&self, _device_index: usize) -> Result<SystemState, SourceError> {
+        let mut next = self.next.lock().unwrap();
+        let index = (*next).min(self.script.len().saturating_sub(1));
+        *next += 1;
+        match self.script.get(index) {
+            Some(Some(system_state)) => Ok(system_state.clone()),
+            _ => Err(SourceError::Nvml(NvmlError::Unknown)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::alerts::{AlertMonitor, Thresholds};
+
+    const CUDA_DRIVER_VERSION: Option<CudaDriverVersion> = Some(CudaDriverVersion {
+        major: 12,
+        minor: 2,
+    });
+
+    /// A reading with the given temperature, memory used in MiB and processes using the given
+    /// amounts of memory in MiB.
+    fn mock_state(temperature: u32, used_mib: u64, process_mib: &[u64]) -> SystemState {
+        let processes = process_mib
+            .iter()
+            .enumerate()
+            .map(|(pid, mib)| ProcessData::mock(pid as u32, UsedGpuMemory::Used(mib * MIB)))
+            .collect();
+        SystemState {
+            device_state: DeviceState::mock()
+                .with_temperature(temperature)
+                .with_memory(used_mib * MIB, 1000 * MIB),
+            process_state: ProcessState::from_lists(Vec::new(), processes),
+        }
+    }
+
+    #[test]
+    fn test_memory_temperature() {
+        assert_eq!(memory_temperature(SampleValue::U32(72)), Some(72));
+        assert_eq!(memory_temperature(SampleValue::U64(72)), Some(72));
+        assert_eq!(memory_temperature(SampleValue::I64(72)), Some(72));
+        assert_eq!(memory_temperature(SampleValue::F64(72.5)), Some(72));
+        assert_eq!(memory_temperature(SampleValue::U32(0)), None);
+        assert_eq!(memory_temperature(SampleValue::I64(-1)), None);
+        assert_eq!(memory_temperature(SampleValue::U64(u64::MAX)), None);
+        assert_eq!(memory_temperature(SampleValue::F64(-1.0)), None);
+        assert_eq!(memory_temperature(SampleValue::F64(f64::NAN)), None);
+        assert_eq!(memory_temperature(SampleValue::F64(f64::INFINITY)), None);
+    }
+
+    #[test]
+    fn test_cached_reads_once_per_interval() {
+        let interval = Duration::from_millis(500);
+        let start = Instant::now();
+        let mut slot = None;
+        let mut reads = 0;
+        let mut read_at = |elapsed_ms| {
+            cached(
+                &mut slot,
+                interval,
+                start + Duration::from_millis(elapsed_ms),
+                || -> Result<u32, ()> {
+                    reads += 1;
+                    Ok(reads)
+                },
+            )
+        };
+        assert_eq!(read_at(0), Ok(1));
+        assert_eq!(read_at(499), Ok(1));
+        assert_eq!(read_at(500), Ok(2));
+        assert_eq!(read_at(700), Ok(2));
+
+        let mut failing = None;
+        assert!(cached(&mut failing, interval, start, || Err::<u32, _>(())).is_err());
+        assert!(failing.is_none());
+    }
+
+    #[test]
+    fn test_mock_source_plays_back_script() {
+        let source = MockSource::new(vec![
+            Some(mock_state(40, 0, &[])),
+            None,
+            Some(mock_state(60, 0, &[])),
+        ]);
+        let temperatures: Vec<Option<u32>> = (0..4)
+            .map(|_| {
+                source
+                    .poll_device(0)
+                    .ok()
+                    .and_then(|system_state| system_state.device_state.temperature)
+            })
+            .collect();
+        assert_eq!(temperatures, vec![Some(40), None, Some(60), Some(60)]);
+        assert_eq!(source.resolve_device(&DeviceSelector::Index(0)).unwrap(), 0);
+        assert!(source.resolve_device(&DeviceSelector::Index(1)).is_err());
+    }
+
+    #[test]
+    fn test_top_processes_from_polled_state() {
+        let source = MockSource::new(vec![Some(mock_state(50, 900, &[300, 500, 100]))]);
+        let system_state = source.poll_device(0).unwrap();
+        let top: Vec<u32> = process::top_by_memory(&system_state.process_state.processes, 2)
+            .iter()
+            .map(|process| process.process_info.pid)
+            .collect();
+        assert_eq!(top, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_alerts_from_polled_states() {
+        let source = MockSource::new(vec![
+            Some(mock_state(50, 100, &[100])),
+            Some(mock_state(90, 100, &[100])),
+            None,
+            Some(mock_state(91, 950, &[300, 650])),
+            Some(mock_state(50, 100, &[100])),
+        ]);
+        let thresholds = Thresholds::default();
+        let mut monitor = AlertMonitor::default();
+        let alert_counts: Vec<usize> = (0..5)
+            .map(|_| match source.poll_device(0) {
+                Ok(system_state) => monitor
+                    .update(&thresholds, &system_state.device_state)
+                    .len(),
+                Err(_) => 0,
+            })
+            .collect();
+        // A failed poll doesn't reset the alerts, so staying hot afterwards isn't a new crossing
+        assert_eq!(alert_counts, vec![0, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn test_parse_cuda_version() {
+        let output = "\
+==============NVSMI LOG==============
+
+Timestamp                                 : Tue Oct 14 10:00:00 2025
+Driver Version                            : 535.104.05
+CUDA Version                              : 12.2
+";
+        let version = parse_cuda_version(output).unwrap();
+        assert_eq!((version.major, version.minor), (12, 2));
+        assert!(parse_cuda_version("Driver Version : 535.104.05").is_none());
+    }
+
+    #[test]
+    fn test_parse_device_state() {
+        let line = "NVIDIA GeForce RTX 3080, 535.104.05, 54, 10240, 1024, 9216, 30, 35.21, \
+                    320.00, 7, 3, P8, 0x0000000000000004, Enabled, 12, 0, 210, 210, 405, \
+                    [N/A]";
+        let device_state = parse_device_state(line, CUDA_DRIVER_VERSION).unwrap();
+        assert_eq!(device_state.name, "NVIDIA GeForce RTX 3080");
+        assert_eq!(device_state.temperature, Some(54));
+        assert_eq!(crate::device::memory_total(&device_state), 10240 * MIB);
+        assert_eq!(crate::device::memory_used(&device_state), Some(1024 * MIB));
+        assert_eq!(device_state.fan_speeds, vec![Some(30)]);
+        assert_eq!(device_state.power_usage, Some(35210));
+        assert_eq!(device_state.power_limit, Some(320000));
+        assert_eq!(
+            device_state.metrics,
+            [Some(210.0), Some(210.0), Some(405.0), None]
+        );
+        assert_eq!(
+            device_state.utilization.map(|u| (u.gpu, u.memory)),
+            Some((7, 3))
+        );
+        assert_eq!(device_state.pstate, Some(8));
+        assert_eq!(
+            device_state.throttle_reasons,
+            Some(ThrottleReasons::SW_POWER_CAP)
+        );
+        assert_eq!(device_state.display_active, Some(true));
+        assert_eq!(
+            device_state.ecc_errors,
+            Some(EccErrors {
+                corrected: 12,
+                uncorrected: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_device_state_unsupported_fields() {
+        let line = "Tesla T4, 535.104.05, [N/A], 15360, 0, 15360, [N/A], [N/A], [N/A], \
+                    [N/A], [N/A], [N/A], [N/A], [N/A], [N/A], [N/A], [N/A], [N/A], [N/A], [N/A]";
+        let device_state = parse_device_state(line, CUDA_DRIVER_VERSION).unwrap();
+        assert_eq!(device_state.temperature, None);
+        assert!(device_state.fan_speeds.is_empty());
+        assert_eq!(device_state.power_usage, None);
+        assert!(device_state.utilization.is_none());
+        assert_eq!(device_state.pstate, None);
+        assert_eq!(device_state.throttle_reasons, None);
+        assert_eq!(device_state.display_active, None);
+        assert_eq!(device_state.ecc_errors, None);
+        assert_eq!(device_state.metrics, [None; METRICS.len()]);
+
+        // A missing field means the output isn't what we asked for
+        assert!(parse_device_state("Tesla T4, 535.104.05", CUDA_DRIVER_VERSION).is_none());
+    }
+
+    #[test]
+    fn test_parse_compute_process() {
+        let process = parse_compute_process("1234, 2048, /opt/my,app/python3 train.py").unwrap();
+        assert_eq!(process.process_info.pid, 1234);
+        assert_eq!(process.used_memory(), 2048 * MIB);
+        assert_eq!(process.process_name, "python3");
+
+        let process = parse_compute_process("1234, [N/A], python3").unwrap();
+        assert!(matches!(
+            process.process_info.used_gpu_memory,
+            UsedGpuMemory::Unavailable
+        ));
+        assert!(parse_compute_process("").is_none());
+    }
+
+    #[test]
+    fn test_parse_device_ids() {
+        let ids = parse_device_ids("00000000:01:00.0, GPU-5c9c1e1a-2b7e-4f1c-9d0e-1234567890ab");
+        assert_eq!(ids.pci_bus_id.as_deref(), Some("00000000:01:00.0"));
+        assert_eq!(
+            ids.uuid.as_deref(),
+            Some("GPU-5c9c1e1a-2b7e-4f1c-9d0e-1234567890ab")
+        );
+        assert_eq!(
+            ids.describe("Tesla T4"),
+            "Tesla T4\nPCI bus id: 00000000:01:00.0\nUUID: GPU-5c9c1e1a-2b7e-4f1c-9d0e-1234567890ab"
+        );
+
+        let ids = parse_device_ids("[N/A], ");
+        assert_eq!(ids, DeviceIds::default());
+        assert!(ids.describe("Tesla T4").ends_with("UUID: unknown"));
+    }
+}
@@ -0,0 +1,428 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::json;
+use crate::source::SystemState;
+
+/// How many lines a subscriber may fall behind before new ones are dropped for it, so a
+/// tool that stops reading can't make the app buffer without bound.
+const SUBSCRIBER_BACKLOG: usize = 16;
+
+/// A Unix domain socket in a directory only the current user can read, e.g.
+/// `$XDG_RUNTIME_DIR/nvsmi-gui/stream.sock` on Linux, or a named pipe of the current user on
+/// Windows. `None` if the platform has no such directory.
+pub fn default_path() -> Option<PathBuf> {
+    #[cfg(unix)]
+    let path = {
+        let dirs = directories::ProjectDirs::from("", "", "nvsmi-gui")?;
+        let dir = dirs.runtime_dir().unwrap_or_else(|| dirs.data_local_dir());
+        dir.join("stream.sock")
+    };
+    // Pipe names are machine-wide, so every user gets one of their own
+    #[cfg(windows)]
+    let path = PathBuf::from(format!(
+        r"\\.\pipe\nvsmi-gui-{}",
+        std::env::var("USERNAME").ok()?
+    ));
+    Some(path)
+}
+
+#[derive(Default)]
+struct Shared {
+    subscribers: Vec<SyncSender<Arc<str>>>,
+    /// Sent to new subscribers first, so they don't have to wait for the next poll.
+    latest: Option<Arc<str>>,
+}
+
+/// Streams every poll as a line of JSON, shaped like the HTTP API's `/api/state`, to the
+/// local tools connected to a Unix domain socket or named pipe.
+pub struct MetricStream {
+    shared: Arc<Mutex<Shared>>,
+    #[cfg(unix)]
+    path: PathBuf,
+}
+
+impl MetricStream {
+    /// Creates the socket or pipe at `path` and starts accepting subscribers on a background
+    /// thread.
+    pub fn spawn(path: &Path) -> io::Result<Self> {
+        let mut listener = platform::Listener::bind(path)?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        thread::Builder::new()
+            .name(String::from("metric-stream"))
+            .spawn({
+                let shared = Arc::clone(&shared);
+                move || loop {
+                    match listener.accept() {
+                        Ok(writer) => subscribe(writer, &shared),
+                        Err(err) => log::debug!("Metric stream connection failed: {err}"),
+                    }
+                }
+            })?;
+        Ok(Self {
+            shared,
+            #[cfg(unix)]
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Sends a poll to every subscriber.
+    pub fn push(&self, system_state: &SystemState) {
+        self.push_line(json::state_json(Some(system_state)));
+    }
+
+    fn push_line(&self, mut line: String) {
+        line.push('\n');
+        let line: Arc<str> = Arc::from(line);
+        let mut shared = self.shared.lock().unwrap();
+        // Slow subscribers miss the line, the ones that disconnected are forgotten
+        shared.subscribers.retain(|subscriber| {
+            !matches!(
+                subscriber.try_send(Arc::clone(&line)),
+                Err(TrySendError::Disconnected(_))
+            )
+        });
+        shared.latest = Some(line);
+    }
+}
+
+#[cfg(unix)]
+impl Drop for MetricStream {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Writes the lines pushed from now on to `writer` on a thread of its own, until it fails.
+fn subscribe(mut writer: Box<dyn Write + Send>, shared: &Mutex<Shared>) {
+    let (sender, receiver) = mpsc::sync_channel::<Arc<str>>(SUBSCRIBER_BACKLOG);
+    {
+        let mut shared = shared.lock().unwrap();
+        if let Some(latest) = &shared.latest {
+            let _ = sender.try_send(Arc::clone(latest));
+        }
+        shared.subscribers.push(sender);
+    }
+    let spawned = thread::Builder::new()
+        .name(String::from("metric-stream-subscriber"))
+        .spawn(move || {
+            for line in receiver {
+                if let Err(err) = writer
+                    .write_all(line.as_bytes())
+                    .and_then(|()| writer.flush())
+                {
+                    log::debug!("Metric stream subscriber went away: {err}");
+                    break;
+                }
+            }
+        });
+    if let Err(err) = spawned {
+        log::warn!("Failed to serve a metric stream subscriber: {err}");
+    }
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::fs::{self, DirBuilder, Permissions};
+    use std::io::{self, Write};
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+
+    pub struct Listener(UnixListener);
+
+    impl Listener {
+        pub fn bind(path: &Path) -> io::Result<Self> {
+            // A socket nobody answers on is left over from an instance that crashed
+            if path.exists() {
+                if UnixStream::connect(path).is_ok() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        "another instance is streaming there",
+                    ));
+                }
+                fs::remove_file(path)?;
+            }
+            if let Some(dir) = path.parent() {
+                DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+            }
+            let listener = UnixListener::bind(path)?;
+            // The lines carry the readings of every process, which are nobody else's business
+            fs::set_permissions(path, Permissions::from_mode(0o600))?;
+            Ok(Self(listener))
+        }
+
+        pub fn accept(&mut self) -> io::Result<Box<dyn Write + Send>> {
+            let (stream, _) = self.0.accept()?;
+            Ok(Box::new(stream))
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::io::{self, Write};
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle, RawHandle};
+    use std::path::Path;
+    use std::ptr;
+
+    const PIPE_ACCESS_OUTBOUND: u32 = 0x0000_0002;
+    const FILE_FLAG_FIRST_PIPE_INSTANCE: u32 = 0x0008_0000;
+    const PIPE_TYPE_BYTE: u32 = 0x0000_0000;
+    const PIPE_WAIT: u32 = 0x0000_0000;
+    const PIPE_REJECT_REMOTE_CLIENTS: u32 = 0x0000_0008;
+    const PIPE_UNLIMITED_INSTANCES: u32 = 255;
+    const BUFFER_SIZE: u32 = 4096;
+    const ERROR_PIPE_CONNECTED: i32 = 535;
+    const TOKEN_QUERY: u32 = 0x0008;
+    /// The `TokenUser` member of `TOKEN_INFORMATION_CLASS`.
+    const TOKEN_USER: i32 = 1;
+    const SDDL_REVISION_1: u32 = 1;
+
+    #[repr(C)]
+    struct SecurityAttributes {
+        length: u32,
+        security_descriptor: *mut c_void,
+        inherit_handle: i32,
+    }
+
+    /// `TOKEN_USER`, which only holds a `SID_AND_ATTRIBUTES`.
+    #[repr(C)]
+    struct TokenUser {
+        sid: *mut c_void,
+        attributes: u32,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CreateNamedPipeW(
+            name: *const u16,
+            open_mode: u32,
+            pipe_mode: u32,
+            max_instances: u32,
+            out_buffer_size: u32,
+            in_buffer_size: u32,
+            default_timeout: u32,
+            security_attributes: *mut SecurityAttributes,
+        ) -> RawHandle;
+        fn ConnectNamedPipe(pipe: RawHandle, overlapped: *mut c_void) -> i32;
+        fn GetCurrentProcess() -> RawHandle;
+        fn LocalFree(memory: *mut c_void) -> *mut c_void;
+    }
+
+    #[link(name = "advapi32")]
+    extern "system" {
+        fn OpenProcessToken(process: RawHandle, desired_access: u32, token: *mut RawHandle) -> i32;
+        fn GetTokenInformation(
+            token: RawHandle,
+            information_class: i32,
+            information: *mut c_void,
+            length: u32,
+            return_length: *mut u32,
+        ) -> i32;
+        fn ConvertSidToStringSidW(sid: *mut c_void, string_sid: *mut *mut u16) -> i32;
+        fn ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            string_security_descriptor: *const u16,
+            revision: u32,
+            security_descriptor: *mut *mut c_void,
+            size: *mut u32,
+        ) -> i32;
+    }
+
+    /// A security descriptor that only lets the current user open the pipe. The default one
+    /// lets everyone read it.
+    struct SecurityDescriptor(*mut c_void);
+
+    // SAFETY: the descriptor is only read after it is created, and freed once
+    unsafe impl Send for SecurityDescriptor {}
+
+    impl SecurityDescriptor {
+        fn current_user_only() -> io::Result<Self> {
+            // Protected, so nothing is inherited, and full access for the user alone
+            let sddl: Vec<u16> = format!("D:P(A;;GA;;;{})", current_user_sid()?)
+                .encode_utf16()
+                .chain([0])
+                .collect();
+            let mut descriptor = ptr::null_mut();
+            // SAFETY: `sddl` is nul-terminated and the size may be null
+            let converted = unsafe {
+                ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                    sddl.as_ptr(),
+                    SDDL_REVISION_1,
+                    &mut descriptor,
+                    ptr::null_mut(),
+                )
+            };
+            if converted == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self(descriptor))
+        }
+    }
+
+    impl Drop for SecurityDescriptor {
+        fn drop(&mut self) {
+            // SAFETY: the descriptor was allocated by
+            // ConvertStringSecurityDescriptorToSecurityDescriptorW and isn't used anymore
+            unsafe { LocalFree(self.0) };
+        }
+    }
+
+    /// The SID of the user running the app, e.g. `S-1-5-21-...`.
+    fn current_user_sid() -> io::Result<String> {
+        let mut token = ptr::null_mut();
+        // SAFETY: the pseudo handle of the current process is always valid
+        if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the token was just opened and nothing else owns it
+        let token = unsafe { OwnedHandle::from_raw_handle(token) };
+
+        // The first call fails, but tells how big the information is
+        let mut length = 0;
+        // SAFETY: a null buffer of length zero only asks for the length
+        unsafe {
+            GetTokenInformation(
+                token.as_raw_handle(),
+                TOKEN_USER,
+                ptr::null_mut(),
+                0,
+                &mut length,
+            )
+        };
+        // `u64`s keep the pointer in `TokenUser` aligned
+        let mut buffer = vec![0u64; (length as usize).div_ceil(8)];
+        // SAFETY: the buffer holds at least `length` bytes
+        let queried = unsafe {
+            GetTokenInformation(
+                token.as_raw_handle(),
+                TOKEN_USER,
+                buffer.as_mut_ptr().cast(),
+                length,
+                &mut length,
+            )
+        };
+        if queried == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the buffer starts with a `TOKEN_USER` whose SID points into the buffer
+        let sid = unsafe { (*buffer.as_ptr().cast::<TokenUser>()).sid };
+
+        let mut string_sid = ptr::null_mut();
+        // SAFETY: `sid` is valid while `buffer` is alive
+        if unsafe { ConvertSidToStringSidW(sid, &mut string_sid) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the string is nul-terminated and freed right after copying it
+        let sid = unsafe {
+            let length = (0..).take_while(|&i| *string_sid.add(i) != 0).count();
+            let sid = String::from_utf16_lossy(std::slice::from_raw_parts(string_sid, length));
+            LocalFree(string_sid.cast());
+            sid
+        };
+        Ok(sid)
+    }
+
+    /// Every subscriber gets a pipe instance of its own, the next one is created up front.
+    pub struct Listener {
+        name: Vec<u16>,
+        security: SecurityDescriptor,
+        next: Option<File>,
+    }
+
+    impl Listener {
+        pub fn bind(path: &Path) -> io::Result<Self> {
+            let name: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+            let security = SecurityDescriptor::current_user_only()?;
+            // Fails if another instance, or another user, already owns the name
+            let next = Some(create_pipe(
+                &name,
+                &security,
+                FILE_FLAG_FIRST_PIPE_INSTANCE,
+            )?);
+            Ok(Self {
+                name,
+                security,
+                next,
+            })
+        }
+
+        pub fn accept(&mut self) -> io::Result<Box<dyn Write + Send>> {
+            let pipe = match self.next.take() {
+                Some(pipe) => pipe,
+                None => create_pipe(&self.name, &self.security, 0)?,
+            };
+            // SAFETY: the handle is a valid pipe owned by `pipe`
+            let connected = unsafe { ConnectNamedPipe(pipe.as_raw_handle(), ptr::null_mut()) };
+            if connected == 0 {
+                let err = io::Error::last_os_error();
+                // The client connected between creating the pipe and waiting for it
+                if err.raw_os_error() != Some(ERROR_PIPE_CONNECTED) {
+                    return Err(err);
+                }
+            }
+            Ok(Box::new(pipe))
+        }
+    }
+
+    fn create_pipe(name: &[u16], security: &SecurityDescriptor, flags: u32) -> io::Result<File> {
+        let mut attributes = SecurityAttributes {
+            length: std::mem::size_of::<SecurityAttributes>() as u32,
+            security_descriptor: security.0,
+            inherit_handle: 0,
+        };
+        // SAFETY: `name` is nul-terminated and `attributes` outlives the call
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                PIPE_ACCESS_OUTBOUND | flags,
+                PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                BUFFER_SIZE,
+                0,
+                0,
+                &mut attributes,
+            )
+        };
+        // INVALID_HANDLE_VALUE
+        if handle as isize == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the handle was just created and nothing else owns it
+        Ok(unsafe { File::from_raw_handle(handle) })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixStream;
+
+    use super::*;
+
+    #[test]
+    fn test_streams_lines() {
+        let path = std::env::temp_dir().join(format!("nvsmi-gui-test-{}.sock", std::process::id()));
+        let stream = MetricStream::spawn(&path).unwrap();
+        assert!(MetricStream::spawn(&path).is_err());
+
+        stream.push_line(String::from(r#"{"poll":1}"#));
+        let mut reader = BufReader::new(UnixStream::connect(&path).unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "{\"poll\":1}\n");
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        drop(stream);
+        assert!(!path.exists());
+    }
+}
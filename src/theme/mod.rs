@@ -0,0 +1,144 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use eframe::egui::{Color32, Stroke, Visuals};
+
+static HIGH_CONTRAST: AtomicBool = AtomicBool::new(false);
+
+/// Switches the plot colors from [`Series::color`] to the high-contrast palette.
+pub fn set_high_contrast(high_contrast: bool) {
+    HIGH_CONTRAST.store(high_contrast, Ordering::Relaxed);
+}
+
+pub fn high_contrast() -> bool {
+    HIGH_CONTRAST.load(Ordering::Relaxed)
+}
+
+/// The default dark or light visuals, optionally made high-contrast: pure black on white or
+/// white on black, with thick borders.
+///
+/// The bundled fonts have no bold weight, so all text is drawn in the strongest color
+/// instead.
+pub fn visuals(dark_mode: bool, high_contrast: bool) -> Visuals {
+    let mut visuals = if dark_mode {
+        Visuals::dark()
+    } else {
+        Visuals::light()
+    };
+    if !high_contrast {
+        return visuals;
+    }
+
+    let (text, background, faint) = if dark_mode {
+        (Color32::WHITE, Color32::BLACK, Color32::from_gray(45))
+    } else {
+        (Color32::BLACK, Color32::WHITE, Color32::from_gray(220))
+    };
+    visuals.override_text_color = Some(text);
+    visuals.panel_fill = background;
+    visuals.window_fill = background;
+    visuals.extreme_bg_color = background;
+    visuals.faint_bg_color = faint;
+    visuals.window_stroke = Stroke::new(2.0, text);
+    visuals.selection.stroke = Stroke::new(2.0, text);
+    let widgets = &mut visuals.widgets;
+    for widget in [
+        &mut widgets.noninteractive,
+        &mut widgets.inactive,
+        &mut widgets.hovered,
+        &mut widgets.active,
+        &mut widgets.open,
+    ] {
+        widget.bg_stroke = Stroke::new(2.0, text);
+        widget.fg_stroke = Stroke::new(2.0, text);
+    }
+    widgets.noninteractive.bg_stroke = Stroke::new(1.5, text);
+    visuals
+}
+
+/// The plotted series, each with its own color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Series {
+    Temperature,
+    MemoryTemperature,
+    Memory,
+    Power,
+    GpuUtilization,
+    MemoryUtilization,
+    Efficiency,
+    Clock,
+}
+
+impl Series {
+    /// The color of the series on a dark or light background. The high-contrast palette
+    /// keeps a contrast ratio of at least 3:1 to the background, as WCAG asks of graphics.
+    pub fn color(self, dark_mode: bool) -> Color32 {
+        if !high_contrast() {
+            return match self {
+                Series::Temperature => Color32::from_rgb(168, 68, 13),
+                Series::MemoryTemperature => Color32::from_rgb(196, 50, 120),
+                Series::Memory => Color32::from_rgb(95, 118, 156),
+                Series::Power => Color32::from_rgb(207, 184, 54),
+                Series::GpuUtilization => Color32::from_rgb(90, 140, 230),
+                Series::MemoryUtilization => Color32::from_rgb(200, 110, 220),
+                Series::Efficiency => Color32::from_rgb(70, 160, 110),
+                Series::Clock => Color32::from_rgb(120, 170, 190),
+            };
+        }
+        // Based on the Okabe-Ito palette, which also stays apart for color blind users
+        match (self, dark_mode) {
+            (Series::Temperature, true) => Color32::from_rgb(255, 110, 30),
+            (Series::Temperature, false) => Color32::from_rgb(190, 70, 0),
+            (Series::MemoryTemperature, true) => Color32::from_rgb(230, 140, 190),
+            (Series::MemoryTemperature, false) => Color32::from_rgb(150, 40, 110),
+            (Series::Memory | Series::GpuUtilization, true) => Color32::from_rgb(86, 180, 233),
+            (Series::Memory | Series::GpuUtilization, false) => Color32::from_rgb(0, 100, 170),
+            (Series::Power, true) => Color32::from_rgb(240, 228, 66),
+            (Series::Power, false) => Color32::from_rgb(140, 95, 0),
+            (Series::MemoryUtilization, true) => Color32::from_rgb(230, 140, 190),
+            (Series::MemoryUtilization, false) => Color32::from_rgb(150, 40, 110),
+            (Series::Efficiency, true) => Color32::from_rgb(0, 200, 150),
+            (Series::Efficiency, false) => Color32::from_rgb(0, 120, 85),
+            (Series::Clock, true) => Color32::from_rgb(200, 200, 200),
+            (Series::Clock, false) => Color32::from_rgb(80, 80, 80),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The WCAG contrast ratio between two opaque colors.
+    fn contrast_ratio(a: Color32, b: Color32) -> f32 {
+        let luminance = |color: Color32| {
+            let linear = eframe::egui::Rgba::from(color);
+            0.2126 * linear.r() + 0.7152 * linear.g() + 0.0722 * linear.b()
+        };
+        let (a, b) = (luminance(a), luminance(b));
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    #[test]
+    fn test_high_contrast_palette() {
+        set_high_contrast(true);
+        for dark_mode in [true, false] {
+            let background = visuals(dark_mode, true).panel_fill;
+            for series in [
+                Series::Temperature,
+                Series::MemoryTemperature,
+                Series::Memory,
+                Series::Power,
+                Series::GpuUtilization,
+                Series::MemoryUtilization,
+                Series::Efficiency,
+                Series::Clock,
+            ] {
+                let ratio = contrast_ratio(series.color(dark_mode), background);
+                assert!(ratio >= 3.0, "{series:?} in dark mode {dark_mode}: {ratio}");
+            }
+            let text = visuals(dark_mode, true).override_text_color.unwrap();
+            assert!(contrast_ratio(text, background) >= 7.0);
+        }
+        set_high_contrast(false);
+    }
+}
@@ -0,0 +1,45 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+/// How long a toast stays on screen.
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+/// Short-lived notifications stacked in the top right corner of the window.
+#[derive(Debug, Clone, Default)]
+pub struct Toasts {
+    toasts: Vec<(String, Instant)>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, message: String) {
+        self.toasts.push((message, Instant::now()));
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.toasts
+            .retain(|(_, shown_at)| shown_at.elapsed() < TOAST_DURATION);
+        let Some(oldest_remaining) = self
+            .toasts
+            .first()
+            .map(|(_, shown_at)| TOAST_DURATION.saturating_sub(shown_at.elapsed()))
+        else {
+            return;
+        };
+
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_TOP, [-10.0, 40.0])
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                for (message, _) in &self.toasts {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(message);
+                    });
+                }
+            });
+
+        // Make sure the toast disappears even if nothing else triggers a repaint
+        ctx.request_repaint_after(oldest_remaining);
+    }
+}
@@ -0,0 +1,128 @@
+use std::error::Error;
+#[cfg(target_os = "linux")]
+use std::sync::mpsc::Sender;
+use std::sync::mpsc::{self, Receiver};
+
+use eframe::egui;
+
+use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+
+const SHOW_HIDE_ID: &str = "show-hide";
+const QUIT_ID: &str = "quit";
+const ICON_SIZE: u32 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayCommand {
+    ToggleWindow,
+    Quit,
+}
+
+pub struct Tray {
+    command_receiver: Receiver<TrayCommand>,
+    last_tooltip: String,
+    // On Linux the tray icon lives on its own GTK thread, so tooltip updates are sent over
+    // to it. Everywhere else the icon has to be owned by the main thread.
+    #[cfg(target_os = "linux")]
+    tooltip_sender: Sender<String>,
+    #[cfg(not(target_os = "linux"))]
+    tray_icon: TrayIcon,
+}
+
+impl Tray {
+    pub fn new(ctx: &egui::Context) -> Result<Self, Box<dyn Error>> {
+        let (command_sender, command_receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+
+        // The window may be hidden when a menu item is clicked, so wake up the UI
+        // explicitly instead of waiting for the next repaint.
+        MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+            let command = if event.id == SHOW_HIDE_ID {
+                TrayCommand::ToggleWindow
+            } else if event.id == QUIT_ID {
+                TrayCommand::Quit
+            } else {
+                return;
+            };
+            let _ = command_sender.send(command);
+            ctx.request_repaint();
+        }));
+
+        #[cfg(target_os = "linux")]
+        {
+            let (tooltip_sender, tooltip_receiver) = mpsc::channel::<String>();
+            std::thread::spawn(move || {
+                if let Err(err) = gtk::init() {
+                    log::error!("Failed to initialize GTK for the tray icon: {err}");
+                    return;
+                }
+                let tray_icon = match build_tray_icon() {
+                    Ok(tray_icon) => tray_icon,
+                    Err(err) => {
+                        log::error!("Failed to create tray icon: {err}");
+                        return;
+                    }
+                };
+                gtk::glib::timeout_add_local(std::time::Duration::from_millis(250), move || {
+                    while let Ok(tooltip) = tooltip_receiver.try_recv() {
+                        let _ = tray_icon.set_tooltip(Some(tooltip));
+                    }
+                    gtk::glib::ControlFlow::Continue
+                });
+                gtk::main();
+            });
+
+            Ok(Self {
+                command_receiver,
+                last_tooltip: String::new(),
+                tooltip_sender,
+            })
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(Self {
+                command_receiver,
+                last_tooltip: String::new(),
+                tray_icon: build_tray_icon()?,
+            })
+        }
+    }
+
+    pub fn set_tooltip(&mut self, tooltip: String) {
+        if tooltip == self.last_tooltip {
+            return;
+        }
+
+        #[cfg(target_os = "linux")]
+        let _ = self.tooltip_sender.send(tooltip.clone());
+        #[cfg(not(target_os = "linux"))]
+        let _ = self.tray_icon.set_tooltip(Some(&tooltip));
+
+        self.last_tooltip = tooltip;
+    }
+
+    pub fn try_recv_command(&self) -> Option<TrayCommand> {
+        self.command_receiver.try_recv().ok()
+    }
+}
+
+fn build_tray_icon() -> Result<TrayIcon, Box<dyn Error>> {
+    let show_hide = MenuItem::with_id(SHOW_HIDE_ID, "Show/Hide", true, None);
+    let quit = MenuItem::with_id(QUIT_ID, "Quit", true, None);
+    let menu = Menu::with_items(&[&show_hide, &PredefinedMenuItem::separator(), &quit])?;
+
+    let tray_icon = TrayIconBuilder::new()
+        .with_menu(Box::new(menu))
+        .with_icon(build_icon())
+        .with_tooltip("nvsmi-gui")
+        .build()?;
+
+    Ok(tray_icon)
+}
+
+/// A plain filled square in NVIDIA green, so that we don't have to ship an image asset.
+fn build_icon() -> Icon {
+    let rgba = [118, 185, 0, 255].repeat((ICON_SIZE * ICON_SIZE) as usize);
+    Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE).expect("icon dimensions match the buffer")
+}
@@ -0,0 +1,232 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+pub const KB: u64 = 1000;
+pub const MB: u64 = 1000 * KB;
+pub const GB: u64 = 1000 * MB;
+pub const KIB: u64 = 1024;
+pub const MIB: u64 = 1024 * KIB;
+pub const GIB: u64 = 1024 * MIB;
+
+/// How memory amounts are displayed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnitSystem {
+    Bytes,
+    Megabytes,
+    #[default]
+    Mebibytes,
+    Gigabytes,
+    Gibibytes,
+    /// Picks kB, MB or GB depending on the size of the value.
+    AutoDecimal,
+    /// Picks KiB, MiB or GiB depending on the size of the value.
+    AutoBinary,
+}
+
+impl UnitSystem {
+    pub const ALL: [UnitSystem; 7] = [
+        UnitSystem::Bytes,
+        UnitSystem::Megabytes,
+        UnitSystem::Mebibytes,
+        UnitSystem::Gigabytes,
+        UnitSystem::Gibibytes,
+        UnitSystem::AutoDecimal,
+        UnitSystem::AutoBinary,
+    ];
+
+    /// The unit to show `reference` bytes in. Pass the same reference for values that should
+    /// share a unit, like the points of a plot.
+    pub fn unit_for(self, reference: u64) -> Unit {
+        match self {
+            UnitSystem::Bytes => Unit::BYTES,
+            UnitSystem::Megabytes => Unit::MB,
+            UnitSystem::Mebibytes => Unit::MIB,
+            UnitSystem::Gigabytes => Unit::GB,
+            UnitSystem::Gibibytes => Unit::GIB,
+            UnitSystem::AutoDecimal => [Unit::GB, Unit::MB, Unit::KB]
+                .into_iter()
+                .find(|unit| reference >= unit.divisor)
+                .unwrap_or(Unit::BYTES),
+            UnitSystem::AutoBinary => [Unit::GIB, Unit::MIB, Unit::KIB]
+                .into_iter()
+                .find(|unit| reference >= unit.divisor)
+                .unwrap_or(Unit::BYTES),
+        }
+    }
+}
+
+impl Display for UnitSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            UnitSystem::Bytes => "Bytes",
+            UnitSystem::Megabytes => "MB",
+            UnitSystem::Mebibytes => "MiB",
+            UnitSystem::Gigabytes => "GB",
+            UnitSystem::Gibibytes => "GiB",
+            UnitSystem::AutoDecimal => "Auto (kB, MB, GB)",
+            UnitSystem::AutoBinary => "Auto (KiB, MiB, GiB)",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// How the digits of large numbers are grouped, and which character separates the decimals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumberFormat {
+    Plain,
+    #[default]
+    Comma,
+    Period,
+    Space,
+}
+
+impl NumberFormat {
+    pub const ALL: [NumberFormat; 4] = [
+        NumberFormat::Plain,
+        NumberFormat::Comma,
+        NumberFormat::Period,
+        NumberFormat::Space,
+    ];
+
+    /// The thousands separator, if any, and the decimal separator.
+    fn separators(self) -> (Option<char>, char) {
+        match self {
+            NumberFormat::Plain => (None, '.'),
+            NumberFormat::Comma => (Some(','), '.'),
+            NumberFormat::Period => (Some('.'), ','),
+            NumberFormat::Space => (Some('\u{202f}'), ','),
+        }
+    }
+
+    /// Formats `value` with `decimals` digits after the decimal separator.
+    pub fn format(self, value: f64, decimals: usize) -> String {
+        let formatted = format!("{value:.decimals$}");
+        let (sign, digits) = match formatted.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", formatted.as_str()),
+        };
+        let (integer, fraction) = match digits.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (digits, None),
+        };
+
+        let (thousands_separator, decimal_separator) = self.separators();
+        let mut result = String::from(sign);
+        for (position, digit) in integer.chars().enumerate() {
+            let remaining = integer.len() - position;
+            if position > 0 && remaining % 3 == 0 {
+                result.extend(thousands_separator);
+            }
+            result.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            result.push(decimal_separator);
+            result.push_str(fraction);
+        }
+        result
+    }
+}
+
+impl Display for NumberFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format(12288.5, 1))
+    }
+}
+
+static NUMBER_FORMAT: AtomicU8 = AtomicU8::new(NumberFormat::Comma as u8);
+
+/// Sets the format used by [`format_number`] and everything built on it.
+pub fn set_number_format(format: NumberFormat) {
+    NUMBER_FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+pub fn number_format() -> NumberFormat {
+    NumberFormat::ALL[NUMBER_FORMAT.load(Ordering::Relaxed) as usize]
+}
+
+/// Formats a whole number in the current number format, e.g. "12,288".
+pub fn format_number(value: impl Into<u64>) -> String {
+    number_format().format(value.into() as f64, 0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unit {
+    /// Number of bytes in one of this unit.
+    pub divisor: u64,
+    pub label: &'static str,
+    decimals: usize,
+}
+
+impl Unit {
+    const BYTES: Unit = Unit::new(1, "B", 0);
+    const KB: Unit = Unit::new(KB, "kB", 0);
+    const MB: Unit = Unit::new(MB, "MB", 0);
+    const GB: Unit = Unit::new(GB, "GB", 2);
+    const KIB: Unit = Unit::new(KIB, "KiB", 0);
+    const MIB: Unit = Unit::new(MIB, "MiB", 0);
+    const GIB: Unit = Unit::new(GIB, "GiB", 2);
+
+    const fn new(divisor: u64, label: &'static str, decimals: usize) -> Self {
+        Self {
+            divisor,
+            label,
+            decimals,
+        }
+    }
+
+    /// Converts `bytes` to this unit.
+    pub fn convert(&self, bytes: f64) -> f64 {
+        bytes / self.divisor as f64
+    }
+
+    pub fn format(&self, bytes: u64) -> String {
+        format!(
+            "{} {}",
+            number_format().format(self.convert(bytes as f64), self.decimals),
+            self.label
+        )
+    }
+}
+
+pub fn format_bytes(value: u64, unit_system: UnitSystem) -> String {
+    unit_system.unit_for(value).format(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        let value = 8 * GIB;
+        assert_eq!(format_bytes(value, UnitSystem::Bytes), "8,589,934,592 B");
+        assert_eq!(format_bytes(value, UnitSystem::Megabytes), "8,590 MB");
+        assert_eq!(format_bytes(value, UnitSystem::Mebibytes), "8,192 MiB");
+        assert_eq!(format_bytes(value, UnitSystem::Gigabytes), "8.59 GB");
+        assert_eq!(format_bytes(value, UnitSystem::Gibibytes), "8.00 GiB");
+        assert_eq!(format_bytes(value, UnitSystem::AutoDecimal), "8.59 GB");
+        assert_eq!(format_bytes(value, UnitSystem::AutoBinary), "8.00 GiB");
+    }
+
+    #[test]
+    fn test_number_format() {
+        assert_eq!(NumberFormat::Plain.format(12288.0, 0), "12288");
+        assert_eq!(NumberFormat::Comma.format(12288.0, 0), "12,288");
+        assert_eq!(NumberFormat::Comma.format(1234567.891, 2), "1,234,567.89");
+        assert_eq!(NumberFormat::Period.format(1234567.891, 2), "1.234.567,89");
+        assert_eq!(NumberFormat::Space.format(9500.0, 0), "9\u{202f}500");
+        assert_eq!(NumberFormat::Comma.format(999.0, 0), "999");
+        assert_eq!(NumberFormat::Comma.format(-1500.5, 1), "-1,500.5");
+        assert_eq!(NumberFormat::Comma.format(0.25, 2), "0.25");
+    }
+
+    #[test]
+    fn test_auto_scaling() {
+        assert_eq!(format_bytes(512, UnitSystem::AutoBinary), "512 B");
+        assert_eq!(format_bytes(300 * MIB, UnitSystem::AutoBinary), "300 MiB");
+        assert_eq!(format_bytes(1_500, UnitSystem::AutoDecimal), "2 kB");
+        assert_eq!(format_bytes(999 * MB, UnitSystem::AutoDecimal), "999 MB");
+    }
+}